@@ -1,38 +1,346 @@
 use std::{
-    fs::File,
-    io::{Read, Write},
-    path::PathBuf,
-    str::FromStr,
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, ExitCode},
 };
 
-use parsely_gen::module::Module;
-use parsely_lexer::Lexer;
-use parsely_parser::ParseStream;
+use clap::{Parser, ValueEnum};
+use parsely_gen::{c::CModule, module::Module, Diagnostic, DiagnosticFmt};
+use parsely_lexer::{tokens::Token, FileId, Lexer, SourceDatabase};
+use parsely_parser::{item::Program, ParseStream};
 
-fn main() {
-    let path = PathBuf::from_str("examples/test.par").unwrap();
+/// The parsely compiler
+#[derive(Debug, Parser)]
+#[command(name = "parsely", version, about)]
+struct Args {
+    /// Source files to compile
+    #[arg(required = true)]
+    inputs: Vec<PathBuf>,
 
-    let mut file = File::open(&path).unwrap();
+    /// Output path. If several artifacts are emitted, each one is written to this path with its
+    /// own extension. With several inputs, only an executable can be emitted to it.
+    #[arg(short, long)]
+    output: Option<PathBuf>,
 
-    let mut buffer = String::with_capacity(256);
-    file.read_to_string(&mut buffer).unwrap();
+    /// Comma separated list of artifacts to emit
+    #[arg(long, value_enum, value_delimiter = ',', default_value = "exe")]
+    emit: Vec<Emit>,
+}
 
-    let tokens = Lexer::run(buffer.as_bytes());
-    println!("{:#?}", tokens);
-    let stream = ParseStream::from(&tokens);
+/// Kind of output produced by the compiler
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Emit {
+    /// Token tree of each input, printed to stdout
+    Tokens,
+    /// Syntax tree of each input, printed to stdout
+    Ast,
+    /// C header
+    CHeader,
+    /// C source
+    CSource,
+    /// Textual LLVM IR
+    LlvmIr,
+    /// Native object file
+    Obj,
+    /// Linked executable
+    Exe,
+}
+
+impl Emit {
+    fn extension(self) -> &'static str {
+        match self {
+            Emit::Tokens | Emit::Ast => "",
+            Emit::CHeader => "h",
+            Emit::CSource => "c",
+            Emit::LlvmIr => "ll",
+            Emit::Obj => "o",
+            Emit::Exe => std::env::consts::EXE_EXTENSION,
+        }
+    }
+
+    /// Whether each input gets its own file of this kind
+    fn is_per_input(self) -> bool {
+        matches!(
+            self,
+            Emit::CHeader | Emit::CSource | Emit::LlvmIr | Emit::Obj
+        )
+    }
+}
+
+/// Marker for a failed step. The diagnostics have already been printed when this is returned.
+struct Failed;
+
+type Result<T> = std::result::Result<T, Failed>;
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+
+    // The artifacts of every input would be written to the same path
+    if args.output.is_some()
+        && args.inputs.len() > 1
+        && args.emit.iter().any(|emit| emit.is_per_input())
+    {
+        error("`--output` can't be used with several inputs, unless only `exe` is emitted".into());
+        return ExitCode::FAILURE;
+    }
+
+    let mut source = SourceDatabase::new();
+    let mut files = Vec::new();
+    let mut failed = false;
+
+    for input in &args.inputs {
+        match source.load(input) {
+            Ok(file) => files.push(file),
+            Err(e) => {
+                error(format!("unable to read `{}`: {}", input.display(), e));
+                failed = true;
+            }
+        }
+    }
+
+    if failed {
+        return ExitCode::FAILURE;
+    }
+
+    let mut objects = Vec::new();
 
-    let program = stream.parse().unwrap();
-    println!("{:#?}", program);
+    for (index, file) in files.into_iter().enumerate() {
+        match compile(&args, &source, file, index) {
+            Ok(Some(object)) => objects.push(object),
+            Ok(None) => (),
+            Err(Failed) => failed = true,
+        }
+    }
 
-    let module = Module::new(path.file_stem().unwrap().to_string_lossy().as_ref());
-    let (header, code) = module.run(&program).unwrap();
+    if !failed && args.emit.contains(&Emit::Exe) {
+        failed = link(&args, &objects).is_err();
+    }
 
-    let header_path = path.with_extension("h");
-    let code_path = path.with_extension("c");
+    // Objects are only kept around for linking if they weren't requested
+    if !args.emit.contains(&Emit::Obj) {
+        for object in &objects {
+            let _ = fs::remove_file(object);
+        }
+    }
+
+    if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+/// Compile a single input file, writing out every requested artifact.
+///
+/// Returns the path of the object file if one is needed for linking. `index` is the position
+/// of the input on the command line, which keeps temporary objects of inputs with the same name
+/// apart.
+fn compile(
+    args: &Args,
+    source: &SourceDatabase,
+    file: FileId,
+    index: usize,
+) -> Result<Option<PathBuf>> {
+    let input = source.path(file);
+
+    let (tokens, lex_errors) = Lexer::run_source(source, file);
+    for e in &lex_errors {
+        eprint!("{}", e.to_report().display(source));
+    }
+
+    if args.emit.contains(&Emit::Tokens) {
+        print_tokens(input, &tokens);
+    }
+
+    let stream = ParseStream::with_source(&tokens, source);
+    let (program, parse_errors) = Program::parse_all(&stream);
+    for e in &parse_errors {
+        eprint!("{}", e.to_report().display(source));
+    }
+
+    if args.emit.contains(&Emit::Ast) {
+        println!("{:#?}", program);
+    }
+
+    if !lex_errors.is_empty() || !parse_errors.is_empty() {
+        return Err(Failed);
+    }
+
+    if !args
+        .emit
+        .iter()
+        .any(|emit| !matches!(emit, Emit::Tokens | Emit::Ast))
+    {
+        return Ok(None);
+    }
+
+    let name = input
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "main".to_string());
+
+    // Each backend checks the program on its own, so C is written even if LLVM fails
+    let c = if args.emit.contains(&Emit::CHeader) || args.emit.contains(&Emit::CSource) {
+        emit_c(args, source, &program, input, &name)
+    } else {
+        Ok(())
+    };
+
+    let object = if args
+        .emit
+        .iter()
+        .any(|emit| matches!(emit, Emit::LlvmIr | Emit::Obj | Emit::Exe))
+    {
+        emit_llvm(args, source, &program, input, &name, index)
+    } else {
+        Ok(None)
+    };
+
+    match (c, object) {
+        (Ok(()), object) => object,
+        // Nothing gets linked, so a temporary object isn't needed anymore
+        (Err(Failed), Ok(Some(object))) => {
+            if !args.emit.contains(&Emit::Obj) {
+                let _ = fs::remove_file(object);
+            }
+            Err(Failed)
+        }
+        (Err(Failed), _) => Err(Failed),
+    }
+}
 
-    let mut header_file = File::create(header_path).unwrap();
-    let mut code_file = File::create(code_path).unwrap();
+/// Writes the requested C header and source of a program
+fn emit_c(
+    args: &Args,
+    source: &SourceDatabase,
+    program: &Program,
+    input: &Path,
+    name: &str,
+) -> Result<()> {
+    let mut c = CModule::new(name, source);
+    let result = c.run(program);
+    eprint!("{}", c.format_diagnostics());
+    let (header, code) = check(result, c.has_errors(), source)?;
+
+    if args.emit.contains(&Emit::CHeader) {
+        write(&output_path(args, input, Emit::CHeader), header)?;
+    }
+    if args.emit.contains(&Emit::CSource) {
+        write(&output_path(args, input, Emit::CSource), code)?;
+    }
+
+    Ok(())
+}
+
+/// Writes the requested LLVM IR and object file of a program. Returns the path of the object
+/// file if one is needed for linking.
+fn emit_llvm(
+    args: &Args,
+    source: &SourceDatabase,
+    program: &Program,
+    input: &Path,
+    name: &str,
+    index: usize,
+) -> Result<Option<PathBuf>> {
+    let context = inkwell::context::Context::create();
+    let mut module = Module::new(name, &context, source);
+
+    let result = module.run(program);
+    eprint!("{}", module.format_diagnostics());
+    check(result, module.has_errors(), source)?;
+
+    if args.emit.contains(&Emit::LlvmIr) {
+        write(&output_path(args, input, Emit::LlvmIr), module.llvm_ir())?;
+    }
+
+    if args.emit.contains(&Emit::Obj) || args.emit.contains(&Emit::Exe) {
+        module
+            .verify()
+            .map_err(|e| error(format!("invalid LLVM IR for `{}`: {}", input.display(), e)))?;
+
+        let path = if args.emit.contains(&Emit::Obj) {
+            output_path(args, input, Emit::Obj)
+        } else {
+            std::env::temp_dir().join(format!("{}-{}-{}.o", name, std::process::id(), index))
+        };
+
+        module
+            .write_object_file(&path)
+            .map_err(|e| error(format!("unable to write `{}`: {}", path.display(), e)))?;
+
+        return Ok(Some(path));
+    }
+
+    Ok(None)
+}
+
+/// Gets the output of a backend. Its diagnostics have been printed already, apart from an error
+/// it stopped at.
+fn check<T>(
+    result: std::result::Result<T, Diagnostic>,
+    has_errors: bool,
+    source: &SourceDatabase,
+) -> Result<T> {
+    match result {
+        Err(Diagnostic::Caught(_)) => Err(Failed),
+        Err(diag) => {
+            eprint!("{}", DiagnosticFmt(&[diag], source));
+            Err(Failed)
+        }
+        Ok(_) if has_errors => Err(Failed),
+        Ok(output) => Ok(output),
+    }
+}
+
+/// Link all of the object files into an executable using the system C compiler
+fn link(args: &Args, objects: &[PathBuf]) -> Result<()> {
+    let output = output_path(args, &args.inputs[0], Emit::Exe);
+    let cc = std::env::var("CC").unwrap_or_else(|_| "cc".to_string());
+
+    let status = Command::new(&cc)
+        .args(objects)
+        .arg("-o")
+        .arg(&output)
+        .status()
+        .map_err(|e| error(format!("unable to run linker `{}`: {}", cc, e)))?;
+
+    if !status.success() {
+        return Err(error(format!("linker `{}` exited with {}", cc, status)));
+    }
+
+    Ok(())
+}
+
+/// Path of the artifact `emit` for `input`.
+///
+/// `--output` is used as is when it names a single artifact, otherwise the artifact is placed
+/// next to the output path, or the input without one, with the extension of its kind. `main`
+/// makes sure `--output` is only given for several inputs when nothing per input is written.
+fn output_path(args: &Args, input: &Path, emit: Emit) -> PathBuf {
+    let files = args
+        .emit
+        .iter()
+        .filter(|emit| !matches!(emit, Emit::Tokens | Emit::Ast))
+        .count();
+
+    match &args.output {
+        Some(output) if files == 1 => output.clone(),
+        Some(output) => output.with_extension(emit.extension()),
+        None => input.with_extension(emit.extension()),
+    }
+}
+
+fn print_tokens(input: &Path, tokens: &[Token]) {
+    println!("// {}", input.display());
+    println!("{:#?}", tokens);
+}
+
+fn write(path: &Path, contents: String) -> Result<()> {
+    fs::write(path, contents)
+        .map_err(|e| error(format!("unable to write `{}`: {}", path.display(), e)))
+}
 
-    header_file.write(header.as_bytes()).unwrap();
-    code_file.write(code.as_bytes()).unwrap();
+fn error(message: String) -> Failed {
+    eprintln!("error: {}", message);
+    Failed
 }
@@ -0,0 +1,859 @@
+//! C backend
+//!
+//! A program is written as a header, with the types of the program and the prototypes of its
+//! exported functions, and a source file that has everything it needs to be compiled on its own.
+//! The backend doesn't know the types of expressions, so it only checks what it would otherwise
+//! write wrong, like matches that don't cover every value. Type errors are left to the C compiler.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Write,
+};
+
+use parsely_lexer::{tokens, AsSpan, SourceDatabase, Span};
+use parsely_parser::{
+    expression::{Call, Expression, FieldAccess, Literal, Member, StructLiteral, UnaryOp},
+    item::{Enum, Function, Parameter, Program, Struct, TopLevelItem},
+    statement::{ArrayDimension, Statement},
+    types, Punctuation,
+};
+
+use crate::{
+    attempt, check::Checker, raise, statement::has_value, Diagnostic, DiagnosticFmt, Result,
+};
+
+/// Headers every file needs for the types it uses
+const INCLUDES: &str = "#include <stdbool.h>\n#include <stddef.h>\n#include <stdint.h>\n";
+
+/// A type as it's written in C
+#[derive(Debug, Clone)]
+pub(crate) struct CType {
+    /// Written before the name in a declaration
+    pub name: String,
+    /// Used in the names of the types generated for slices and arrays of this type
+    pub mangled: String,
+}
+
+impl CType {
+    fn new(name: impl ToString, mangled: impl ToString) -> CType {
+        CType {
+            name: name.to_string(),
+            mangled: mangled.to_string(),
+        }
+    }
+
+    fn is_void(&self) -> bool {
+        self.name == "void"
+    }
+}
+
+pub struct CModule<'a> {
+    name: String,
+    source: &'a SourceDatabase,
+    errors: Vec<Diagnostic>,
+    dirty: bool,
+
+    /// Definitions of the types of the program, and of the types generated for it
+    types: String,
+    /// Names of the generated types that are defined already
+    generated: HashSet<String>,
+    /// Structs, enums and aliases of the program by name
+    pub(crate) items: HashMap<String, TopLevelItem>,
+    /// Types of the parameters of each function of the program, and the type it returns
+    pub(crate) functions: HashMap<String, (Vec<types::Type>, types::Type)>,
+    /// Return type of the function being written
+    pub(crate) return_type: Option<types::Type>,
+    /// Variables in scope of the statement being written, innermost last, with their declared
+    /// types. Loop counters and pattern bindings don't have one.
+    pub(crate) variables: Vec<(String, Option<types::Type>)>,
+    /// Labelled loops around the statement being written, innermost last. Each label has the
+    /// suffix of the C labels its `break` and `continue` jump to.
+    pub(crate) labels: Vec<(String, String)>,
+    /// Number of loops around the statement being written
+    pub(crate) loops: usize,
+}
+
+impl<'a> CModule<'a> {
+    pub fn new(name: impl ToString, source: &'a SourceDatabase) -> CModule<'a> {
+        CModule {
+            name: name.to_string(),
+            source,
+            errors: Vec::new(),
+            dirty: false,
+
+            types: String::new(),
+            generated: HashSet::new(),
+            items: HashMap::new(),
+            functions: HashMap::new(),
+            return_type: None,
+            variables: Vec::new(),
+            labels: Vec::new(),
+            loops: 0,
+        }
+    }
+
+    /// Diagnostics produced while generating this module
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.errors
+    }
+
+    /// Source files the module is generated from
+    pub fn source(&self) -> &'a SourceDatabase {
+        self.source
+    }
+
+    /// Diagnostics of this module, formatted against its source files
+    pub fn format_diagnostics(&self) -> DiagnosticFmt<'_> {
+        DiagnosticFmt(&self.errors, self.source)
+    }
+
+    /// Returns true if any errors were produced during generation
+    pub fn has_errors(&self) -> bool {
+        self.dirty
+    }
+
+    pub(crate) fn push_error(&mut self, error: Diagnostic) {
+        self.errors.push(error);
+    }
+
+    /// Writes the program, and returns the header and the source file
+    pub fn run(&mut self, program: &Program) -> Result<(String, String)> {
+        let errors = Checker::run(program);
+        if !errors.is_empty() {
+            self.errors.extend(errors);
+            self.dirty = true;
+            return Err(Diagnostic::Caught(Span::EMPTY));
+        }
+
+        let mut prototypes = String::new();
+        let mut code = String::new();
+
+        // Patterns need to know how an enum is laid out, and calls what their arguments are,
+        // wherever it's declared
+        for item in &program.items {
+            let (ident, params, return_type) = match item {
+                TopLevelItem::Enum(e) => {
+                    self.items.insert(e.ident.value.clone(), item.clone());
+                    continue;
+                }
+                TopLevelItem::Struct(s) => {
+                    self.items.insert(s.ident.value.clone(), item.clone());
+                    continue;
+                }
+                TopLevelItem::TypeAlias(alias) => {
+                    self.items.insert(alias.ident.value.clone(), item.clone());
+                    continue;
+                }
+                TopLevelItem::Function(f) => (&f.ident, &f.params.value, &f.return_type),
+                TopLevelItem::ExternalFunction(f) => (&f.ident, &f.params.value, &f.return_type),
+                _ => continue,
+            };
+
+            let params = params.iter().map(|p| (*p.parameter_type).clone()).collect();
+            let return_type = (**return_type).clone();
+            self.functions
+                .insert(ident.value.clone(), (params, return_type));
+        }
+
+        for item in &program.items {
+            self.gen_item(&mut prototypes, &mut code, item)?;
+        }
+
+        self.dirty = !self.errors.is_empty();
+
+        let guard: String = self
+            .name
+            .chars()
+            .map(|c| match c {
+                'a'..='z' | 'A'..='Z' | '0'..='9' => c.to_ascii_uppercase(),
+                _ => '_',
+            })
+            .collect();
+
+        let mut header = String::new();
+        writeln!(header, "#ifndef {}_H", guard)?;
+        writeln!(header, "#define {}_H\n", guard)?;
+        writeln!(header, "{}", INCLUDES)?;
+        writeln!(header, "{}", self.types)?;
+        header.push_str(&prototypes);
+        writeln!(header, "\n#endif")?;
+
+        let source = format!("{}\n{}\n{}", INCLUDES, self.types, code);
+
+        Ok((header, source))
+    }
+
+    /// Logs that the C backend can't write `what` yet
+    pub(crate) fn unsupported(&mut self, what: &str, span: Span) -> Diagnostic {
+        let msg = format!("The C backend doesn't support {} yet", what);
+        raise!(@log Error => self, msg, span);
+        Diagnostic::Caught(span)
+    }
+
+    /// Writes an item. Prototypes of exported functions go to `header`, everything else that
+    /// isn't a type goes to `code`.
+    fn gen_item(
+        &mut self,
+        header: &mut String,
+        code: &mut String,
+        item: &TopLevelItem,
+    ) -> Result<()> {
+        match item {
+            TopLevelItem::Function(f) => {
+                let (prototype, ty) =
+                    self.gen_prototype(&f.ident, &f.return_type, &f.params.value)?;
+                if f.export.is_some() {
+                    writeln!(header, "{};", prototype)?;
+                }
+
+                writeln!(code, "{} {{", prototype)?;
+                self.return_type = Some((*f.return_type).clone());
+                self.variables = f
+                    .params
+                    .value
+                    .iter()
+                    .map(|p| {
+                        (
+                            p.ident.value.clone(),
+                            declared_type(&p.parameter_type, &p.arrays),
+                        )
+                    })
+                    .collect();
+                let result = self.gen_function_body(code, f, &ty);
+                self.return_type = None;
+                self.variables.clear();
+                result?;
+                writeln!(code, "}}\n")?;
+            }
+            TopLevelItem::ExternalFunction(f) => {
+                let (prototype, _) =
+                    self.gen_prototype(&f.ident, &f.return_type, &f.params.value)?;
+                if f.export.is_some() {
+                    writeln!(header, "{};", prototype)?;
+                }
+
+                writeln!(code, "{};\n", prototype)?;
+            }
+            TopLevelItem::Struct(s) => self.gen_struct(s)?,
+            TopLevelItem::Enum(e) => self.gen_enum(e)?,
+            // C can't keep distinct aliases apart, so they are plain typedefs as well
+            TopLevelItem::TypeAlias(alias) => {
+                let ty = self.gen_type(&alias.ty)?;
+                writeln!(self.types, "typedef {} {};\n", ty.name, alias.ident.value)?;
+            }
+            // Reported by the parser already
+            TopLevelItem::Error(_) => (),
+        }
+
+        Ok(())
+    }
+
+    /// Declares a struct with a typedef, so it can be named without `struct`, and defines it
+    /// after. Its fields can point to it that way. An opaque struct is only declared.
+    fn gen_struct(&mut self, s: &Struct) -> Result<()> {
+        let name = &s.ident.value;
+
+        writeln!(self.types, "typedef struct {} {};\n", name, name)?;
+        if s.opaque.is_some() {
+            return Ok(());
+        }
+
+        let mut def = format!("struct {} {{\n", name);
+        for field in s.body.value.iter() {
+            let decl =
+                self.gen_declaration(&field.parameter_type, &field.arrays, &field.ident.value)?;
+            writeln!(def, "{};", decl)?;
+        }
+
+        if s.packed.is_some() {
+            writeln!(def, "}} __attribute__((packed));\n")?;
+        } else {
+            writeln!(def, "}};\n")?;
+        }
+
+        // After the types of the fields, which may be generated
+        self.types.push_str(&def);
+        Ok(())
+    }
+
+    /// Defines an enum as its tag type, or a tagged union as a struct
+    ///
+    /// Tagged unions have a `tag`, and a union `as` holding a struct for the payload of each
+    /// variant with one, with fields `_0`, `_1`... They're declared before they're defined, like
+    /// structs, so payloads can point to them. The discriminants are constants named
+    /// `Enum_Variant`. The tag type is fixed, since the size of a C enum isn't.
+    fn gen_enum(&mut self, e: &Enum) -> Result<()> {
+        let name = &e.ident.value;
+        let tag = int_type(e.tag.as_ref().map_or(32, |tag| tag.ty.size as u32), true);
+
+        let mut def = String::from("enum {\n");
+        for (variant, d) in e.variants.value.iter().zip(e.discriminants()) {
+            writeln!(def, "{}_{} = {},", name, variant.ident.value, d)?;
+        }
+        writeln!(def, "}};")?;
+
+        if !e.is_tagged_union() {
+            writeln!(def, "typedef {} {};\n", tag.name, name)?;
+            self.types.push_str(&def);
+            return Ok(());
+        }
+
+        writeln!(self.types, "typedef struct {} {};\n", name, name)?;
+        writeln!(def, "struct {} {{", name)?;
+        writeln!(def, "{} tag;", tag.name)?;
+        writeln!(def, "union {{")?;
+
+        for variant in e.variants.value.iter() {
+            // C doesn't allow empty structs, and there is nothing to store anyway
+            let Some(fields) = variant.fields.as_ref().filter(|f| !f.value.is_empty()) else {
+                continue;
+            };
+
+            writeln!(def, "struct {{")?;
+            for (i, ty) in fields.value.iter().enumerate() {
+                let ty = self.gen_type(ty)?;
+                writeln!(def, "{} _{};", ty.name, i)?;
+            }
+            writeln!(def, "}} {};", variant.ident.value)?;
+        }
+
+        writeln!(def, "}} as;")?;
+        writeln!(def, "}};\n")?;
+
+        // After the types of the payloads, which may be generated
+        self.types.push_str(&def);
+        Ok(())
+    }
+
+    /// Returns the prototype of a function, and its return type
+    fn gen_prototype(
+        &mut self,
+        ident: &tokens::Ident,
+        return_type: &types::Type,
+        params: &Punctuation<Parameter, tokens::Tok![,]>,
+    ) -> Result<(String, CType)> {
+        let ty = self.gen_type(return_type)?;
+        let mut prototype = format!("{} {}(", ty.name, ident.value);
+
+        if params.is_empty() {
+            prototype.push_str("void");
+        }
+
+        for (i, param) in params.iter().enumerate() {
+            if i > 0 {
+                prototype.push_str(", ");
+            }
+
+            let decl =
+                self.gen_declaration(&param.parameter_type, &param.arrays, &param.ident.value)?;
+            prototype.push_str(&decl);
+        }
+        prototype.push(')');
+
+        Ok((prototype, ty))
+    }
+
+    /// Writes the statements of a function. The value of the last statement is returned, if
+    /// the function returns one.
+    fn gen_function_body(&mut self, buffer: &mut String, f: &Function, ty: &CType) -> Result<()> {
+        let (tail, body) = match f.body.value.split_last() {
+            Some((last, rest)) if !ty.is_void() && has_value(last) => (Some(last), rest),
+            _ => (None, f.body.value.as_slice()),
+        };
+
+        // Not a block of its own, so the tail sees the variables declared before it
+        for stmt in body {
+            let _ = attempt!(self, self.gen_statement(buffer, stmt));
+        }
+
+        if let Some(tail) = tail {
+            let _ = attempt!(self, self.gen_return_value(buffer, tail));
+        }
+
+        Ok(())
+    }
+
+    /// Writes the last statement of a function as the value it returns
+    fn gen_return_value(&mut self, buffer: &mut impl Write, stmt: &Statement) -> Result<()> {
+        write!(buffer, "return ")?;
+        match (stmt, self.return_type.clone()) {
+            (Statement::Expression(expr), Some(ty)) => {
+                self.gen_expression_as(buffer, &expr.expression, &ty)?
+            }
+            _ => self.gen_value(buffer, stmt)?,
+        }
+        writeln!(buffer, ";")?;
+
+        Ok(())
+    }
+
+    /// The C spelling of a type
+    pub(crate) fn gen_type(&mut self, ty: &types::Type) -> Result<CType> {
+        match ty {
+            types::Type::Empty | types::Type::Void(_) => Ok(CType::new("void", "void")),
+            types::Type::Int(i) => Ok(int_type(i.size as u32, true)),
+            types::Type::Str(_) => Ok(self.slice_type(int_type(8, true))),
+            types::Type::Named(i) => Ok(CType::new(&i.value, &i.value)),
+            types::Type::Array(a) | types::Type::Slice(a) => {
+                let ty = self.gen_type(&a.element)?;
+                Ok(self.array_dims(ty, &a.arrays))
+            }
+            // Only `#` pointers can be written through. `const` goes after the pointee, so it
+            // applies to the right pointer when they are nested.
+            types::Type::Pointer(p) => {
+                let ty = self.gen_type(&p.pointee)?;
+                if p.is_mutable() {
+                    Ok(CType::new(
+                        format!("{} *", ty.name),
+                        format!("ptr_{}", ty.mangled),
+                    ))
+                } else {
+                    Ok(CType::new(
+                        format!("{} const *", ty.name),
+                        format!("cptr_{}", ty.mangled),
+                    ))
+                }
+            }
+            types::Type::Optional(o) => {
+                let ty = self.gen_type(&o.inner)?;
+                Ok(self.optional_type(ty))
+            }
+            types::Type::Tuple(t) => {
+                // C doesn't allow empty structs
+                if t.value.is_empty() {
+                    return Err(self.unsupported("empty tuples", t.as_span()));
+                }
+
+                let fields = t
+                    .value
+                    .iter()
+                    .map(|field| self.gen_type(field))
+                    .collect::<Result<Vec<_>>>()?;
+                Ok(self.tuple_type(fields))
+            }
+        }
+    }
+
+    /// Wraps `ty` in array dimensions. The last dimension is the innermost one, and dimensions
+    /// without a size are slices.
+    pub(crate) fn array_dims(&mut self, mut ty: CType, arrays: &[ArrayDimension]) -> CType {
+        for dim in arrays.iter().rev() {
+            ty = match &*dim.dimension.value {
+                Some(size) => self.array_type(ty, size.value),
+                None => self.slice_type(ty),
+            };
+        }
+
+        ty
+    }
+
+    /// Declares `name` with the type `ty` and the dimensions written after the name
+    ///
+    /// Sized dimensions stay after the name, like in C, up to the first slice. Everything from
+    /// there on is part of the type.
+    pub(crate) fn gen_declaration(
+        &mut self,
+        ty: &types::Type,
+        arrays: &[ArrayDimension],
+        name: &str,
+    ) -> Result<String> {
+        let sized = arrays
+            .iter()
+            .take_while(|dim| dim.dimension.value.is_some())
+            .count();
+
+        let ty = self.gen_type(ty)?;
+        let ty = self.array_dims(ty, &arrays[sized..]);
+
+        let mut decl = format!("{} {}", ty.name, name);
+        for dim in &arrays[..sized] {
+            if let Some(size) = &*dim.dimension.value {
+                write!(decl, "[{}]", size.value)?;
+            }
+        }
+
+        Ok(decl)
+    }
+
+    /// A typedef of an array, so it can be used where C only takes a type name
+    fn array_type(&mut self, element: CType, size: u64) -> CType {
+        let mangled = format!("array{}_{}", size, element.mangled);
+        self.generated_type(mangled, |name| {
+            format!("typedef {} {}[{}];\n", element.name, name, size)
+        })
+    }
+
+    /// A slice is a length followed by a pointer to the first element, like in the LLVM backend
+    fn slice_type(&mut self, element: CType) -> CType {
+        let mangled = format!("slice_{}", element.mangled);
+        self.generated_type(mangled, |name| {
+            format!(
+                "typedef struct {{ size_t len; {} *ptr; }} {};\n",
+                element.name, name
+            )
+        })
+    }
+
+    /// An optional is a flag for whether there is a value, followed by the value, like in the
+    /// LLVM backend
+    fn optional_type(&mut self, inner: CType) -> CType {
+        let mangled = format!("opt_{}", inner.mangled);
+        self.generated_type(mangled, |name| {
+            format!(
+                "typedef struct {{ bool has; {} value; }} {};\n",
+                inner.name, name
+            )
+        })
+    }
+
+    /// Functions that make an `optional` holding a value of `inner`, and that keep one as it
+    /// is. They go with the types, and are only defined once like them.
+    fn optional_functions(&mut self, optional: &CType, inner: &CType) {
+        let mangled = format!("some_{}", optional.mangled);
+        self.generated_type(mangled, |some| {
+            format!(
+                "static inline {opt} {some}({inner} value) {{ return ({opt}){{ true, value }}; }}\n\
+                 static inline {opt} __keep_{m}({opt} value) {{ return value; }}\n",
+                opt = optional.name,
+                inner = inner.name,
+                m = optional.mangled,
+            )
+        });
+    }
+
+    /// A tuple is a struct with fields `_0`, `_1`...
+    fn tuple_type(&mut self, fields: Vec<CType>) -> CType {
+        let mut mangled = format!("tuple{}", fields.len());
+        for field in &fields {
+            mangled.push('_');
+            mangled.push_str(&field.mangled);
+        }
+
+        self.generated_type(mangled, |name| {
+            let mut def = String::from("typedef struct { ");
+            for (i, field) in fields.iter().enumerate() {
+                def.push_str(&format!("{} _{}; ", field.name, i));
+            }
+            format!("{}}} {};\n", def, name)
+        })
+    }
+
+    /// A type that only exists in the C output, which is defined the first time it's used
+    fn generated_type(&mut self, mangled: String, define: impl FnOnce(&str) -> String) -> CType {
+        let name = format!("__{}", mangled);
+        if self.generated.insert(name.clone()) {
+            let definition = define(&name);
+            self.types.push_str(&definition);
+        }
+
+        CType { name, mangled }
+    }
+
+    pub(crate) fn gen_expression(
+        &mut self,
+        buffer: &mut impl Write,
+        expr: &Expression,
+    ) -> Result<()> {
+        match expr {
+            Expression::Literal(lit) => self.gen_literal(buffer, lit)?,
+            Expression::Ident(ident) => write!(buffer, "{}", ident.value)?,
+            Expression::Parens(p) => {
+                write!(buffer, "(")?;
+                self.gen_expression(buffer, &p.value)?;
+                write!(buffer, ")")?;
+            }
+            // `x == none` and `x != none` test the flag of the optional
+            Expression::BinOp(b) if matches!(*b.right, Expression::Literal(Literal::None(_))) => {
+                match b.op {
+                    tokens::Tok![enum ==] => write!(buffer, "(!(")?,
+                    tokens::Tok![enum !=] => write!(buffer, "((")?,
+                    _ => return Err(self.unsupported("this operator with `none`", b.op.as_span())),
+                }
+                self.gen_expression(buffer, &b.left)?;
+                write!(buffer, ").has)")?;
+            }
+            // Every operator is in parentheses, so C's precedence doesn't matter
+            Expression::BinOp(b) => {
+                write!(buffer, "(")?;
+                self.gen_expression(buffer, &b.left)?;
+                write!(buffer, " {} ", b.op)?;
+                self.gen_expression(buffer, &b.right)?;
+                write!(buffer, ")")?;
+            }
+            Expression::Call(call) => self.gen_call(buffer, call)?,
+            // C has the same compound operators, and also evaluates the target once
+            Expression::Assign(a) => {
+                write!(buffer, "(")?;
+                self.gen_expression(buffer, &a.target)?;
+                write!(buffer, " {} ", a.op)?;
+                self.gen_expression(buffer, &a.value)?;
+                write!(buffer, ")")?;
+            }
+            Expression::Field(f) => self.gen_field(buffer, f)?,
+            Expression::StructLiteral(lit) => self.gen_struct_literal(buffer, lit)?,
+            Expression::AddressOf(a) => {
+                write!(buffer, "(&")?;
+                self.gen_expression(buffer, &a.expr)?;
+                write!(buffer, ")")?;
+            }
+            Expression::Deref(d) => {
+                write!(buffer, "(*")?;
+                self.gen_expression(buffer, &d.expr)?;
+                write!(buffer, ")")?;
+            }
+            // Written whole, so `-128i8` doesn't negate 128 as an `int8_t`
+            Expression::Unary(u) if is_negative_literal(u) => {
+                let Expression::Literal(Literal::Int(i)) = &*u.expr else {
+                    unreachable!("Checked above!")
+                };
+                write_int(buffer, &i.value, true)?;
+            }
+            Expression::Unary(u) => {
+                write!(buffer, "({}", u.op)?;
+                self.gen_expression(buffer, &u.expr)?;
+                write!(buffer, ")")?;
+            }
+            Expression::ArrayInit(_) | Expression::Index(_) | Expression::Slice(_) => {
+                return Err(self.unsupported("arrays", expr.as_span()))
+            }
+            Expression::Match(m) => self.gen_match_expression(buffer, m)?,
+            Expression::If(stmt) => self.gen_if_expression(buffer, stmt)?,
+            Expression::Block(body) => self.gen_block_expression(buffer, &body.value)?,
+        }
+
+        Ok(())
+    }
+
+    /// Writes `expr` where a value of type `ty` is expected. String literals become a `str`
+    /// and values become an optional where one is expected, like in the LLVM backend.
+    pub(crate) fn gen_expression_as(
+        &mut self,
+        buffer: &mut impl Write,
+        expr: &Expression,
+        ty: &types::Type,
+    ) -> Result<()> {
+        match (string_literal(expr), ty) {
+            (Some(bytes), types::Type::Str(_)) => {
+                let str_ty = self.gen_type(ty)?;
+                write!(buffer, "(({}){{ {}, (int8_t *)", str_ty.name, bytes.len())?;
+                write_bytes(buffer, bytes)?;
+                write!(buffer, " }})")?;
+            }
+            (_, types::Type::Optional(o)) => self.gen_optional(buffer, expr, ty, &o.inner)?,
+            _ => self.gen_expression(buffer, expr)?,
+        }
+
+        Ok(())
+    }
+
+    /// Writes `expr` as the optional `ty`, holding its value unless it's an optional already.
+    /// Only the C compiler knows whether variables, calls and the like are, so those pick
+    /// between `__some_` and `__keep_` with `_Generic`.
+    fn gen_optional(
+        &mut self,
+        buffer: &mut impl Write,
+        expr: &Expression,
+        ty: &types::Type,
+        inner: &types::Type,
+    ) -> Result<()> {
+        let optional = self.gen_type(ty)?;
+        let inner_ty = self.gen_type(inner)?;
+        self.optional_functions(&optional, &inner_ty);
+
+        match expr {
+            Expression::Parens(p) => self.gen_optional(buffer, &p.value, ty, inner)?,
+            Expression::Literal(Literal::None(_)) => {
+                write!(buffer, "(({}){{ false }})", optional.name)?
+            }
+            Expression::Literal(_)
+            | Expression::BinOp(_)
+            | Expression::Unary(_)
+            | Expression::AddressOf(_)
+            | Expression::StructLiteral(_) => {
+                write!(buffer, "__some_{}(", optional.mangled)?;
+                self.gen_expression_as(buffer, expr, inner)?;
+                write!(buffer, ")")?;
+            }
+            _ => {
+                // The controlling expression isn't evaluated, so this only runs it once
+                let mut value = String::new();
+                self.gen_expression(&mut value, expr)?;
+                write!(
+                    buffer,
+                    "_Generic(({value}), {}: __keep_{m}, default: __some_{m})({value})",
+                    optional.name,
+                    m = optional.mangled,
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes a call. A method call `a.f(x)` becomes `f(a, x)`.
+    fn gen_call(&mut self, buffer: &mut impl Write, call: &Call) -> Result<()> {
+        let (name, receiver) = match &*call.expr {
+            Expression::Field(FieldAccess {
+                expr,
+                member: Member::Named(ident),
+                ..
+            }) => {
+                write!(buffer, "{}", ident.value)?;
+                (Some(ident), Some(&**expr))
+            }
+            expr => {
+                self.gen_expression(buffer, expr)?;
+                match expr {
+                    Expression::Ident(ident) => (Some(ident), None),
+                    _ => (None, None),
+                }
+            }
+        };
+        let params = name
+            .and_then(|name| self.functions.get(&name.value))
+            .map(|(params, _)| params.clone())
+            .unwrap_or_default();
+
+        write!(buffer, "(")?;
+        let args = receiver.into_iter().chain(call.args.value.iter());
+        for (i, arg) in args.enumerate() {
+            if i > 0 {
+                write!(buffer, ", ")?;
+            }
+
+            match params.get(i) {
+                Some(param) => self.gen_expression_as(buffer, arg, param)?,
+                None => self.gen_expression(buffer, arg)?,
+            }
+        }
+        write!(buffer, ")")?;
+
+        Ok(())
+    }
+
+    /// Writes a field access. Tuples are structs with fields `_0`, `_1`...
+    fn gen_field(&mut self, buffer: &mut impl Write, f: &FieldAccess) -> Result<()> {
+        write!(buffer, "(")?;
+        self.gen_expression(buffer, &f.expr)?;
+
+        match &f.member {
+            Member::Named(ident) => write!(buffer, ").{}", ident.value)?,
+            Member::Index(i) => write!(buffer, ")._{}", i.value.value)?,
+        }
+
+        Ok(())
+    }
+
+    /// Writes a struct literal as a compound literal with designated initializers. The checker
+    /// has made sure that every field is given, since C would leave out the missing ones as zero.
+    fn gen_struct_literal(&mut self, buffer: &mut impl Write, lit: &StructLiteral) -> Result<()> {
+        write!(buffer, "({}){{", lit.ident.value)?;
+        for field in lit.fields.value.iter() {
+            write!(buffer, " .{} = ", field.ident.value)?;
+            self.gen_expression(buffer, &field.value)?;
+            write!(buffer, ",")?;
+        }
+        write!(buffer, " }}")?;
+
+        Ok(())
+    }
+
+    /// Writes a literal. Strings are written byte by byte, so they mean the same in C.
+    fn gen_literal(&mut self, buffer: &mut impl Write, lit: &Literal) -> Result<()> {
+        match lit {
+            Literal::Int(i) => write_int(buffer, &i.value, false)?,
+            Literal::Float(f) => match f.value.suffix {
+                Some(tokens::FloatSuffix::F32) => write!(buffer, "{:?}f", f.value.value)?,
+                Some(tokens::FloatSuffix::F64) | None => write!(buffer, "{:?}", f.value.value)?,
+            },
+            Literal::String(s) => write_bytes(buffer, s.value.value.as_bytes())?,
+            Literal::ByteString(s) => write_bytes(buffer, &s.value.value)?,
+            Literal::Char(c) => write!(buffer, "{}", c.value.value as u32)?,
+            Literal::None(n) => {
+                let msg = "`none` needs an optional type to be expected";
+                raise!(@log Error => self, msg, n.as_span());
+                return Err(Diagnostic::Caught(n.as_span()));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// An integer type of `bits` bits. Sizes C doesn't have are bit-precise integers, except for
+/// booleans, since a signed `_BitInt` needs a bit for the sign.
+/// The type of a variable that a match on it can use, unless it's an array
+pub(crate) fn declared_type(ty: &types::Type, arrays: &[ArrayDimension]) -> Option<types::Type> {
+    arrays.is_empty().then(|| ty.clone())
+}
+
+/// Whether `u` negates an integer literal with a signed suffix
+fn is_negative_literal(u: &UnaryOp) -> bool {
+    matches!(
+        (&u.op, &*u.expr),
+        (tokens::Tok![enum -], Expression::Literal(Literal::Int(i)))
+            if i.value.suffix.is_some_and(|suffix| suffix.is_signed())
+    )
+}
+
+fn int_type(bits: u32, signed: bool) -> CType {
+    let (name, mangled) = match (bits, signed) {
+        (1, _) => ("bool".to_string(), "bool".to_string()),
+        (8 | 16 | 32 | 64, true) => (format!("int{}_t", bits), format!("int{}", bits)),
+        (8 | 16 | 32 | 64, false) => (format!("uint{}_t", bits), format!("uint{}", bits)),
+        (_, true) => (format!("_BitInt({})", bits), format!("int{}", bits)),
+        (_, false) => (
+            format!("unsigned _BitInt({})", bits),
+            format!("uint{}", bits),
+        ),
+    };
+
+    CType { name, mangled }
+}
+
+/// Writes an integer literal, negated if `negative`. Suffixed integers are cast to their type.
+pub(crate) fn write_int(
+    buffer: &mut impl Write,
+    i: &tokens::Int,
+    negative: bool,
+) -> std::fmt::Result {
+    // Anything larger doesn't fit in a signed C literal, and the minimum can't be negated
+    let digits = match (negative, i.value) {
+        (true, value) if value == i64::MIN.unsigned_abs() => "(-9223372036854775807ll - 1)".into(),
+        (true, value) if value > i64::MAX as u64 => format!("-{}ull", value),
+        (true, value) => format!("-{}", value),
+        (false, value) if value > i64::MAX as u64 => format!("{}ull", value),
+        (false, value) => value.to_string(),
+    };
+
+    match i.suffix {
+        Some(suffix) => {
+            let ty = int_type(suffix.bits(), suffix.is_signed());
+            write!(buffer, "(({}){})", ty.name, digits)
+        }
+        None => write!(buffer, "{}", digits),
+    }
+}
+
+/// The bytes of a string literal, which may be in parentheses
+fn string_literal(expr: &Expression) -> Option<&[u8]> {
+    match expr {
+        Expression::Literal(Literal::String(s)) => Some(s.value.value.as_bytes()),
+        Expression::Parens(p) => string_literal(&p.value),
+        _ => None,
+    }
+}
+
+/// Writes a string literal. Anything but printable ASCII is an octal escape, which unlike a hex
+/// escape can't run into the next character.
+fn write_bytes(buffer: &mut impl Write, bytes: &[u8]) -> std::fmt::Result {
+    write!(buffer, "\"")?;
+    for &b in bytes {
+        match b {
+            b'"' | b'\\' => write!(buffer, "\\{}", b as char)?,
+            b' '..=b'~' => write!(buffer, "{}", b as char)?,
+            _ => write!(buffer, "\\{:03o}", b)?,
+        }
+    }
+    write!(buffer, "\"")
+}
@@ -0,0 +1,288 @@
+//! Checks that only need the syntax tree, shared by both backends
+//!
+//! They run over the whole program before anything is generated, so the backends can count on
+//! enums having valid tags and struct literals giving every field exactly once.
+
+use std::collections::HashMap;
+
+use parsely_lexer::{tokens, AsSpan};
+use parsely_parser::{
+    expression::{Expression, Literal, StructLiteral},
+    item::{Enum, Program, TopLevelItem},
+    pattern::{ArmBody, Match},
+    statement::{ElseBody, ForIter, IfStatement, Statement},
+    types,
+};
+
+use crate::{raise, Diagnostic};
+
+pub(crate) struct Checker<'p> {
+    /// Structs, enums and aliases of the program by name
+    types: HashMap<&'p str, &'p TopLevelItem>,
+    errors: Vec<Diagnostic>,
+}
+
+impl<'p> Checker<'p> {
+    /// Checks `program`, and returns what's wrong with it
+    pub fn run(program: &'p Program) -> Vec<Diagnostic> {
+        let mut checker = Checker {
+            types: HashMap::new(),
+            errors: Vec::new(),
+        };
+
+        for item in &program.items {
+            let name = match item {
+                TopLevelItem::Struct(s) => &s.ident,
+                TopLevelItem::Enum(e) => &e.ident,
+                TopLevelItem::TypeAlias(alias) => &alias.ident,
+                _ => continue,
+            };
+            checker.types.insert(&name.value, item);
+        }
+
+        for item in &program.items {
+            match item {
+                TopLevelItem::Enum(e) => checker.check_enum(e),
+                TopLevelItem::Function(f) => checker.check_block(&f.body.value),
+                _ => (),
+            }
+        }
+
+        checker.errors
+    }
+
+    fn push_error(&mut self, error: Diagnostic) {
+        self.errors.push(error);
+    }
+
+    /// Checks that the tag of an enum is as wide as both backends allow, and that every
+    /// discriminant fits in it once
+    fn check_enum(&mut self, e: &Enum) {
+        let bits = match &e.tag {
+            // There is no `_BitInt(0)` in C, and `_BitInt` may not be wider than 64 bits
+            Some(tag) if !(1..=64).contains(&tag.ty.size) => {
+                let msg = format!("Tag type `int{}` isn't 1 to 64 bits wide", tag.ty.size);
+                raise!(@log Error => self, msg, tag.ty.token.as_span());
+                return;
+            }
+            Some(tag) => tag.ty.size as u32,
+            None => 32,
+        };
+
+        // Tags are signed
+        let max = (1i128 << (bits - 1)) - 1;
+        let discriminants = e.discriminants();
+
+        for (i, (variant, &d)) in e.variants.value.iter().zip(&discriminants).enumerate() {
+            let span = match &variant.discriminant {
+                Some(d) => d.value.as_span(),
+                None => variant.ident.as_span(),
+            };
+
+            if d > max || d < -max - 1 {
+                let msg = format!("Discriminant `{}` doesn't fit in `int{}`", d, bits);
+                raise!(@log Error => self, msg, span);
+            } else if discriminants[..i].contains(&d) {
+                let msg = format!("Discriminant `{}` is used more than once", d);
+                raise!(@log Error => self, msg, span);
+            }
+        }
+    }
+
+    fn check_block(&mut self, body: &[Statement]) {
+        for stmt in body {
+            self.check_statement(stmt);
+        }
+    }
+
+    fn check_statement(&mut self, stmt: &Statement) {
+        match stmt {
+            Statement::Expression(expr) => self.check_expression(&expr.expression),
+            Statement::VariableDeclaration(var) => {
+                if let Some(init) = &var.init {
+                    self.check_expression(&init.expression);
+                }
+            }
+            Statement::IfStatement(stmt) => self.check_if(stmt),
+            Statement::WhileLoop(stmt) => {
+                self.check_expression(&stmt.condition);
+                self.check_block(&stmt.body.value);
+            }
+            Statement::ForLoop(stmt) => {
+                match &stmt.iter {
+                    ForIter::Range(range) => {
+                        for bound in range.left.iter().chain(&range.right) {
+                            self.check_expression(bound);
+                        }
+                    }
+                    ForIter::Slice(expr) => self.check_expression(expr),
+                }
+                self.check_block(&stmt.body.value);
+            }
+            Statement::ReturnStatement(stmt) => self.check_expression(&stmt.expr),
+            Statement::Match(m) => self.check_match(m),
+            Statement::Break(_) | Statement::Continue(_) | Statement::Error(_) => (),
+        }
+    }
+
+    fn check_if(&mut self, stmt: &IfStatement) {
+        self.check_expression(&stmt.condition);
+        self.check_block(&stmt.body.value);
+
+        match stmt.else_branch.as_ref().map(|e| &e.body) {
+            Some(ElseBody::If(stmt)) => self.check_if(stmt),
+            Some(ElseBody::Block(body)) => self.check_block(&body.value),
+            None => (),
+        }
+    }
+
+    fn check_match(&mut self, m: &Match) {
+        self.check_expression(&m.expr);
+
+        for arm in m.arms.value.iter() {
+            if let Some(guard) = &arm.guard {
+                self.check_expression(&guard.condition);
+            }
+
+            match &arm.body {
+                ArmBody::Expression(expr) => self.check_expression(expr),
+                ArmBody::Block(body) => self.check_block(&body.value),
+            }
+        }
+    }
+
+    fn check_expression(&mut self, expr: &Expression) {
+        match expr {
+            Expression::Literal(Literal::Int(i)) => self.check_int(&i.value),
+            Expression::Literal(_) | Expression::Ident(_) => (),
+            Expression::ArrayInit(a) => {
+                for element in a.elements.value.iter() {
+                    self.check_expression(element);
+                }
+            }
+            Expression::Parens(p) => self.check_expression(&p.value),
+            Expression::BinOp(b) => {
+                self.check_expression(&b.left);
+                self.check_expression(&b.right);
+            }
+            Expression::Assign(a) => {
+                self.check_expression(&a.target);
+                self.check_expression(&a.value);
+            }
+            Expression::Index(i) => {
+                self.check_expression(&i.expr);
+                self.check_expression(&i.index.value);
+            }
+            Expression::Slice(s) => {
+                self.check_expression(&s.expr);
+                for bound in s.range.value.left.iter().chain(&s.range.value.right) {
+                    self.check_expression(bound);
+                }
+            }
+            Expression::Call(call) => {
+                self.check_expression(&call.expr);
+                for arg in call.args.value.iter() {
+                    self.check_expression(arg);
+                }
+            }
+            Expression::Field(f) => self.check_expression(&f.expr),
+            Expression::StructLiteral(lit) => self.check_struct_literal(lit),
+            Expression::Match(m) => self.check_match(m),
+            Expression::If(stmt) => self.check_if(stmt),
+            Expression::Block(body) => self.check_block(&body.value),
+            // The lexer lets signed literals go one further, which only fits negated
+            Expression::Unary(u)
+                if matches!(
+                    (&u.op, &*u.expr),
+                    (tokens::Tok![enum -], Expression::Literal(Literal::Int(_)))
+                ) => {}
+            Expression::Unary(u) => self.check_expression(&u.expr),
+            Expression::AddressOf(a) => self.check_expression(&a.expr),
+            Expression::Deref(d) => self.check_expression(&d.expr),
+        }
+    }
+
+    /// Checks that an integer literal with a suffix fits its type when it isn't negated
+    fn check_int(&mut self, i: &tokens::Int) {
+        match i.suffix {
+            Some(suffix) if suffix.is_signed() && i.value > suffix.max_value() => {
+                let msg = format!(
+                    "`{}` doesn't fit in a signed {}-bit integer",
+                    i.value,
+                    suffix.bits()
+                );
+                raise!(@log Error => self, msg, i.as_span());
+            }
+            _ => (),
+        }
+    }
+
+    /// The item `name` is, through any aliases of it
+    fn resolve(&self, name: &str) -> Option<&'p TopLevelItem> {
+        let mut item = *self.types.get(name)?;
+        // Aliases in a cycle never reach anything else, so give up once every one could be visited
+        for _ in 0..self.types.len() {
+            match item {
+                TopLevelItem::TypeAlias(alias) => match &*alias.ty {
+                    types::Type::Named(ident) => item = self.types.get(ident.value.as_str())?,
+                    _ => return Some(item),
+                },
+                _ => return Some(item),
+            }
+        }
+
+        None
+    }
+
+    /// Checks that a struct literal names a struct, or an alias of one, and gives each of its
+    /// fields exactly once
+    fn check_struct_literal(&mut self, lit: &StructLiteral) {
+        let s = match self.resolve(&lit.ident.value) {
+            Some(TopLevelItem::Struct(s)) => s,
+            Some(_) => {
+                let msg = format!("`{}` isn't a struct", lit.ident.value);
+                raise!(@log Error => self, msg, lit.ident.as_span());
+                return;
+            }
+            None => {
+                raise!(@not_found => self, lit.ident.clone());
+                return;
+            }
+        };
+
+        let mut given = vec![false; s.body.value.len()];
+        for init in lit.fields.value.iter() {
+            self.check_expression(&init.value);
+
+            let Some(i) = s
+                .body
+                .value
+                .iter()
+                .position(|f| f.ident.value == init.ident.value)
+            else {
+                let msg = format!("No field named `{}`", init.ident.value);
+                raise!(@log Error => self, msg, init.ident.as_span());
+                continue;
+            };
+
+            if given[i] {
+                let msg = format!("Field `{}` is given more than once", init.ident.value);
+                raise!(@log Error => self, msg, init.ident.as_span());
+            }
+            given[i] = true;
+        }
+
+        let missing: Vec<_> = s
+            .body
+            .value
+            .iter()
+            .zip(&given)
+            .filter(|(_, given)| !**given)
+            .map(|(field, _)| format!("`{}`", field.ident.value))
+            .collect();
+        if !missing.is_empty() {
+            let msg = format!("Missing fields {}", missing.join(", "));
+            raise!(@log Error => self, msg, lit.as_span());
+        }
+    }
+}
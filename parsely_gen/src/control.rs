@@ -0,0 +1,491 @@
+use inkwell::{
+    basic_block::BasicBlock,
+    types::{AnyTypeEnum, BasicTypeEnum},
+    values::IntValue,
+    IntPredicate,
+};
+use parsely_lexer::{
+    tokens::{self, LoopLabel},
+    AsSpan, Span,
+};
+use parsely_parser::{
+    expression::Expression,
+    statement::{
+        BreakStatement, ContinueStatement, ElseBody, ForIter, ForLoop, IfStatement, Label,
+        Statement, WhileLoop,
+    },
+};
+
+use crate::{
+    attempt,
+    expression::{untyped_int, ValOrExpr},
+    llvm_value::{AsValue, Type, TypeBuilder, TypeFlags, Value, Variable},
+    module::{Module, EMPTY_NAME},
+    raise, ErrorHelper, Result,
+};
+
+/// Where `break` and `continue` jump to inside a loop
+pub(crate) struct LoopBlocks<'ctx> {
+    pub label: Option<String>,
+    pub break_block: BasicBlock<'ctx>,
+    pub continue_block: BasicBlock<'ctx>,
+}
+
+impl<'ctx> Module<'ctx> {
+    /// Generates an if statement and its else chain. Returns the value of the branch that ran,
+    /// if there is an `else` and every branch has a value of the same type.
+    ///
+    /// Each `else if` gets its own condition block, and every branch jumps to a single block
+    /// after the whole chain, where a phi picks the value.
+    pub(crate) fn gen_if(
+        &mut self,
+        stmt: &IfStatement,
+        expected: Option<&Type<'ctx>>,
+    ) -> Result<Option<Value<'ctx>>> {
+        let llvm_func = self
+            .bb()
+            .get_parent()
+            .expect("Basic block is not in a function!");
+        let merge = self.context.append_basic_block(llvm_func, "endif");
+
+        let mut result = IfResult {
+            incoming: Vec::new(),
+            all_valued: true,
+        };
+
+        let mut stmt = stmt;
+        loop {
+            let condition = self.gen_condition(&stmt.condition)?;
+
+            let then_block = self.context.append_basic_block(llvm_func, "then");
+            let else_block = self.context.append_basic_block(llvm_func, "else");
+            self.builder
+                .build_conditional_branch(condition, then_block, else_block);
+
+            self.position_at(then_block);
+            let value = self.gen_block(&stmt.body.value, expected);
+            self.gen_branch_end(value, merge, &mut result);
+
+            self.position_at(else_block);
+            match stmt.else_branch.as_ref().map(|e| &e.body) {
+                Some(ElseBody::If(else_if)) => stmt = else_if,
+                Some(ElseBody::Block(body)) => {
+                    let value = self.gen_block(&body.value, expected);
+                    self.gen_branch_end(value, merge, &mut result);
+                    break;
+                }
+                None => {
+                    result.all_valued = false;
+                    self.builder.build_unconditional_branch(merge);
+                    break;
+                }
+            }
+        }
+
+        self.position_at(merge);
+
+        let ty = match result.incoming.first() {
+            Some((value, _)) if result.all_valued => value.ty.clone(),
+            _ => return Ok(None),
+        };
+        let same_type = result
+            .incoming
+            .iter()
+            .all(|(value, _)| value.ty.llvm == ty.llvm);
+        if !same_type {
+            return Ok(None);
+        }
+
+        let llvm: BasicTypeEnum = ty.llvm.try_into().expect("Unable to get basic type");
+        let phi = self.builder.build_phi(llvm, "if");
+        for (value, block) in &result.incoming {
+            phi.add_incoming(&[(&value.llvm, *block)]);
+        }
+
+        Ok(Some(phi.as_basic_value().as_value(ty)))
+    }
+
+    /// Jumps from the end of a branch of an if to `merge`, keeping its value for the phi
+    fn gen_branch_end(
+        &mut self,
+        value: Option<Value<'ctx>>,
+        merge: BasicBlock<'ctx>,
+        result: &mut IfResult<'ctx>,
+    ) {
+        // The branch can have branches of its own, so this isn't always the block it started in
+        let block = self.builder.get_insert_block().expect("Block not set");
+
+        match value {
+            Some(value) => result.incoming.push((value, block)),
+            None => result.all_valued = false,
+        }
+
+        self.builder.build_unconditional_branch(merge);
+    }
+
+    pub(crate) fn gen_while(&mut self, stmt: &WhileLoop) -> Result<()> {
+        let llvm_func = self
+            .bb()
+            .get_parent()
+            .expect("Basic block is not in a function!");
+
+        let cond_block = self.context.append_basic_block(llvm_func, "while.cond");
+        let body_block = self.context.append_basic_block(llvm_func, "while.body");
+        let end_block = self.context.append_basic_block(llvm_func, "while.end");
+
+        self.builder.build_unconditional_branch(cond_block);
+        self.position_at(cond_block);
+
+        let condition = self.gen_condition(&stmt.condition)?;
+        self.builder
+            .build_conditional_branch(condition, body_block, end_block);
+
+        self.position_at(body_block);
+        self.gen_loop_body(stmt.label.as_ref(), end_block, cond_block, &stmt.body.value);
+        self.builder.build_unconditional_branch(cond_block);
+
+        self.position_at(end_block);
+
+        Ok(())
+    }
+
+    pub(crate) fn gen_break(&mut self, stmt: &BreakStatement) -> Result<()> {
+        let span = stmt.token.as_span();
+        let i = self.find_loop(span, stmt.label.as_ref(), "break")?;
+
+        self.gen_jump(self.loops[i].break_block);
+        Ok(())
+    }
+
+    pub(crate) fn gen_continue(&mut self, stmt: &ContinueStatement) -> Result<()> {
+        let span = stmt.token.as_span();
+        let i = self.find_loop(span, stmt.label.as_ref(), "continue")?;
+
+        self.gen_jump(self.loops[i].continue_block);
+        Ok(())
+    }
+
+    /// Finds the loop a `break` or `continue` refers to: the loop named by `label`, or the
+    /// innermost one. Returns its index in the loop stack.
+    fn find_loop(&mut self, span: Span, label: Option<&LoopLabel>, keyword: &str) -> Result<usize> {
+        let found = match label {
+            Some(label) => self
+                .loops
+                .iter()
+                .rposition(|l| l.label.as_deref() == Some(label.value.as_str())),
+            None => self.loops.len().checked_sub(1),
+        };
+
+        match (found, label) {
+            (Some(i), _) => Ok(i),
+            (None, Some(label)) => {
+                let msg = format!("Use of undeclared label `{}`", label);
+                raise!(@log Error => self, msg, label.as_span());
+                Err(crate::Diagnostic::Caught(label.as_span()))
+            }
+            (None, None) => {
+                let msg = format!("`{}` outside of a loop", keyword);
+                raise!(@log Error => self, msg, span);
+                Err(crate::Diagnostic::Caught(span))
+            }
+        }
+    }
+
+    /// Branches to `block`. Anything generated after the jump goes in a new block with no
+    /// predecessors, since the current one is terminated.
+    pub(crate) fn gen_jump(&mut self, block: BasicBlock<'ctx>) {
+        let llvm_func = self
+            .bb()
+            .get_parent()
+            .expect("Basic block is not in a function!");
+
+        self.builder.build_unconditional_branch(block);
+
+        let dead = self.context.append_basic_block(llvm_func, "unreachable");
+        self.position_at(dead);
+    }
+
+    /// Generates a loop body with the loop pushed onto the loop stack
+    fn gen_loop_body(
+        &mut self,
+        label: Option<&Label>,
+        break_block: BasicBlock<'ctx>,
+        continue_block: BasicBlock<'ctx>,
+        body: &[Statement],
+    ) {
+        self.loops.push(LoopBlocks {
+            label: label.map(|l| l.name.value.clone()),
+            break_block,
+            continue_block,
+        });
+        self.gen_block(body, None);
+        self.loops.pop();
+    }
+
+    /// Generates a for loop as a counted loop
+    ///
+    /// Ranges count from the start to the end, which is only evaluated once. Slices count up to
+    /// the length in the slice struct, and load each element through its pointer.
+    pub(crate) fn gen_for(&mut self, stmt: &ForLoop) -> Result<()> {
+        let llvm_func = self
+            .bb()
+            .get_parent()
+            .expect("Basic block is not in a function!");
+        let name = stmt.ident.value.as_str();
+
+        let (counter_ty, start, end, slice) = match &stmt.iter {
+            ForIter::Range(range) => {
+                let (Some(start_expr), Some(end_expr)) = (&range.left, &range.right) else {
+                    unreachable!("for loop ranges always have both ends");
+                };
+
+                // An untyped literal bound takes the type of the other bound, so `0..n` counts
+                // in the type of `n`
+                let (start, end) =
+                    if untyped_int(start_expr).is_some() && untyped_int(end_expr).is_none() {
+                        let end = self.gen_expression(end_expr)?;
+                        (self.gen_expression_as(start_expr, &end.ty)?, end)
+                    } else {
+                        let start = self.gen_expression(start_expr)?;
+                        let end = self.gen_expression_as(end_expr, &start.ty)?;
+                        (start, end)
+                    };
+
+                // Both bounds need the same sign, like the sides of a `<`
+                let same_sign = start.ty.flags.contains(TypeFlags::SIGNED)
+                    == end.ty.flags.contains(TypeFlags::SIGNED);
+                match (start.ty.llvm, end.ty.llvm) {
+                    (AnyTypeEnum::IntType(l), AnyTypeEnum::IntType(r)) if l == r && same_sign => (),
+                    _ => {
+                        return Err(
+                            raise!(@mismatch => self, start_expr.as_span(), end_expr.as_span()),
+                        )
+                        .caught()
+                    }
+                }
+
+                (
+                    start.ty,
+                    start.llvm.into_int_value(),
+                    end.llvm.into_int_value(),
+                    None,
+                )
+            }
+            ForIter::Slice(expr) => {
+                let slice = self.gen_expression(expr)?;
+                if !slice.ty.flags.contains(TypeFlags::SLICE) {
+                    return Err(raise!(@mismatch => self, expr.as_span())).caught();
+                }
+
+                let elem_ty = *slice
+                    .ty
+                    .base_type
+                    .clone()
+                    .expect("Slice without an element type!");
+
+                let slice = slice.llvm.into_struct_value();
+                let len = self
+                    .builder
+                    .build_extract_value(slice, 0, "len")
+                    .expect("Slice without a length!")
+                    .into_int_value();
+                let ptr = self
+                    .builder
+                    .build_extract_value(slice, 1, "ptr")
+                    .expect("Slice without a pointer!")
+                    .into_pointer_value();
+
+                (
+                    len.get_type().to_type(),
+                    len.get_type().const_zero(),
+                    len,
+                    Some((ptr, elem_ty)),
+                )
+            }
+        };
+
+        let counter_llvm: BasicTypeEnum = counter_ty
+            .llvm
+            .try_into()
+            .expect("Unable to get basic type");
+        let counter = self.insert_alloca(&counter_ty, name);
+        self.builder.build_store(counter, start);
+
+        let cond_block = self.context.append_basic_block(llvm_func, "for.cond");
+        let body_block = self.context.append_basic_block(llvm_func, "for.body");
+        let step_block = self.context.append_basic_block(llvm_func, "for.step");
+        let end_block = self.context.append_basic_block(llvm_func, "for.end");
+
+        self.builder.build_unconditional_branch(cond_block);
+        self.position_at(cond_block);
+
+        let index = self
+            .builder
+            .build_load(counter_llvm, counter, EMPTY_NAME)
+            .into_int_value();
+        // Slice lengths are unsigned, range bounds count with the sign of their type
+        let predicate = if counter_ty.flags.contains(TypeFlags::SIGNED) {
+            IntPredicate::SLT
+        } else {
+            IntPredicate::ULT
+        };
+        let in_bounds = self
+            .builder
+            .build_int_compare(predicate, index, end, EMPTY_NAME);
+        self.builder
+            .build_conditional_branch(in_bounds, body_block, end_block);
+
+        self.position_at(body_block);
+        self.symbol_table.push_scope();
+
+        match slice {
+            Some((ptr, elem_ty)) => {
+                let elem_llvm: BasicTypeEnum =
+                    elem_ty.llvm.try_into().expect("Unable to get basic type");
+                let elem_ptr = unsafe {
+                    self.builder
+                        .build_in_bounds_gep(elem_llvm, ptr, &[index], EMPTY_NAME)
+                };
+                let elem = self.builder.build_load(elem_llvm, elem_ptr, name);
+
+                let alloc = self.insert_alloca(&elem_ty, name);
+                self.builder.build_store(alloc, elem);
+                self.symbol_table
+                    .insert_variable(name, Variable { ty: elem_ty, alloc });
+            }
+            None => {
+                self.symbol_table.insert_variable(
+                    name,
+                    Variable {
+                        ty: counter_ty,
+                        alloc: counter,
+                    },
+                );
+            }
+        }
+
+        self.gen_loop_body(stmt.label.as_ref(), end_block, step_block, &stmt.body.value);
+        self.symbol_table.pop_scope();
+        self.builder.build_unconditional_branch(step_block);
+
+        self.position_at(step_block);
+        let index = self
+            .builder
+            .build_load(counter_llvm, counter, EMPTY_NAME)
+            .into_int_value();
+        let next =
+            self.builder
+                .build_int_add(index, index.get_type().const_int(1, false), EMPTY_NAME);
+        self.builder.build_store(counter, next);
+        self.builder.build_unconditional_branch(cond_block);
+
+        self.position_at(end_block);
+
+        Ok(())
+    }
+
+    /// Generates the statements of a block in a new scope, and returns the value of its last
+    /// statement, if it has one. Statements that fail are logged and skipped.
+    pub(crate) fn gen_block(
+        &mut self,
+        body: &[Statement],
+        expected: Option<&Type<'ctx>>,
+    ) -> Option<Value<'ctx>> {
+        self.symbol_table.push_scope();
+
+        let mut value = None;
+        for (i, stmt) in body.iter().enumerate() {
+            if i == body.len() - 1 && stmt.has_value() {
+                value = attempt!(self, self.gen_statement(stmt, expected))
+                    .ok()
+                    .flatten();
+            } else {
+                let _ = attempt!(self, self.gen_statement(stmt, None));
+            }
+        }
+
+        self.symbol_table.pop_scope();
+        value
+    }
+
+    /// Generates `expr` as an `i1`. Integers are compared against zero, like in C.
+    pub(crate) fn gen_condition(&mut self, expr: &Expression) -> Result<IntValue<'ctx>> {
+        let value = self.gen_expression(expr)?;
+        self.condition_of(value, expr.as_span())
+    }
+
+    /// Converts a value to an `i1`, comparing integers against zero
+    fn condition_of(&mut self, value: Value<'ctx>, span: Span) -> Result<IntValue<'ctx>> {
+        match value.ty.llvm {
+            AnyTypeEnum::IntType(ty) if ty.get_bit_width() == 1 => Ok(value.llvm.into_int_value()),
+            AnyTypeEnum::IntType(ty) => Ok(self.builder.build_int_compare(
+                IntPredicate::NE,
+                value.llvm.into_int_value(),
+                ty.const_zero(),
+                "cond",
+            )),
+            _ => Err(raise!(@mismatch => self, span)).caught(),
+        }
+    }
+
+    /// Generates `&&` or `||`
+    ///
+    /// The right side is only evaluated if the left side doesn't decide the result, so
+    /// `n != 0 && total / n > 2` never divides by zero. The result comes from a phi of
+    /// the constant the left side decided, and the value of the right side.
+    pub(crate) fn gen_logical(
+        &mut self,
+        left: ValOrExpr<'ctx, '_>,
+        op: &tokens::Token,
+        right: &Expression,
+    ) -> Result<Value<'ctx>> {
+        let left = match left {
+            ValOrExpr::Expr(expr) => self.gen_condition(expr)?,
+            ValOrExpr::Val(value, span) => self.condition_of(value?, span)?,
+        };
+        let is_and = matches!(op, tokens::Tok![enum &&]);
+
+        // The left side can have branches of its own, so this isn't always the block it started in
+        let left_block = self.builder.get_insert_block().expect("Block not set");
+        let llvm_func = left_block
+            .get_parent()
+            .expect("Basic block is not in a function!");
+        let right_block = self.context.append_basic_block(llvm_func, "logic.rhs");
+        let end_block = self.context.append_basic_block(llvm_func, "logic.end");
+
+        if is_and {
+            self.builder
+                .build_conditional_branch(left, right_block, end_block);
+        } else {
+            self.builder
+                .build_conditional_branch(left, end_block, right_block);
+        }
+
+        self.position_at(right_block);
+        let right = self.gen_condition(right);
+        let right_end = self.builder.get_insert_block().expect("Block not set");
+        self.builder.build_unconditional_branch(end_block);
+
+        self.position_at(end_block);
+        let right = right?;
+
+        let bool_type = self.context.bool_type();
+        let decided = bool_type.const_int(!is_and as u64, false);
+        let phi = self.builder.build_phi(bool_type, "logic");
+        phi.add_incoming(&[(&decided, left_block), (&right, right_end)]);
+
+        Ok(phi.as_basic_value().as_value(bool_type.to_type()))
+    }
+
+    pub(crate) fn position_at(&mut self, block: inkwell::basic_block::BasicBlock<'ctx>) {
+        self.builder.position_at_end(block);
+        self.basic_block = Some(block);
+    }
+}
+
+/// Values of the branches of an if, and the blocks they jump to the merge from
+struct IfResult<'ctx> {
+    incoming: Vec<(Value<'ctx>, BasicBlock<'ctx>)>,
+    /// False if any branch has no value, in which case the if doesn't either
+    all_valued: bool,
+}
@@ -0,0 +1,182 @@
+use inkwell::{
+    types::{BasicTypeEnum, StructType},
+    values::IntValue,
+    AddressSpace,
+};
+use parsely_lexer::AsSpan;
+use parsely_parser::item::Enum;
+
+use crate::{
+    attempt,
+    llvm_value::{Type, TypeBuilder, Value},
+    module::{Module, EMPTY_NAME},
+    raise, Diagnostic, Result,
+};
+
+/// How the values of an enum are laid out
+///
+/// An enum without payloads is just its tag. A tagged union is a struct of the tag and storage
+/// for the largest payload, aligned like the most aligned one, so it matches a C struct holding
+/// a tag and a union of the payloads.
+#[derive(Debug, Clone)]
+pub(crate) struct EnumLayout<'ctx> {
+    pub variants: Vec<VariantLayout<'ctx>>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct VariantLayout<'ctx> {
+    pub name: String,
+    pub discriminant: i128,
+    /// Types of the payload
+    pub fields: Vec<Type<'ctx>>,
+    /// The payload as a struct, if the variant has one
+    pub payload: Option<StructType<'ctx>>,
+}
+
+impl<'ctx> Module<'ctx> {
+    /// Declares the type of an enum, and its layout for matching
+    ///
+    /// A tagged union is declared before its payloads, so they can point to it.
+    pub(crate) fn gen_enum(&mut self, e: &Enum) -> Result<()> {
+        let name = e.ident.value.as_str();
+        // The checker has made sure the tag is 1 to 64 bits wide, and every discriminant fits
+        let tag_llvm = match &e.tag {
+            Some(tag) => self.context.custom_width_int_type(tag.ty.size as u32),
+            None => self.context.i32_type(),
+        };
+        let discriminants = e.discriminants();
+
+        let ty = if e.is_tagged_union() {
+            self.context.opaque_struct_type(name).to_type()
+        } else {
+            tag_llvm.signed(true)
+        };
+        let ty = Type {
+            name: Some(name.to_string()),
+            ..ty
+        };
+
+        if !self.symbol_table.insert_type(name, ty.clone()) {
+            let msg = format!("Type `{}` is already defined", name);
+            raise!(@log Error => self, msg, e.ident.as_span());
+            return Err(Diagnostic::Caught(e.ident.as_span()));
+        }
+
+        let mut failed = false;
+        let mut variants = Vec::new();
+        for (variant, &discriminant) in e.variants.value.iter().zip(&discriminants) {
+            let mut fields = Vec::new();
+            for field in variant.fields.iter().flat_map(|f| f.value.iter()) {
+                match attempt!(self, self.gen_type(field)) {
+                    Ok(field_ty) if !field_ty.is_sized() => {
+                        let msg = format!(
+                            "Payload of `{}` has a type without a size",
+                            variant.ident.value
+                        );
+                        raise!(@log Error => self, msg, variant.ident.as_span());
+                        failed = true;
+                    }
+                    Ok(field_ty) => fields.push(field_ty),
+                    Err(_) => failed = true,
+                }
+            }
+
+            let payload = variant.fields.as_ref().map(|_| {
+                let llvm_fields: Vec<BasicTypeEnum> = fields
+                    .iter()
+                    .map(|f| f.llvm.try_into().expect("Unable to get basic type"))
+                    .collect();
+
+                let payload = self
+                    .context
+                    .opaque_struct_type(&format!("{}.{}", name, variant.ident.value));
+                payload.set_body(&llvm_fields, false);
+                payload
+            });
+
+            variants.push(VariantLayout {
+                name: variant.ident.value.clone(),
+                discriminant,
+                fields,
+                payload,
+            });
+        }
+
+        if failed {
+            return Err(Diagnostic::Caught(e.ident.as_span()));
+        }
+
+        if e.is_tagged_union() {
+            let (size, align) = variants
+                .iter()
+                .filter_map(|v| v.payload)
+                .map(|p| {
+                    (
+                        self.target_data.get_abi_size(&p),
+                        self.target_data.get_abi_alignment(&p) as u64,
+                    )
+                })
+                .fold((0, 1), |(size, align), (s, a)| (size.max(s), align.max(a)));
+
+            // Storage is made of integers as wide as the alignment, so it's aligned the same
+            let unit = self.context.custom_width_int_type(align as u32 * 8);
+            let storage = unit.array_type(size.div_ceil(align) as u32);
+
+            ty.llvm
+                .into_struct_type()
+                .set_body(&[tag_llvm.into(), storage.into()], false);
+        }
+
+        self.symbol_table.insert_enum(name, EnumLayout { variants });
+
+        Ok(())
+    }
+
+    /// Gets the tag of an enum value
+    pub(crate) fn gen_tag(&mut self, value: &Value<'ctx>) -> IntValue<'ctx> {
+        if value.llvm.is_struct_value() {
+            self.builder
+                .build_extract_value(value.llvm.into_struct_value(), 0, "tag")
+                .expect("Tagged union without a tag!")
+                .into_int_value()
+        } else {
+            value.llvm.into_int_value()
+        }
+    }
+
+    /// Loads a field of the payload of `variant`, from a tagged union `value`. The tag isn't
+    /// checked.
+    pub(crate) fn gen_payload_field(
+        &mut self,
+        value: &Value<'ctx>,
+        variant: &VariantLayout<'ctx>,
+        field: usize,
+    ) -> Value<'ctx> {
+        let payload_ty = variant.payload.expect("Variant without a payload!");
+
+        let alloc = self.insert_alloca(&value.ty, EMPTY_NAME);
+        self.builder.build_store(alloc, value.llvm);
+
+        let storage = self
+            .builder
+            .build_struct_gep(value.ty.llvm.into_struct_type(), alloc, 1, EMPTY_NAME)
+            .expect("Tagged union without storage!");
+        let payload = self.builder.build_pointer_cast(
+            storage,
+            payload_ty.ptr_type(AddressSpace::default()),
+            EMPTY_NAME,
+        );
+        let ptr = self
+            .builder
+            .build_struct_gep(payload_ty, payload, field as u32, EMPTY_NAME)
+            .expect("Payload field out of bounds!");
+
+        let ty = variant.fields[field].clone();
+        let llvm: BasicTypeEnum = ty.llvm.try_into().expect("Unable to get basic type");
+
+        Value {
+            llvm: self.builder.build_load(llvm, ptr, EMPTY_NAME),
+            ty,
+        }
+    }
+}
@@ -0,0 +1,293 @@
+//! Exhaustiveness checking for `match`
+//!
+//! Patterns are first lowered to [`Pat`]s, checking them against a [`Space`] that describes the
+//! values of the type being matched. The check then splits the space up by the constructors the
+//! patterns use (integer ranges, variants, ...) and looks for a part no arm covers.
+
+use parsely_lexer::AsSpan;
+use parsely_parser::pattern::Pattern;
+
+use crate::Diagnostic;
+
+/// The values of a type, as far as matching is concerned
+#[derive(Debug, Clone)]
+pub(crate) enum Space {
+    /// Integers from `min` to `max`, inclusive
+    Int {
+        min: i128,
+        max: i128,
+    },
+    Bool,
+    /// An enum or tagged union, with the payload of each variant
+    Enum {
+        name: String,
+        variants: Vec<(String, Vec<Space>)>,
+    },
+    Struct {
+        name: String,
+        fields: Vec<(String, Space)>,
+    },
+    /// Values that can only be matched by a wildcard or binding
+    Opaque,
+}
+
+impl Space {
+    /// The integers of `bits` bits. Wider ones than an `i128` holds are cut off at its limits,
+    /// past the values any pattern can be written for.
+    pub fn int(bits: u32, signed: bool) -> Space {
+        let (min, max) = match (bits, signed) {
+            (0, _) => (0, 0),
+            (128.., true) => (i128::MIN, i128::MAX),
+            (_, true) => (-(1 << (bits - 1)), (1 << (bits - 1)) - 1),
+            (127.., false) => (0, i128::MAX),
+            (_, false) => (0, (1 << bits) - 1),
+        };
+
+        Space::Int { min, max }
+    }
+}
+
+/// A pattern, checked against the space it matches in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Pat {
+    Wild,
+    /// Integers from the first value to the second, inclusive
+    Int(i128, i128),
+    Bool(bool),
+    /// Index of the variant, and patterns for its payload
+    Variant(usize, Vec<Pat>),
+    /// Patterns for every field, in declaration order
+    Struct(Vec<Pat>),
+}
+
+/// Lowers `pattern` to a [`Pat`] over `space`
+///
+/// Fails if the pattern can't match a value of the type, or names a variant or field that
+/// doesn't exist.
+pub(crate) fn lower_pattern(pattern: &Pattern, space: &Space) -> Result<Pat, Diagnostic> {
+    let mismatch = || Diagnostic::IncompatibleType(pattern.as_span());
+
+    match (pattern, space) {
+        (Pattern::Wildcard(_) | Pattern::Binding(_), _) => Ok(Pat::Wild),
+        (Pattern::Int(i), Space::Int { min, max }) => {
+            let value = i.value();
+            if value < *min || value > *max {
+                return Err(mismatch());
+            }
+
+            Ok(Pat::Int(value, value))
+        }
+        (Pattern::Char(c), Space::Int { min, max }) => {
+            let value = c.value as i128;
+            if value < *min || value > *max {
+                return Err(mismatch());
+            }
+
+            Ok(Pat::Int(value, value))
+        }
+        (Pattern::Range(r), Space::Int { min, max }) => {
+            let start = r.start.value();
+            let end = r.end.value() - 1;
+            if start > end || start < *min || end > *max {
+                return Err(mismatch());
+            }
+
+            Ok(Pat::Int(start, end))
+        }
+        (Pattern::Bool(b), Space::Bool) => Ok(Pat::Bool(b.value)),
+        (Pattern::Variant(v), Space::Enum { name, variants }) => {
+            if v.ty.value != *name {
+                return Err(mismatch());
+            }
+
+            let Some(index) = variants
+                .iter()
+                .position(|(variant, _)| *variant == v.variant.value)
+            else {
+                return Err(Diagnostic::SymbolNotFound(v.variant.clone()));
+            };
+            let payload = &variants[index].1;
+
+            let fields = match &v.fields {
+                Some(fields) if fields.value.len() != payload.len() => return Err(mismatch()),
+                Some(fields) => fields
+                    .value
+                    .iter()
+                    .zip(payload)
+                    .map(|(field, space)| lower_pattern(field, space))
+                    .collect::<Result<_, _>>()?,
+                None => vec![Pat::Wild; payload.len()],
+            };
+
+            Ok(Pat::Variant(index, fields))
+        }
+        (Pattern::Struct(s), Space::Struct { name, fields }) => {
+            if s.ty.value != *name {
+                return Err(mismatch());
+            }
+
+            let mut pats = vec![Pat::Wild; fields.len()];
+            for field in s.fields.value.iter() {
+                let Some(index) = fields.iter().position(|(f, _)| *f == field.ident.value) else {
+                    return Err(Diagnostic::SymbolNotFound(field.ident.clone()));
+                };
+
+                if let Some((_, pattern)) = &field.pattern {
+                    pats[index] = lower_pattern(pattern, &fields[index].1)?;
+                }
+            }
+
+            Ok(Pat::Struct(pats))
+        }
+        _ => Err(mismatch()),
+    }
+}
+
+/// Returns a value that none of `arms` match, written as a pattern, or `None` if the arms
+/// cover all of `space`
+///
+/// Arms with guards might not match, so they should be left out.
+pub(crate) fn missing_pattern(space: &Space, arms: &[Pat]) -> Option<String> {
+    let rows: Vec<_> = arms.iter().map(|arm| vec![arm.clone()]).collect();
+
+    uncovered(&rows, std::slice::from_ref(space)).map(|mut witness| witness.remove(0))
+}
+
+/// A set of values in a space that patterns can either cover completely, or not at all
+#[derive(Debug, Clone, Copy)]
+enum Ctor {
+    Range(i128, i128),
+    Bool(bool),
+    Variant(usize),
+    Struct,
+    /// Anything in an opaque space
+    Any,
+}
+
+impl Ctor {
+    /// Spaces of the values inside the constructor
+    fn fields(self, space: &Space) -> Vec<Space> {
+        match (self, space) {
+            (Ctor::Variant(i), Space::Enum { variants, .. }) => variants[i].1.clone(),
+            (Ctor::Struct, Space::Struct { fields, .. }) => {
+                fields.iter().map(|(_, space)| space.clone()).collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Formats a value built with this constructor as a pattern
+    fn witness(self, space: &Space, fields: Vec<String>) -> String {
+        match (self, space) {
+            (Ctor::Range(lo, hi), Space::Int { min, max }) => match (lo == *min, hi == *max) {
+                _ if lo == hi => lo.to_string(),
+                (true, true) => "_".to_string(),
+                (false, true) => format!("{}..", lo),
+                (true, false) => format!("..{}", hi + 1),
+                (false, false) => format!("{}..{}", lo, hi + 1),
+            },
+            (Ctor::Bool(b), _) => b.to_string(),
+            (Ctor::Variant(i), Space::Enum { name, variants }) if fields.is_empty() => {
+                format!("{}::{}", name, variants[i].0)
+            }
+            (Ctor::Variant(i), Space::Enum { name, variants }) => {
+                format!("{}::{}({})", name, variants[i].0, fields.join(", "))
+            }
+            (
+                Ctor::Struct,
+                Space::Struct {
+                    name,
+                    fields: names,
+                },
+            ) => {
+                let fields: Vec<_> = names
+                    .iter()
+                    .zip(fields)
+                    .map(|((name, _), field)| format!("{}: {}", name, field))
+                    .collect();
+                format!("{} {{ {} }}", name, fields.join(", "))
+            }
+            _ => "_".to_string(),
+        }
+    }
+}
+
+/// Constructors that split up `space` so that each head pattern in `rows` covers some of them
+/// completely, and the rest not at all
+fn constructors(space: &Space, rows: &[Vec<Pat>]) -> Vec<Ctor> {
+    match space {
+        Space::Int { min, max } => {
+            // Every range starts a new part, and ends one just after it
+            let mut bounds = vec![*min];
+            for row in rows {
+                if let Pat::Int(lo, hi) = row[0] {
+                    bounds.push(lo);
+                    if hi < *max {
+                        bounds.push(hi + 1);
+                    }
+                }
+            }
+            bounds.sort_unstable();
+            bounds.dedup();
+
+            let ends = bounds.iter().skip(1).map(|b| b - 1).chain([*max]);
+            bounds
+                .iter()
+                .zip(ends)
+                .map(|(lo, hi)| Ctor::Range(*lo, hi))
+                .collect()
+        }
+        Space::Bool => vec![Ctor::Bool(false), Ctor::Bool(true)],
+        Space::Enum { variants, .. } => (0..variants.len()).map(Ctor::Variant).collect(),
+        Space::Struct { .. } => vec![Ctor::Struct],
+        Space::Opaque => vec![Ctor::Any],
+    }
+}
+
+/// The rest of `row` if its head covers `ctor`, with the head replaced by its fields
+fn specialize(row: &[Pat], ctor: Ctor, arity: usize) -> Option<Vec<Pat>> {
+    let (head, rest) = row.split_first()?;
+
+    let mut fields = match (head, ctor) {
+        (Pat::Wild, _) => vec![Pat::Wild; arity],
+        (Pat::Int(a, b), Ctor::Range(lo, hi)) if *a <= lo && hi <= *b => Vec::new(),
+        (Pat::Bool(a), Ctor::Bool(b)) if *a == b => Vec::new(),
+        (Pat::Variant(a, fields), Ctor::Variant(b)) if *a == b => fields.clone(),
+        (Pat::Struct(fields), Ctor::Struct) => fields.clone(),
+        _ => return None,
+    };
+
+    fields.extend_from_slice(rest);
+    Some(fields)
+}
+
+/// Finds values, one per column, that no row matches
+///
+/// Each row matches a value in each of `spaces`. Returns the values as patterns.
+fn uncovered(rows: &[Vec<Pat>], spaces: &[Space]) -> Option<Vec<String>> {
+    let Some((space, rest)) = spaces.split_first() else {
+        return rows.is_empty().then(Vec::new);
+    };
+
+    for ctor in constructors(space, rows) {
+        let fields = ctor.fields(space);
+        let arity = fields.len();
+
+        let specialized: Vec<_> = rows
+            .iter()
+            .filter_map(|row| specialize(row, ctor, arity))
+            .collect();
+
+        let spaces: Vec<_> = fields.into_iter().chain(rest.iter().cloned()).collect();
+
+        if let Some(mut witness) = uncovered(&specialized, &spaces) {
+            let tail = witness.split_off(arity);
+
+            let mut values = vec![ctor.witness(space, witness)];
+            values.extend(tail);
+            return Some(values);
+        }
+    }
+
+    None
+}
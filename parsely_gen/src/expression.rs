@@ -1,19 +1,34 @@
 use inkwell::{
+    module::Linkage,
     types::{AnyTypeEnum, BasicTypeEnum},
-    values::{AnyValue, BasicValueEnum},
+    values::{BasicValue, BasicValueEnum, IntValue, PointerValue},
+    AddressSpace, IntPredicate,
 };
-use parsely_lexer::{AsSpan, Span};
-use parsely_parser::expression::{
-    BinOperator, BinOrUnary, ByOrImpl, Expression, Literal, UnaryOperator,
+use parsely_lexer::{
+    tokens::{self, FloatSuffix},
+    AsSpan, Span,
+};
+use parsely_parser::{
+    expression::{Assign, Expression, Literal, LiteralInt, UnaryOp},
+    pattern::Match,
+    statement::{IfStatement, Statement},
+    Braces,
 };
 
 use crate::{
-    attempt,
-    llvm_value::{AsValue, TypeBuilder, TypeFlags, Value},
+    llvm_value::{AsValue, Type, TypeBuilder, TypeFlags, Value},
     module::{Module, EMPTY_NAME},
     raise, ErrorHelper, Result,
 };
 
+/// Somewhere a value is stored, that can be pointed to
+pub(crate) struct Place<'ctx> {
+    pub ptr: PointerValue<'ctx>,
+    pub ty: Type<'ctx>,
+    /// False if the place is behind a `@` pointer
+    pub mutable: bool,
+}
+
 pub enum ValOrExpr<'ctx, 'a> {
     Val(Result<Value<'ctx>>, Span),
     Expr(&'a Expression),
@@ -40,248 +55,620 @@ impl<'ctx, 'a> AsSpan for ValOrExpr<'ctx, 'a> {
     }
 }
 
-pub enum Op<'ctx, 'a> {
-    Bin {
-        left: ValOrExpr<'ctx, 'a>,
-        op: BinOperator,
-        right: &'a Expression,
-    },
-    Uni {
-        expr: ValOrExpr<'ctx, 'a>,
-        op: UnaryOperator,
-    },
-}
+impl<'ctx> Module<'ctx> {
+    pub(crate) fn gen_expression(&mut self, expr: &Expression) -> Result<Value<'ctx>> {
+        match expr {
+            Expression::Literal(lit) => self.gen_literal(lit),
+            Expression::Ident(_) | Expression::Deref(_) => {
+                let place = self.gen_place(expr)?;
+                let llvm: BasicTypeEnum =
+                    place.ty.llvm.try_into().expect("Unable to get basic type");
 
-impl<'ctx, 'a> From<&'a BinOrUnary> for Op<'ctx, 'a> {
-    fn from(value: &'a BinOrUnary) -> Self {
-        match value {
-            BinOrUnary::BinOp(b) => Op::Bin {
-                left: ValOrExpr::Expr(&b.left),
-                right: &b.right,
-                op: b.op.clone(),
-            },
-            BinOrUnary::UnaryOp(u) => Op::Uni {
-                expr: ValOrExpr::Expr(&u.expr),
-                op: u.op.clone(),
+                Ok(Value {
+                    llvm: self.builder.build_load(llvm, place.ptr, EMPTY_NAME),
+                    ty: place.ty,
+                })
+            }
+            Expression::Parens(p) => self.gen_expression(&p.value),
+            Expression::Unary(u) => self.gen_unary(u, None),
+            Expression::Field(f) => self.gen_field(f),
+            Expression::StructLiteral(lit) => self.gen_struct_literal(lit),
+            Expression::AddressOf(a) => {
+                let place = self.gen_place(&a.expr)?;
+                if a.is_mutable() && !place.mutable {
+                    let msg = "Can't take a `#` pointer through a `@` pointer";
+                    raise!(@log Error => self, msg, expr.as_span());
+                    return Err(crate::Diagnostic::Caught(expr.as_span()));
+                }
+
+                Ok(Value {
+                    llvm: place.ptr.into(),
+                    ty: self.pointer_type(place.ty).mutable(a.is_mutable()),
+                })
+            }
+            Expression::Match(m) => self.gen_match_expression(m, None),
+            Expression::BinOp(b) => self.gen_binary(ValOrExpr::Expr(&b.left), &b.op, &b.right),
+            Expression::Assign(a) => self.gen_assign(a),
+            Expression::If(stmt) => self.gen_if_expression(stmt, None),
+            Expression::Block(body) => self.gen_block_expression(body, None),
+            Expression::Call(call) => match self.gen_call(call)? {
+                Some(value) => Ok(value),
+                None => {
+                    let msg = "Function doesn't return a value";
+                    raise!(@log Error => self, msg, call.as_span());
+                    Err(crate::Diagnostic::Caught(call.as_span()))
+                }
             },
+            Expression::ArrayInit(_) | Expression::Index(_) | Expression::Slice(_) => {
+                let msg = "Arrays aren't supported by code generation yet";
+                raise!(@log Error => self, msg, expr.as_span());
+                Err(crate::Diagnostic::Caught(expr.as_span()))
+            }
         }
     }
-}
 
-impl<'ctx, 'a> From<(Result<Value<'ctx>>, Span, &'a ByOrImpl)> for Op<'ctx, 'a> {
-    fn from(value: (Result<Value<'ctx>>, Span, &'a ByOrImpl)) -> Self {
-        match &value.2 {
-            ByOrImpl::OpBy(b) => Op::Bin {
-                left: ValOrExpr::Val(value.0, value.1),
-                right: &b.expr,
-                op: b.op.clone(),
-            },
-            ByOrImpl::UnaryImpl(u) => Op::Uni {
-                expr: ValOrExpr::Val(value.0, value.1),
-                op: u.op.clone(),
-            },
+    /// Generates `expr` where a value of type `ty` is expected
+    ///
+    /// Integer literals without a suffix are `int64` on their own, here they take the type
+    /// that's expected instead, so `int32 x = 1;` works, as long as they fit in it. A negated
+    /// literal is a literal of its own, so `int8 x = -128;` fits as well. String literals become a `str` where
+    /// one is expected, and values become an optional where one is expected, with `none` being an
+    /// optional without a value. The values that `if` branches, match arms and blocks end with
+    /// are expected to have the type as well, and so are the operands of `-` and `~`.
+    pub(crate) fn gen_expression_as(
+        &mut self,
+        expr: &Expression,
+        ty: &Type<'ctx>,
+    ) -> Result<Value<'ctx>> {
+        match (expr, untyped_int(expr), ty.llvm) {
+            (_, Some(value), AnyTypeEnum::IntType(int)) if int.get_bit_width() > 1 => {
+                self.gen_int_constant(expr.as_span(), value, false, ty)
+            }
+            (Expression::Unary(u), _, AnyTypeEnum::IntType(_))
+                if matches!(u.op, tokens::Tok![enum -])
+                    && ty.flags.contains(TypeFlags::SIGNED)
+                    && untyped_int(&u.expr).is_some() =>
+            {
+                let value = untyped_int(&u.expr).expect("Checked above!");
+                self.gen_int_constant(expr.as_span(), value, true, ty)
+            }
+            (Expression::Parens(p), ..) => self.gen_expression_as(&p.value, ty),
+            (Expression::Literal(Literal::String(s)), ..) if is_str(ty) => {
+                Ok(self.gen_str(s.value.value.as_bytes(), ty))
+            }
+            (Expression::If(stmt), ..) => self.gen_if_expression(stmt, Some(ty)),
+            (Expression::Match(m), ..) => self.gen_match_expression(m, Some(ty)),
+            (Expression::Block(body), ..) => self.gen_block_expression(body, Some(ty)),
+            (Expression::Literal(Literal::None(_)), ..)
+                if ty.flags.contains(TypeFlags::OPTIONAL) =>
+            {
+                Ok(ty.llvm.into_struct_type().const_zero().as_value(ty.clone()))
+            }
+            _ if ty.flags.contains(TypeFlags::OPTIONAL) => self.gen_some(expr, ty),
+            (Expression::Unary(u), ..) => self.gen_unary(u, Some(ty)),
+            _ => self.gen_expression(expr),
         }
     }
-}
 
-impl<'ctx> Module<'ctx> {
-    pub(crate) fn gen_expression(&mut self, expr: &Expression) -> Result<Value<'ctx>> {
-        match expr {
-            Expression::Value(lit) => self.gen_literal(&lit.lit),
-            Expression::ValueOf(v) => {
-                let Some(var) = self.symbol_table.find_variable(&v.ident.value) else {
-                    return Err(raise!(@not_found => self, v.ident.clone())).caught();
-                };
+    /// Generates an integer literal of `magnitude`, negated if `negative`, as the integer type
+    /// `ty`. It has to fit, rather than being truncated.
+    fn gen_int_constant(
+        &mut self,
+        span: Span,
+        magnitude: u64,
+        negative: bool,
+        ty: &Type<'ctx>,
+    ) -> Result<Value<'ctx>> {
+        let int = ty.llvm.into_int_type();
+        let bits = int.get_bit_width();
+        let signed = ty.flags.contains(TypeFlags::SIGNED);
+
+        // Signed integers go one further when negative, like -128 in 8 bits
+        let width = bits - signed as u32;
+        let fits = if negative {
+            width >= 64 || magnitude <= 1 << width
+        } else {
+            magnitude.checked_shr(width).unwrap_or(0) == 0
+        };
+
+        if !fits {
+            let msg = format!(
+                "`{}{}` doesn't fit in a{} {}-bit integer",
+                if negative { "-" } else { "" },
+                magnitude,
+                if signed { " signed" } else { "n unsigned" },
+                bits
+            );
+            raise!(@log Error => self, msg, span);
+            return Err(crate::Diagnostic::Caught(span));
+        }
+
+        let value = if negative {
+            magnitude.wrapping_neg()
+        } else {
+            magnitude
+        };
+        // A negative value is sign extended into integers wider than 64 bits
+        Ok(int.const_int(value, negative).as_value(ty.clone()))
+    }
 
-                let value = self.builder.build_load::<BasicTypeEnum>(
-                    var.ty.llvm.try_into().expect("Unable to get as basic type"),
-                    var.alloc,
-                    EMPTY_NAME,
+    /// Generates `expr` as the optional `ty`, holding its value unless it's an optional already
+    fn gen_some(&mut self, expr: &Expression, ty: &Type<'ctx>) -> Result<Value<'ctx>> {
+        let inner = ty
+            .base_type
+            .as_deref()
+            .expect("Optional without an inner type!");
+        let value = self.gen_expression_as(expr, inner)?;
+
+        // Anything else is left for the caller to check against `ty`
+        if value.ty.flags.contains(TypeFlags::OPTIONAL) || !value.ty.assignable_to(inner) {
+            return Ok(value);
+        }
+
+        let has = self.context.bool_type().const_int(1, false);
+        let optional = self
+            .builder
+            .build_insert_value(ty.llvm.into_struct_type().get_undef(), has, 0, EMPTY_NAME)
+            .expect("Optional without a flag!");
+        let optional = self
+            .builder
+            .build_insert_value(optional, value.llvm, 1, EMPTY_NAME)
+            .expect("Optional without a value!");
+
+        Ok(optional.as_basic_value_enum().as_value(ty.clone()))
+    }
+
+    /// Generates an `if` that has a value, which every branch needs to end with
+    fn gen_if_expression(
+        &mut self,
+        stmt: &IfStatement,
+        expected: Option<&Type<'ctx>>,
+    ) -> Result<Value<'ctx>> {
+        match self.gen_if(stmt, expected)? {
+            Some(value) => Ok(value),
+            None => {
+                let msg = "`if` branches don't all have a value of the same type";
+                raise!(@log Error => self, msg, stmt.as_span());
+                Err(crate::Diagnostic::Caught(stmt.as_span()))
+            }
+        }
+    }
+
+    /// Generates a match that has a value, which every arm needs to have
+    fn gen_match_expression(
+        &mut self,
+        m: &Match,
+        expected: Option<&Type<'ctx>>,
+    ) -> Result<Value<'ctx>> {
+        match self.gen_match(m, expected)? {
+            Some(value) => Ok(value),
+            None => {
+                let msg = "Match arms don't all have a value of the same type";
+                raise!(@log Error => self, msg, m.as_span());
+                Err(crate::Diagnostic::Caught(m.as_span()))
+            }
+        }
+    }
+
+    /// Generates a block that ends with a value
+    fn gen_block_expression(
+        &mut self,
+        body: &Braces<Vec<Statement>>,
+        expected: Option<&Type<'ctx>>,
+    ) -> Result<Value<'ctx>> {
+        match self.gen_block(&body.value, expected) {
+            Some(value) => Ok(value),
+            None => {
+                raise!(@log Error => self, "Block doesn't end with a value", body.as_span());
+                Err(crate::Diagnostic::Caught(body.as_span()))
+            }
+        }
+    }
+
+    /// Generates `-`, `!` and `~`
+    ///
+    /// `-` needs a signed integer or a float, `~` an integer, and `!` a bool.
+    fn gen_unary(&mut self, u: &UnaryOp, expected: Option<&Type<'ctx>>) -> Result<Value<'ctx>> {
+        // A negated literal with a suffix is generated whole, so it reaches `-128i8`
+        if let (tokens::Tok![enum -], Expression::Literal(Literal::Int(i))) = (&u.op, &*u.expr) {
+            let ty = self.int_literal_type(i);
+            if i.value.suffix.is_some() && ty.flags.contains(TypeFlags::SIGNED) {
+                return self.gen_int_constant(
+                    u.op.as_span().join(i.value.as_span()),
+                    i.value.value,
+                    true,
+                    &ty,
                 );
+            }
+        }
 
-                Ok(Value {
-                    llvm: value,
-                    ty: var.ty.clone(),
-                })
+        // `-` and `~` have the type of their operand, so `int32 x = -1;` works
+        let value = match (&u.op, expected) {
+            (tokens::Tok![enum -] | tokens::Tok![enum ~], Some(ty)) => {
+                self.gen_expression_as(&u.expr, ty)?
+            }
+            _ => self.gen_expression(&u.expr)?,
+        };
+
+        let llvm: BasicValueEnum = match (&u.op, value.ty.llvm) {
+            (tokens::Tok![enum -], AnyTypeEnum::IntType(_))
+                if value.ty.flags.contains(TypeFlags::SIGNED) =>
+            {
+                self.builder
+                    .build_int_neg(value.llvm.into_int_value(), EMPTY_NAME)
+                    .into()
             }
-            Expression::Result(res) => {
-                let Some(first) = res.op.first.as_ref() else {
-                    raise!(@log Error => self, "Found empty expression", res.the_tok.as_span().join(res.of.as_span()));
-                    return Err(crate::Diagnostic::Caught(res.op.as_span()));
+            (tokens::Tok![enum -], AnyTypeEnum::FloatType(_)) => self
+                .builder
+                .build_float_neg(value.llvm.into_float_value(), EMPTY_NAME)
+                .into(),
+            (tokens::Tok![enum !], AnyTypeEnum::IntType(i)) if i.get_bit_width() == 1 => self
+                .builder
+                .build_not(value.llvm.into_int_value(), EMPTY_NAME)
+                .into(),
+            (tokens::Tok![enum ~], AnyTypeEnum::IntType(i)) if i.get_bit_width() > 1 => self
+                .builder
+                .build_not(value.llvm.into_int_value(), EMPTY_NAME)
+                .into(),
+            _ => return Err(raise!(@mismatch => self, u.expr.as_span())).caught(),
+        };
+
+        Ok(llvm.as_value(value.ty))
+    }
+
+    /// Generates an assignment, which has the value that was assigned
+    ///
+    /// The target is only evaluated once, so `*next() += 1` only calls `next` once.
+    fn gen_assign(&mut self, a: &Assign) -> Result<Value<'ctx>> {
+        let place = self.gen_place(&a.target)?;
+        if !place.mutable {
+            let msg = "Can't assign through a `@` pointer";
+            raise!(@log Error => self, msg, a.target.as_span());
+            return Err(crate::Diagnostic::Caught(a.target.as_span()));
+        }
+
+        let value = if a.is_compound() {
+            let llvm: BasicTypeEnum = place.ty.llvm.try_into().expect("Unable to get basic type");
+            let current = Value {
+                llvm: self.builder.build_load(llvm, place.ptr, EMPTY_NAME),
+                ty: place.ty.clone(),
+            };
+
+            let left = ValOrExpr::Val(Ok(current), a.target.as_span());
+            self.gen_binary(left, &a.op, &a.value)?
+        } else {
+            self.gen_expression_as(&a.value, &place.ty)?
+        };
+
+        if !value.ty.assignable_to(&place.ty) {
+            let span = a.target.as_span();
+            return Err(raise!(@mismatch => self, span, a.value.as_span())).caught();
+        }
+
+        self.builder.build_store(place.ptr, value.llvm);
+
+        Ok(value)
+    }
+
+    /// Gets a pointer to the place `expr` refers to: a variable, a field of a place, or what a
+    /// pointer points to
+    pub(crate) fn gen_place(&mut self, expr: &Expression) -> Result<Place<'ctx>> {
+        match expr {
+            Expression::Ident(ident) => {
+                let Some(var) = self.symbol_table.find_variable(&ident.value) else {
+                    return Err(raise!(@not_found => self, ident.clone())).caught();
                 };
-                let mut acc = self.gen_bin_or_uni(first.into());
-                let mut span = first.as_span();
 
-                for expr in res.op.rest.iter() {
-                    acc = attempt!(self, self.gen_bin_or_uni((acc, span, expr).into()));
-                    span = span.join(expr.as_span());
+                Ok(Place {
+                    ptr: var.alloc,
+                    ty: var.ty.clone(),
+                    mutable: true,
+                })
+            }
+            Expression::Deref(d) => {
+                let ptr = self.gen_expression(&d.expr)?;
+                if !ptr.ty.flags.contains(TypeFlags::POINTER) {
+                    return Err(raise!(@mismatch => self, d.expr.as_span())).caught();
                 }
 
-                if acc.is_err() {
-                    acc.caught_span(span)
-                } else {
-                    acc
-                }
+                Ok(Place {
+                    ptr: ptr.llvm.into_pointer_value(),
+                    mutable: ptr.ty.flags.contains(TypeFlags::MUTABLE),
+                    ty: *ptr.ty.base_type.expect("Pointer without a pointee!"),
+                })
+            }
+            Expression::Field(f) => self.gen_field_place(f),
+            Expression::Parens(p) => self.gen_place(&p.value),
+            _ => {
+                let msg = "Expected a variable, a field or a dereference";
+                raise!(@log Error => self, msg, expr.as_span());
+                Err(crate::Diagnostic::Caught(expr.as_span()))
             }
-            _ => unimplemented!(),
         }
     }
 
-    fn gen_bin_or_uni(&mut self, op: Op<'ctx, '_>) -> Result<Value<'ctx>> {
-        match op {
-            Op::Bin {
-                left: left_raw,
-                right: right_raw,
-                op,
-            } => {
-                let left_span = left_raw.as_span();
-                let left = left_raw.val_or(|left| self.gen_expression(left));
-                let right = self.gen_expression(right_raw);
-
-                let (left, right) = match (left, right) {
-                    (Ok(left), Ok(right)) => (left, right),
-                    (Err(e), _) => return Err(e),
-                    (_, Err(e)) => return Err(e),
-                };
+    /// Generates a binary operator, or the operator of a compound assignment like `+=`
+    fn gen_binary(
+        &mut self,
+        left_raw: ValOrExpr<'ctx, '_>,
+        op: &tokens::Token,
+        right_raw: &Expression,
+    ) -> Result<Value<'ctx>> {
+        if let tokens::Tok![enum &&] | tokens::Tok![enum ||] = op {
+            return self.gen_logical(left_raw, op, right_raw);
+        }
 
-                match (op, &left.ty.llvm, &right.ty.llvm) {
-                    // TODO: maybe we should match on llvm types instead of saying into
-                    // Adding ints
-                    (BinOperator::Add(_), AnyTypeEnum::IntType(l), AnyTypeEnum::IntType(r))
-                        if l.size_of() == r.size_of() =>
-                    {
-                        let result = self.builder.build_int_add(
-                            left.llvm.into_int_value(),
-                            right.llvm.into_int_value(),
-                            EMPTY_NAME,
-                        );
-
-                        Ok(result.as_value(left.ty.clone()))
-                    }
-                    (BinOperator::Sub(_), AnyTypeEnum::IntType(l), AnyTypeEnum::IntType(r))
-                        if l.size_of() == r.size_of() =>
-                    {
-                        let result = self.builder.build_int_sub(
-                            left.llvm.into_int_value(),
-                            right.llvm.into_int_value(),
-                            EMPTY_NAME,
-                        );
-
-                        Ok(result.as_value(left.ty.clone()))
-                    }
-                    (BinOperator::Mult(_), AnyTypeEnum::IntType(l), AnyTypeEnum::IntType(r))
-                        if l.size_of() == r.size_of() =>
-                    {
-                        let result = self.builder.build_int_mul(
-                            left.llvm.into_int_value(),
-                            right.llvm.into_int_value(),
-                            EMPTY_NAME,
-                        );
-
-                        Ok(result.as_value(left.ty.clone()))
-                    }
-
-                    (BinOperator::Div(_), AnyTypeEnum::IntType(l), AnyTypeEnum::IntType(r))
-                        if l.size_of() == r.size_of()
-                            && left.ty.flags.contains(TypeFlags::SIGNED)
-                                == right.ty.flags.contains(TypeFlags::SIGNED) =>
-                    {
-                        let result = if left.ty.flags.contains(TypeFlags::SIGNED) {
-                            self.builder.build_int_signed_div(
-                                left.llvm.into_int_value(),
-                                right.llvm.into_int_value(),
-                                EMPTY_NAME,
-                            )
-                        } else {
-                            self.builder.build_int_unsigned_div(
-                                left.llvm.into_int_value(),
-                                right.llvm.into_int_value(),
-                                EMPTY_NAME,
-                            )
-                        };
-
-                        Ok(result.as_value(left.ty.clone()))
-                    }
-                    // Incompatible types for operator
-                    _ => Err(raise!(@mismatch => self, left_span, right_raw.as_span())).caught(),
-                }
+        if let Expression::Literal(Literal::None(_)) = right_raw {
+            return self.gen_presence(left_raw, op, right_raw);
+        }
+
+        let left_span = left_raw.as_span();
+        let left_literal = match &left_raw {
+            ValOrExpr::Expr(expr) => untyped_int(expr),
+            ValOrExpr::Val(..) => None,
+        };
+        let left = left_raw.val_or(|left| self.gen_expression(left));
+        let right = match &left {
+            Ok(left) => self.gen_expression_as(right_raw, &left.ty),
+            Err(_) => self.gen_expression(right_raw),
+        };
+
+        let (mut left, right) = match (left, right) {
+            (Ok(left), Ok(right)) => (left, right),
+            (Err(e), _) => return Err(e),
+            (_, Err(e)) => return Err(e),
+        };
+
+        // A literal on the left takes the type of the right, like in `1 + x`
+        if let (Some(value), AnyTypeEnum::IntType(int)) = (left_literal, right.ty.llvm) {
+            if int.get_bit_width() > 1 {
+                left = int.const_int(value, false).as_value(right.ty.clone());
             }
-            Op::Uni { expr: expr_raw, op } => {
-                // TODO: this is a little weird. Maybe extend functions to be values?
-                match op {
-                    UnaryOperator::Exe(_) => {
-                        let ValOrExpr::Expr(expr @ Expression::Function(f)) = expr_raw else {
-                            panic!("Expected only a function here");
-                        };
-
-                        let Some(func) = self.symbol_table.find_function(&f.ident.value) else {
-                            return Err(raise!(@not_found => self, f.ident.clone())).caught();
-                        };
-
-                        let val = self.builder.build_call(func.fn_val, &[], EMPTY_NAME);
-                        return BasicValueEnum::try_from(val.as_any_value_enum())
-                            .map(|val| Value {
-                                llvm: val,
-                                ty: func.return_type.as_ref().unwrap().clone(),
-                            })
-                            .map_err(|_| raise!(@mismatch => self, expr.as_span()));
-                    }
-                    _ => (),
-                };
+        }
+
+        // Distinct aliases and enums only mix with themselves, or with values that have no
+        // named type, like literals
+        if let (Some(l), Some(r)) = (&left.ty.name, &right.ty.name) {
+            if l != r {
+                return Err(raise!(@mismatch => self, left_span, right_raw.as_span())).caught();
+            }
+        }
 
-                let expr_span = expr_raw.as_span();
-                let expr = expr_raw.val_or(|expr| self.gen_expression(expr))?;
-
-                match (op, expr.ty.llvm) {
-                    (UnaryOperator::Neg(_), AnyTypeEnum::IntType(_)) => {
-                        let result = self
-                            .builder
-                            .build_int_neg(expr.llvm.into_int_value(), EMPTY_NAME);
-                        Ok(result.as_value(expr.ty.clone()))
-                    }
-                    (UnaryOperator::Exe(_), AnyTypeEnum::FunctionType(_)) => unimplemented!(),
-                    _ => Err(raise!(@mismatch => self, expr_span)).caught(),
+        match (&left.ty.llvm, &right.ty.llvm) {
+            (AnyTypeEnum::IntType(l), AnyTypeEnum::IntType(r))
+                if l.get_bit_width() == r.get_bit_width() =>
+            {
+                if let Some(result) = self.gen_int_compare(op, &left, &right) {
+                    return Ok(result.as_value(self.context.bool_type().to_type()));
+                }
+
+                match self.gen_int_op(op, &left, &right) {
+                    Some(result) => Ok(result.as_value(left.ty.clone())),
+                    None => Err(raise!(@mismatch => self, left_span, right_raw.as_span())).caught(),
                 }
             }
+            // Incompatible types for operator
+            _ => Err(raise!(@mismatch => self, left_span, right_raw.as_span())).caught(),
         }
     }
 
+    /// Generates an arithmetic or bitwise operator on two integers of the same width, or
+    /// `None` if the operator doesn't apply to them
+    ///
+    /// Division, remainder and right shifts depend on the sign, so both sides need the same
+    /// one.
+    fn gen_int_op(
+        &mut self,
+        op: &tokens::Token,
+        left: &Value<'ctx>,
+        right: &Value<'ctx>,
+    ) -> Option<IntValue<'ctx>> {
+        let (l, r) = (left.llvm.into_int_value(), right.llvm.into_int_value());
+        let signed = left.ty.flags.contains(TypeFlags::SIGNED);
+        let same_sign = signed == right.ty.flags.contains(TypeFlags::SIGNED);
+
+        let result = match op {
+            tokens::Tok![enum +] | tokens::Tok![enum +=] => {
+                self.builder.build_int_add(l, r, EMPTY_NAME)
+            }
+            tokens::Tok![enum -] | tokens::Tok![enum -=] => {
+                self.builder.build_int_sub(l, r, EMPTY_NAME)
+            }
+            tokens::Tok![enum *] | tokens::Tok![enum *=] => {
+                self.builder.build_int_mul(l, r, EMPTY_NAME)
+            }
+            tokens::Tok![enum /] | tokens::Tok![enum /=] if same_sign && signed => {
+                self.builder.build_int_signed_div(l, r, EMPTY_NAME)
+            }
+            tokens::Tok![enum /] | tokens::Tok![enum /=] if same_sign => {
+                self.builder.build_int_unsigned_div(l, r, EMPTY_NAME)
+            }
+            tokens::Tok![enum %] | tokens::Tok![enum %=] if same_sign && signed => {
+                self.builder.build_int_signed_rem(l, r, EMPTY_NAME)
+            }
+            tokens::Tok![enum %] | tokens::Tok![enum %=] if same_sign => {
+                self.builder.build_int_unsigned_rem(l, r, EMPTY_NAME)
+            }
+            tokens::Tok![enum <<] | tokens::Tok![enum <<=] => {
+                self.builder.build_left_shift(l, r, EMPTY_NAME)
+            }
+            // Signed values keep their sign
+            tokens::Tok![enum >>] | tokens::Tok![enum >>=] => {
+                self.builder.build_right_shift(l, r, signed, EMPTY_NAME)
+            }
+            tokens::Tok![enum &] | tokens::Tok![enum &=] => {
+                self.builder.build_and(l, r, EMPTY_NAME)
+            }
+            tokens::Tok![enum ^] | tokens::Tok![enum ^=] => {
+                self.builder.build_xor(l, r, EMPTY_NAME)
+            }
+            tokens::Tok![enum |] | tokens::Tok![enum |=] => self.builder.build_or(l, r, EMPTY_NAME),
+            _ => return None,
+        };
+
+        Some(result)
+    }
+
+    /// Generates whether an optional has a value for `x != none`, or doesn't for `x == none`
+    fn gen_presence(
+        &mut self,
+        optional_raw: ValOrExpr<'ctx, '_>,
+        op: &tokens::Token,
+        none: &Expression,
+    ) -> Result<Value<'ctx>> {
+        let span = optional_raw.as_span();
+        let optional = optional_raw.val_or(|expr| self.gen_expression(expr))?;
+        if !optional.ty.flags.contains(TypeFlags::OPTIONAL) {
+            return Err(raise!(@mismatch => self, span)).caught();
+        }
+
+        let has = self
+            .builder
+            .build_extract_value(optional.llvm.into_struct_value(), 0, "has")
+            .expect("Optional without a flag!")
+            .into_int_value();
+        let result = match op {
+            tokens::Tok![enum ==] => self.builder.build_not(has, EMPTY_NAME),
+            tokens::Tok![enum !=] => has,
+            _ => return Err(raise!(@mismatch => self, span, none.as_span())).caught(),
+        };
+
+        Ok(result.as_value(self.context.bool_type().to_type()))
+    }
+
+    /// Generates a comparison of two integers of the same width as a bool, or `None` if the
+    /// operator isn't a comparison
+    ///
+    /// Ordering depends on the sign, so both sides of `<`, `>`, `<=` and `>=` need the same one.
+    fn gen_int_compare(
+        &mut self,
+        op: &tokens::Token,
+        left: &Value<'ctx>,
+        right: &Value<'ctx>,
+    ) -> Option<IntValue<'ctx>> {
+        let signed = left.ty.flags.contains(TypeFlags::SIGNED);
+        let same_sign = signed == right.ty.flags.contains(TypeFlags::SIGNED);
+
+        let predicate = match op {
+            tokens::Tok![enum ==] => IntPredicate::EQ,
+            tokens::Tok![enum !=] => IntPredicate::NE,
+            tokens::Tok![enum <] if same_sign && signed => IntPredicate::SLT,
+            tokens::Tok![enum <] if same_sign => IntPredicate::ULT,
+            tokens::Tok![enum >] if same_sign && signed => IntPredicate::SGT,
+            tokens::Tok![enum >] if same_sign => IntPredicate::UGT,
+            tokens::Tok![enum <=] if same_sign && signed => IntPredicate::SLE,
+            tokens::Tok![enum <=] if same_sign => IntPredicate::ULE,
+            tokens::Tok![enum >=] if same_sign && signed => IntPredicate::SGE,
+            tokens::Tok![enum >=] if same_sign => IntPredicate::UGE,
+            _ => return None,
+        };
+
+        let (l, r) = (left.llvm.into_int_value(), right.llvm.into_int_value());
+        Some(self.builder.build_int_compare(predicate, l, r, EMPTY_NAME))
+    }
+
+    /// The type of an integer literal, which is `int64` without a suffix
+    fn int_literal_type(&self, i: &LiteralInt) -> Type<'ctx> {
+        let (bits, signed) = i
+            .value
+            .suffix
+            .map_or((64, true), |suffix| (suffix.bits(), suffix.is_signed()));
+        self.context.custom_width_int_type(bits).signed(signed)
+    }
+
+    /// Generates a literal. Integers without a suffix are `int64`, and floats without one are
+    /// `f64`. Strings are arrays of their bytes, and chars are 32 bit code points.
     fn gen_literal(&mut self, lit: &Literal) -> Result<Value<'ctx>> {
         match lit {
             Literal::Int(i) => {
-                let ty = self.context.i64_type().to_type();
-                Ok(ty.into_int_type().const_int(i.value, false).as_value(ty))
+                let ty = self.int_literal_type(i);
+                Ok(ty
+                    .llvm
+                    .into_int_type()
+                    .const_int(i.value.value, false)
+                    .as_value(ty))
             }
             Literal::Float(f) => {
-                let ty = self.context.f64_type().to_type();
-                Ok(ty.into_float_type().const_float(f.value).as_value(ty))
+                let ty = match f.value.suffix {
+                    Some(FloatSuffix::F32) => self.context.f32_type(),
+                    Some(FloatSuffix::F64) | None => self.context.f64_type(),
+                };
+                Ok(ty.const_float(f.value.value).as_value(ty.to_type()))
             }
-            Literal::Bool(b) => {
-                let ty = self.context.bool_type().to_type();
+            Literal::String(s) => Ok(self.gen_bytes(s.value.value.as_bytes())),
+            Literal::ByteString(s) => Ok(self.gen_bytes(&s.value.value)),
+            Literal::Char(c) => {
+                let ty = self.context.i32_type();
                 Ok(ty
-                    .into_int_type()
-                    .const_int(if b.value { 1 } else { 0 }, false)
-                    .as_value(ty))
+                    .const_int(c.value.value as u64, false)
+                    .as_value(ty.to_type()))
+            }
+            Literal::None(n) => {
+                let msg = "`none` needs an optional type to be expected";
+                raise!(@log Error => self, msg, n.as_span());
+                Err(crate::Diagnostic::Caught(n.as_span()))
             }
-            Literal::String(s) => {
-                let llvm_bt = self.context.i8_type();
-                let base_type = llvm_bt.to_type();
+        }
+    }
 
-                let array_vals: Vec<_> = s
-                    .value
-                    .chars()
-                    .map(|c| llvm_bt.const_int(c as _, false))
-                    .collect();
+    /// A constant array of bytes
+    fn gen_bytes(&self, bytes: &[u8]) -> Value<'ctx> {
+        let byte_ty = self.context.i8_type();
+        let values: Vec<_> = bytes
+            .iter()
+            .map(|b| byte_ty.const_int(*b as u64, false))
+            .collect();
 
-                let array_vals = llvm_bt.const_array(&array_vals);
+        byte_ty
+            .const_array(&values)
+            .as_value(byte_ty.to_type().array_type(bytes.len() as u32))
+    }
 
-                let val = Ok(base_type
-                    .into_int_type()
-                    .array_type(s.value.len() as _)
-                    .const_array(&[array_vals])
-                    .as_value(base_type.array_type(s.value.len() as _)));
-                val
-            }
-        }
+    /// A `str` of `bytes`, which are stored in a constant global
+    ///
+    /// The bytes are followed by a nul that isn't part of the length, so the pointer can be
+    /// passed to C as well.
+    fn gen_str(&mut self, bytes: &[u8], ty: &Type<'ctx>) -> Value<'ctx> {
+        let content = self.context.const_string(bytes, true);
+        let global = self.module.add_global(content.get_type(), None, "str");
+        global.set_initializer(&content);
+        global.set_constant(true);
+        global.set_linkage(Linkage::Private);
+        global.set_unnamed_addr(true);
+
+        let ptr_type = self.context.i8_type().ptr_type(AddressSpace::default());
+        let ptr = global.as_pointer_value().const_cast(ptr_type);
+        let len = self
+            .context
+            .ptr_sized_int_type(&self.target_data, None)
+            .const_int(bytes.len() as u64, false);
+
+        ty.llvm
+            .into_struct_type()
+            .const_named_struct(&[len.into(), ptr.into()])
+            .as_value(ty.clone())
+    }
+}
+
+/// Returns true if `ty` is a `str`, or another slice of bytes
+fn is_str(ty: &Type) -> bool {
+    let element = ty.base_type.as_deref().map(|base| base.llvm);
+    ty.flags.contains(TypeFlags::SLICE)
+        && matches!(element, Some(AnyTypeEnum::IntType(int)) if int.get_bit_width() == 8)
+}
+
+/// The value of an integer literal without a suffix, which can take the type it's used as
+pub(crate) fn untyped_int(expr: &Expression) -> Option<u64> {
+    match expr {
+        Expression::Literal(Literal::Int(LiteralInt {
+            value:
+                tokens::Int {
+                    value,
+                    suffix: None,
+                    ..
+                },
+        })) => Some(*value),
+        Expression::Parens(p) => untyped_int(&p.value),
+        _ => None,
     }
 }
@@ -1,156 +1,173 @@
 use inkwell::{
-    types::{BasicType, BasicTypeEnum},
-    values::{AnyValue, BasicValueEnum, PointerValue},
+    types::{BasicMetadataTypeEnum, BasicType, BasicTypeEnum},
+    values::{FunctionValue, PointerValue},
 };
+use parsely_lexer::{tokens, AsSpan};
 use parsely_parser::{
-    item::Noun,
-    item::TopLevelItem,
-    statement::{Statement, VariableInit},
+    expression::Expression,
+    item::{Function, Parameter, TopLevelItem},
+    statement::{ReturnStatement, Statement, VariableDeclaration},
+    types, Punctuation,
 };
 
 use crate::{
     attempt,
-    llvm_value::{Function, Type, TypeBuilder, TypeFlags, Value, Variable},
+    llvm_value::{Type, Value, Variable},
     module::{Module, EMPTY_NAME},
-    raise, ErrorHelper, Result,
+    raise, Diagnostic, ErrorHelper, Result,
 };
 
 impl<'ctx> Module<'ctx> {
     pub(crate) fn gen_item(&mut self, item: &TopLevelItem) -> Result<()> {
         match item {
-            TopLevelItem::Definition(def) => match def.what.noun {
-                Noun::Function(_) => {
-                    let args = if let Some(args) = &def.args {
-                        args.args
-                            .iter()
-                            .filter_map(|arg| {
-                                attempt!(self, self.gen_type(&arg.ty.ty))
-                                    .ok()
-                                    .map(|a| (arg.ident.value.as_str(), a))
-                            })
-                            .collect::<Vec<_>>()
-                    } else {
-                        Vec::new()
-                    };
-
-                    let ret_ty = if let Some(out) = &def.out {
-                        attempt!(self, self.gen_type(&out.of_type.ty)).ok()
-                    } else {
-                        None
-                    };
-
-                    let llvm_args = args
-                        .iter()
-                        .map(|arg| arg.1.llvm.try_into().unwrap())
-                        .collect::<Vec<_>>();
-
-                    let void_ty = self.context.void_type().to_type();
-                    let ty =
-                        BasicTypeEnum::try_from(ret_ty.as_ref().unwrap_or(&void_ty).llvm)
-                            .expect("Unable to get basic type enum")
-                            .fn_type(&llvm_args, false);
-
-                    let llvm_func = self.module.add_function(&def.ident.value, ty, None);
-
-                    self.alloc_block = Some(self.context.append_basic_block(llvm_func, "locals"));
-                    let entry = self.context.append_basic_block(llvm_func, "entry");
-                    self.basic_block = Some(entry);
-                    self.return_block = Some(self.context.append_basic_block(llvm_func, "end"));
-
-                    // Add parameter allocas
-                    self.builder.position_at_end(self.alloc_block.unwrap());
-                    let var_allocs = args
-                        .iter()
-                        .map(|arg| {
-                            self.builder.build_alloca::<BasicTypeEnum>(
-                                arg.1
-                                    .llvm
-                                    .try_into()
-                                    .expect("Unable to get basic type from any type"),
-                                EMPTY_NAME,
-                            )
-                        })
-                        .collect::<Vec<_>>();
-
-                    if let Some(ret) = &ret_ty {
-                        self.return_alloc = Some(
-                            self.builder.build_alloca::<BasicTypeEnum>(
-                                ret.llvm
-                                    .try_into()
-                                    .expect("Unable to get basic type from any type"),
-                                "ret",
-                            ),
-                        );
-                    }
-
-                    self.builder.position_at_end(entry);
-
-                    let func = Function {
-                        fn_type: ty,
-                        fn_val: llvm_func,
-                        param_types: args.iter().map(|f| f.1.clone()).collect(),
-                        return_type: ret_ty.clone(),
-                    };
-
-                    self.symbol_table.insert_function(&def.ident.value, func);
-                    self.symbol_table.push_scope();
-
-                    for ((arg, param), alloc) in
-                        args.iter().zip(llvm_func.get_param_iter()).zip(var_allocs)
-                    {
-                        self.builder.build_store(alloc, param);
-
-                        self.symbol_table.insert_variable(
-                            arg.0,
-                            Variable {
-                                ty: arg.1.clone(),
-                                flags: TypeFlags::empty(),
-                                alloc,
-                            },
-                        );
-                    }
-
-                    for (i, stmt) in def.init.as_fn().statements.iter().enumerate() {
-                        let Ok(val) = attempt!(self, self.gen_statement(stmt)) else {
-                            continue;
-                        };
-
-                        // Set return value to the last statement in the function body (if it has a value)
-                        if let Some(val) = val {
-                            if i == def.init.as_fn().statements.len() - 1 && ret_ty.is_some() {
-                                self.builder
-                                    .build_store(self.return_alloc.unwrap(), val.llvm);
-                            }
-                        }
-                    }
-
-                    self.builder
-                        .build_unconditional_branch(self.return_block.unwrap());
-                    self.builder.position_at_end(self.alloc_block.unwrap());
-                    self.builder.build_unconditional_branch(entry);
-
-                    self.builder.position_at_end(self.return_block.unwrap());
-
-                    if let Some(ret) = &ret_ty {
-                        let value = self.builder.build_load(
-                            BasicTypeEnum::try_from(ret.llvm)
-                                .expect("Unable to convert to basic type"),
-                            self.return_alloc.unwrap(),
-                            EMPTY_NAME,
-                        );
-                        self.builder.build_return(Some(&value));
-                    } else {
-                        self.builder.build_return(None);
-                    }
-
-                    let scope = self.symbol_table.pop_scope();
-                    println!("{:#?}", scope);
-
-                    self.basic_block = None;
-                }
-                _ => unimplemented!(),
+            TopLevelItem::Function(f) => self.gen_function(f)?,
+            TopLevelItem::ExternalFunction(f) => {
+                self.declare_function(&f.ident, &f.return_type, &f.params.value)?;
+            }
+            TopLevelItem::Struct(s) => self.gen_struct(s)?,
+            TopLevelItem::Enum(e) => self.gen_enum(e)?,
+            TopLevelItem::TypeAlias(alias) => self.gen_type_alias(alias)?,
+            // Reported by the parser already
+            TopLevelItem::Error(_) => (),
+        }
+
+        Ok(())
+    }
+
+    /// Adds a function to the module and the symbol table, without a body
+    fn declare_function(
+        &mut self,
+        ident: &tokens::Ident,
+        return_type: &types::Type,
+        params: &Punctuation<Parameter, tokens::Tok![,]>,
+    ) -> Result<FunctionValue<'ctx>> {
+        let mut failed = false;
+        let mut param_types = Vec::new();
+        for param in params.iter() {
+            let span = param.ident.as_span();
+            match attempt!(
+                self,
+                self.gen_value_type(&param.parameter_type, &param.arrays, span)
+            ) {
+                Ok(ty) => param_types.push(ty),
+                Err(_) => failed = true,
+            }
+        }
+
+        let return_type = match attempt!(self, self.gen_type(return_type)) {
+            Ok(ty) if ty.is_void_type() => None,
+            Ok(ty) => Some(ty),
+            Err(_) => {
+                failed = true;
+                None
+            }
+        };
+
+        if failed {
+            return Err(Diagnostic::Caught(ident.as_span()));
+        }
+
+        if self.symbol_table.find_function(&ident.value).is_some() {
+            let msg = format!("Function `{}` is already defined", ident.value);
+            raise!(@log Error => self, msg, ident.as_span());
+            return Err(Diagnostic::Caught(ident.as_span()));
+        }
+
+        let llvm_params: Vec<BasicMetadataTypeEnum> = param_types
+            .iter()
+            .map(|ty| {
+                BasicTypeEnum::try_from(ty.llvm)
+                    .expect("Unable to get basic type")
+                    .into()
+            })
+            .collect();
+        let fn_type = match &return_type {
+            Some(ty) => BasicTypeEnum::try_from(ty.llvm)
+                .expect("Unable to get basic type")
+                .fn_type(&llvm_params, false),
+            None => self.context.void_type().fn_type(&llvm_params, false),
+        };
+
+        let fn_val = self.module.add_function(&ident.value, fn_type, None);
+        self.symbol_table.insert_function(
+            &ident.value,
+            crate::llvm_value::Function {
+                fn_val,
+                param_types,
+                return_type,
             },
+        );
+
+        Ok(fn_val)
+    }
+
+    /// Generates a function with its body
+    ///
+    /// Parameters are copied into allocas so they can be assigned like variables. Every return
+    /// stores its value and jumps to the `end` block, which returns what was stored. The value
+    /// of the last statement of the body is returned too, if it has one.
+    fn gen_function(&mut self, f: &Function) -> Result<()> {
+        let fn_val = self.declare_function(&f.ident, &f.return_type, &f.params.value)?;
+        let func = self
+            .symbol_table
+            .find_function(&f.ident.value)
+            .expect("Function was just declared!");
+        let (param_types, return_type) = (func.param_types.clone(), func.return_type.clone());
+
+        let alloc_block = self.context.append_basic_block(fn_val, "locals");
+        let entry = self.context.append_basic_block(fn_val, "entry");
+        let return_block = self.context.append_basic_block(fn_val, "end");
+        self.alloc_block = Some(alloc_block);
+        self.return_block = Some(return_block);
+        self.position_at(entry);
+
+        self.return_alloc = return_type.clone().map(|ty| {
+            let alloc = self.insert_alloca(&ty, "ret");
+            Variable { ty, alloc }
+        });
+
+        self.symbol_table.push_scope();
+        let params = f.params.value.iter().zip(param_types);
+        for ((param, ty), llvm) in params.zip(fn_val.get_param_iter()) {
+            let alloc = self.insert_alloca(&ty, &param.ident.value);
+            self.builder.build_store(alloc, llvm);
+
+            let var = Variable { ty, alloc };
+            if !self.symbol_table.insert_variable(&param.ident.value, var) {
+                let msg = format!("Parameter `{}` is already defined", param.ident.value);
+                raise!(@log Error => self, msg, param.ident.as_span());
+            }
+        }
+
+        let value = self.gen_block(&f.body.value, return_type.as_ref());
+        if let (Some(value), Some(ret)) = (value, &self.return_alloc) {
+            if value.ty.assignable_to(&ret.ty) {
+                self.builder.build_store(ret.alloc, value.llvm);
+            } else {
+                raise!(@mismatch => self, f.body.as_span());
+            }
         }
+        self.symbol_table.pop_scope();
+
+        self.builder.build_unconditional_branch(return_block);
+        self.builder.position_at_end(alloc_block);
+        self.builder.build_unconditional_branch(entry);
+
+        self.builder.position_at_end(return_block);
+        match self.return_alloc.take() {
+            Some(ret) => {
+                let llvm: BasicTypeEnum = ret.ty.llvm.try_into().expect("Unable to get basic type");
+                let value = self.builder.build_load(llvm, ret.alloc, EMPTY_NAME);
+                self.builder.build_return(Some(&value));
+            }
+            None => {
+                self.builder.build_return(None);
+            }
+        }
+
+        self.alloc_block = None;
+        self.basic_block = None;
+        self.return_block = None;
 
         Ok(())
     }
@@ -158,7 +175,7 @@ impl<'ctx> Module<'ctx> {
     /// Inserts an alloca instruction at the beginning of the function (the alloc_block)
     ///
     /// I'm not sure if this is necessary but other compilers seem to do this
-    fn insert_alloca(&self, ty: &Type<'ctx>, name: &str) -> PointerValue<'ctx> {
+    pub(crate) fn insert_alloca(&self, ty: &Type<'ctx>, name: &str) -> PointerValue<'ctx> {
         let alloc_block = self.alloc_block.expect("Alloc block not set!");
         let old_block = self.builder.get_insert_block().expect("Block not set");
         self.builder.position_at_end(alloc_block);
@@ -174,69 +191,80 @@ impl<'ctx> Module<'ctx> {
         alloc
     }
 
-    fn gen_statement(&mut self, statement: &Statement) -> Result<Option<Value<'ctx>>> {
+    /// Generates a statement, and its value if it has one. The value is generated as
+    /// `expected`, if given.
+    pub(crate) fn gen_statement(
+        &mut self,
+        statement: &Statement,
+        expected: Option<&Type<'ctx>>,
+    ) -> Result<Option<Value<'ctx>>> {
         match statement {
-            Statement::Definition(def) => match def.what.noun {
-                Noun::Variable(_) => {
-                    let init = self.gen_variable_init(def.init.as_var())?;
-                    let alloc = self.insert_alloca(&init.ty, &def.ident.value);
-
-                    self.builder.build_store(alloc, init.llvm);
-
-                    let var = Variable {
-                        alloc,
-                        flags: TypeFlags::empty(),
-                        ty: init.ty.clone(),
-                    };
-
-                    self.symbol_table.insert_variable(&def.ident.value, var);
-                }
-                Noun::Constant(_) => {
-                    let init = self.gen_variable_init(def.init.as_var())?;
-                    let alloc = self.insert_alloca(&init.ty, &def.ident.value);
-
-                    self.builder.build_store(alloc, init.llvm);
-
-                    let var = Variable {
-                        alloc,
-                        flags: TypeFlags::empty(),
-                        ty: init.ty.clone(),
-                    };
-
-                    self.symbol_table.insert_variable(&def.ident.value, var);
-                }
-                _ => unimplemented!(),
-            },
-            Statement::Execute(exe) => match &exe.what.noun {
-                Noun::Function(_) => {
-                    let Some(func) = self.symbol_table.find_function(&exe.ident.value) else {
-                        return Err(raise!(@not_found => self, exe.ident.clone())).caught();
-                    };
-
-                    let val = self.builder.build_call(func.fn_val, &[], EMPTY_NAME);
-                    let val = BasicValueEnum::try_from(val.as_any_value_enum());
-
-                    if let (Some(ret), Ok(val)) = (&func.return_type, val) {
-                        return Ok(Some(Value {
-                            llvm: val,
-                            ty: ret.clone(),
-                        }));
-                    }
-                }
-                t => panic!("{:?} is not callable", t),
+            // Calls to functions without a value are fine as statements
+            Statement::Expression(expr) => match (&*expr.expression, expected) {
+                (Expression::Call(call), _) => return self.gen_call(call),
+                (expr, Some(ty)) => return self.gen_expression_as(expr, ty).map(Some),
+                (expr, None) => return self.gen_expression(expr).map(Some),
             },
-            Statement::Evaluate(eval) => {
-                return self.gen_expression(&eval.expr).map(Some);
-            }
+            Statement::VariableDeclaration(decl) => self.gen_variable(decl)?,
+            Statement::IfStatement(stmt) => return self.gen_if(stmt, expected),
+            Statement::WhileLoop(stmt) => self.gen_while(stmt)?,
+            Statement::ForLoop(stmt) => self.gen_for(stmt)?,
+            Statement::ReturnStatement(stmt) => self.gen_return(stmt)?,
+            Statement::Break(stmt) => self.gen_break(stmt)?,
+            Statement::Continue(stmt) => self.gen_continue(stmt)?,
+            Statement::Match(m) => return self.gen_match(m, expected),
+            // Reported by the parser already
+            Statement::Error(_) => (),
         }
 
         Ok(None)
     }
 
-    fn gen_variable_init(&mut self, init: &VariableInit) -> Result<Value<'ctx>> {
-        match init {
-            VariableInit::Const(c) => self.gen_expression(&c.value),
-            VariableInit::Mutable(c) => self.gen_expression(&c.value),
+    /// Declares a variable in the current scope, and stores its initial value if it has one
+    fn gen_variable(&mut self, decl: &VariableDeclaration) -> Result<()> {
+        let span = decl.ident.as_span();
+        let ty = self.gen_value_type(&decl.ty, &decl.arrays, span)?;
+        let alloc = self.insert_alloca(&ty, &decl.ident.value);
+
+        // The variable isn't in scope yet in its own initializer
+        if let Some(init) = &decl.init {
+            let value = self.gen_expression_as(&init.expression, &ty)?;
+            if !value.ty.assignable_to(&ty) {
+                let init_span = init.expression.as_span();
+                return Err(raise!(@mismatch => self, span, init_span)).caught();
+            }
+
+            self.builder.build_store(alloc, value.llvm);
+        }
+
+        if !self
+            .symbol_table
+            .insert_variable(&decl.ident.value, Variable { ty, alloc })
+        {
+            let msg = format!("`{}` is already defined in this scope", decl.ident.value);
+            raise!(@log Error => self, msg, span);
+            return Err(Diagnostic::Caught(span));
+        }
+
+        Ok(())
+    }
+
+    /// Stores the returned value and jumps to the end of the function
+    fn gen_return(&mut self, stmt: &ReturnStatement) -> Result<()> {
+        let span = stmt.expr.as_span();
+        let Some(ret) = self.return_alloc.clone() else {
+            raise!(@log Error => self, "This function doesn't return a value", span);
+            return Err(Diagnostic::Caught(span));
+        };
+
+        let value = self.gen_expression_as(&stmt.expr, &ret.ty)?;
+        if !value.ty.assignable_to(&ret.ty) {
+            return Err(raise!(@mismatch => self, span)).caught();
         }
+
+        self.builder.build_store(ret.alloc, value.llvm);
+        self.gen_jump(self.return_block.expect("Return block not set!"));
+
+        Ok(())
     }
 }
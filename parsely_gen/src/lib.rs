@@ -1,20 +1,23 @@
+// Diagnostics are only built on the error path, so their size doesn't matter
+#![allow(clippy::result_large_err)]
+
 use std::fmt::Display;
 
-use colored::Colorize;
-use module::Module;
-use parsely_lexer::{
-    tokens,
-    AsSpan, Span,
-};
-use parsely_parser::{
-    item::{Program, TokenCache}
-};
+use parsely_lexer::{tokens, AsSpan, Level, Report, SourceDatabase, Span};
 
+pub mod c;
 pub mod module;
 
+mod check;
+mod control;
+mod enums;
+mod exhaustive;
 mod expression;
 mod item;
 mod llvm_value;
+mod pattern;
+mod statement;
+mod structs;
 mod symbols;
 mod types;
 
@@ -63,192 +66,44 @@ impl Diagnostic {
         }
     }
 
-    /// Format a diagnostic into 'f'
-    /// `module` and `program` should be for the same file
-    /// `cache` is token and line information cache
-    pub fn format(
-        &self,
-        _module: &Module<'_>,
-        program: &Program,
-        cache: &mut TokenCache,
-        f: &mut std::fmt::Formatter<'_>,
-    ) -> std::fmt::Result {
-        // How many characters on each side of the diagnostic's span should be displayed
-        const LINE_PEEK: usize = 3;
-
-        match self.level() {
-            DiagnosticLevel::Internal => return Ok(()),
-            DiagnosticLevel::Info => write!(f, "{}", "info".bold().cyan()),
-            DiagnosticLevel::Warning => write!(f, "{}", "warning".bold().yellow()),
-            DiagnosticLevel::Error => write!(f, "{}", "error".bold().red()),
-        }?;
-
-        write!(f, "{}", ": ".bold())?;
-
-        let pad = match self {
-            Diagnostic::SymbolNotFound(ident) => {
-                write!(
-                    f,
-                    "{}",
-                    format!("Symbol `{}` not found in scope", ident.value).bold(),
-                )?;
-
-                ident.as_span().end.line.to_string().len()
-            }
-            Diagnostic::IncompatibleType(ty) => {
-                write!(f, "{}", "Unexpected type".to_string().bold())?;
-                ty.end.line.to_string().len()
-            }
-            Diagnostic::IncompatibleTypes(left, right) => {
-                write!(f, "{}", "Types don't match in expression".to_string().bold())?;
-                left.end.line.max(right.end.line).to_string().len()
-            }
-            Diagnostic::Message(msg, span, _) => {
-                write!(f, "{}", msg.bold())?;
-                span.end.line.to_string().len()
-            }
-            Diagnostic::Caught(_) => 0,
-        };
-
-        writeln!(f)?;
-
-        let span = self.primary_span();
-        writeln!(
-            f,
-            " {} {}:{}:{}",
-            "-->".bold().cyan(),
-            program.path.to_str().unwrap(),
-            span.start.line + 1,
-            span.start.column + 1,
-        )?;
-
-        writeln!(f, "{:width$} {}", "", "|".bold().cyan(), width = pad)?;
-
-        // write line number
-        let write_ln = |f: &mut std::fmt::Formatter<'_>, line_number: usize| {
-            write!(
-                f,
-                "{:width$} {} ",
-                line_number.to_string().bold().white(),
-                "|".bold().cyan(),
-                width = pad
-            )
+    /// Report for rendering the diagnostic with the source it points to. Internal diagnostics
+    /// aren't shown, so they have no report.
+    pub fn to_report(&self) -> Option<Report> {
+        let level = match self.level() {
+            DiagnosticLevel::Internal => return None,
+            DiagnosticLevel::Info => Level::Info,
+            DiagnosticLevel::Warning => Level::Warning,
+            DiagnosticLevel::Error => Level::Error,
         };
 
-        // Print one line of source code
-        let write_line = |f: &mut std::fmt::Formatter<'_>,
-                          highlight: &Span,
-                          peeked: &Span,
-                          line_offset: usize,
-                          line: &str|
-         -> std::fmt::Result {
-            assert!(peeked.contains(highlight));
-
-            write_ln(f, highlight.start.line + line_offset)?;
-
-            if line_offset == 0 {
-                let peek = &line[peeked.start.column..highlight.start.column];
-                write!(f, "{}", peek)?;
-            }
-
-            if highlight.start.line == highlight.end.line {
-                write!(f, "{}", &line[highlight.start.column..highlight.end.column],)?;
-            } else if line_offset == 0 {
-                write!(f, "{}", &line[highlight.start.column..])?;
-            } else if line_offset > 0 {
-                write!(f, "{}", &line[..highlight.end.column])?;
-            } else {
-                unreachable!()
-            }
-
-            if line_offset == highlight.end.line - highlight.start.line {
-                let peek = &line[highlight.end.column..peeked.end.column];
-                write!(f, "{}", peek)?;
-            }
-
-            writeln!(f)?;
-
-            write!(f, "{:width$} {} ", "", "|".bold().cyan(), width = pad)?;
-
-            let (swidth, ewidth) = if highlight.start.line == highlight.end.line {
-                (
-                    highlight.start.column - peeked.start.column,
-                    peeked.end.column - highlight.end.column,
-                )
-            } else if line_offset == 0 {
-                (highlight.start.column - peeked.start.column, 0)
-            } else if line_offset > 0 {
-                (0, peeked.end.column - highlight.end.column)
-            } else {
-                unreachable!()
-            };
-
-            let carets = String::from_utf8(vec![b'^'; highlight.len()]).unwrap();
-            let carets = match self.level() {
-                DiagnosticLevel::Info => carets.green(),
-                DiagnosticLevel::Warning => carets.yellow(),
-                DiagnosticLevel::Error => carets.red(),
-                _ => carets.white(),
-            }
-            .bold();
-
-            writeln!(
-                f,
-                "{0:<swidth$}{1}{0:<ewidth$}",
-                "",
-                carets,
-                swidth = swidth,
-                ewidth = ewidth,
-            )
-        };
-
-        // Print source code using `span`
-        let mut write_span = |span: &Span| -> std::fmt::Result {
-            let token_index = cache.token_index(&span.start);
-            let token_range = cache.line_index(span.start.line);
-            assert!(token_range.0.contains(&token_index));
-
-            let start_token = &program.tokens[(token_index - LINE_PEEK).max(token_range.0.start)];
-            let end_token = &program.tokens[(token_index + LINE_PEEK).min(token_range.0.end - 1)];
-
-            // The span of the padded tokens
-            let peeked_span = start_token.as_span().join(end_token.as_span());
-
-            // str of tokens with padding
-            let lines = program
-                .source_lines_slice(&peeked_span)
-                .expect("Unable to slice into source file!");
-
-            for (i, line) in lines.lines().enumerate() {
-                write_line(f, span, &peeked_span, i, line)?;
+        let report = match self {
+            Diagnostic::SymbolNotFound(ident) => Report::new(
+                level,
+                format!("Symbol `{}` not found in scope", ident.value),
+                ident.as_span(),
+            ),
+            Diagnostic::IncompatibleType(ty) => Report::new(level, "Unexpected type", *ty),
+            Diagnostic::IncompatibleTypes(left, right) => {
+                Report::new(level, "Types don't match in expression", *left).with_label(*right, "")
             }
-
-            Ok(())
+            Diagnostic::Message(msg, span, _) => Report::new(level, msg.clone(), *span),
+            Diagnostic::Caught(_) => return None,
         };
 
-        match self {
-            Diagnostic::SymbolNotFound(ident) => {
-                let span = ident.as_span();
-                assert_eq!(span.start.line, span.end.line); // Identifiers should always be on the same line (i think)
+        Some(report)
+    }
 
-                write_span(&span)?;
-            }
-            Diagnostic::IncompatibleType(ty) => {
-                write_span(ty)?;
-            }
-            Diagnostic::IncompatibleTypes(left, right) => {
-                write_span(left)?;
-                write_span(right)?;
-            }
-            Diagnostic::Message(_, span, _) => {
-                write_span(span)?;
-            }
-            _ => unimplemented!(),
+    /// Format a diagnostic into 'f'
+    /// `source` should contain the file the diagnostic's spans point into
+    pub fn format(
+        &self,
+        source: &SourceDatabase,
+        f: &mut std::fmt::Formatter<'_>,
+    ) -> std::fmt::Result {
+        match self.to_report() {
+            Some(report) => report.render(source, f),
+            None => Ok(()),
         }
-
-        writeln!(f, "{:width$} {}", "", "|".bold().cyan(), width = pad)?;
-
-        Ok(())
     }
 }
 
@@ -262,17 +117,16 @@ impl Display for Diagnostic {
 ///
 /// # Example
 ///
-/// ```
-/// let fmtr = DiagnosticFmt(&module.errors, &module, program);
+/// ```ignore
+/// let fmtr = DiagnosticFmt(module.diagnostics(), &source);
 /// println!("{}", fmtr);
-/// ```    
-pub struct DiagnosticFmt<'a, 'ctx>(&'a [Diagnostic], &'a Module<'ctx>, &'a Program);
+/// ```
+pub struct DiagnosticFmt<'a>(pub &'a [Diagnostic], pub &'a SourceDatabase);
 
-impl Display for DiagnosticFmt<'_, '_> {
+impl Display for DiagnosticFmt<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let mut cache = TokenCache::new(self.2);
         for diag in self.0 {
-            diag.format(self.1, self.2, &mut cache, f)?;
+            diag.format(self.1, f)?;
         }
         Ok(())
     }
@@ -280,32 +134,34 @@ impl Display for DiagnosticFmt<'_, '_> {
 
 impl std::error::Error for Diagnostic {}
 
+/// C output is written to strings, which don't fail to be written to
+impl From<std::fmt::Error> for Diagnostic {
+    fn from(_: std::fmt::Error) -> Self {
+        Diagnostic::Message(
+            "Unable to write C output".to_string(),
+            Span::EMPTY,
+            DiagnosticLevel::Internal,
+        )
+    }
+}
+
 /// Code gen result
 type Result<T> = std::result::Result<T, Diagnostic>;
 
 trait ErrorHelper {
     fn caught(self) -> Self;
-    fn caught_span(self, span: Span) -> Self;
 }
 
 impl<T> ErrorHelper for Result<T> {
     fn caught(self) -> Self {
         Err(Diagnostic::Caught(Span::EMPTY))
     }
-
-    fn caught_span(self, span: Span) -> Self {
-        Err(Diagnostic::Caught(span))
-    }
 }
 
 impl ErrorHelper for Diagnostic {
     fn caught(self) -> Self {
         Diagnostic::Caught(Span::EMPTY)
     }
-
-    fn caught_span(self, span: Span) -> Self {
-        Diagnostic::Caught(span)
-    }
 }
 
 /// If `$res` is an error it is logged, otherwise the value is returned
@@ -368,4 +224,906 @@ macro_rules! raise {
 }
 
 #[cfg(test)]
-mod tests {}
+mod tests {
+    use inkwell::{context::Context, values::AnyValue};
+    use parsely_lexer::{Lexer, SourceDatabase};
+    use parsely_parser::{expression::Expression, item::Program, pattern::Pattern, ParseStream};
+
+    use crate::{
+        c::CModule,
+        exhaustive::{lower_pattern, missing_pattern, Pat, Space},
+        llvm_value::{TypeBuilder, Variable},
+        module::Module,
+    };
+
+    /// Parses the patterns in `input`, separated by `;`
+    fn patterns(input: &str, space: &Space) -> Vec<Pat> {
+        input
+            .split(';')
+            .map(|input| {
+                let (tokens, _) = Lexer::run(input.as_bytes());
+                let stream = ParseStream::from(&tokens);
+
+                let pattern: Pattern = stream.parse().unwrap();
+                lower_pattern(&pattern, space).unwrap()
+            })
+            .collect()
+    }
+
+    /// Generates the expression `input` as the result of a function with `bool` variables `a`,
+    /// `b` and `c`, and returns the IR of the function
+    fn gen_function(input: &str) -> String {
+        let context = Context::create();
+        let source = SourceDatabase::new();
+        let mut module = Module::new("test", &context, &source);
+
+        let bool_type = context.bool_type();
+        let llvm_func = module
+            .module
+            .add_function("test", bool_type.fn_type(&[], false), None);
+        module.alloc_block = Some(context.append_basic_block(llvm_func, "locals"));
+        let entry = context.append_basic_block(llvm_func, "entry");
+        module.position_at(entry);
+
+        for name in ["a", "b", "c"] {
+            let ty = bool_type.to_type();
+            let alloc = module.insert_alloca(&ty, name);
+            module
+                .symbol_table
+                .insert_variable(name, Variable { ty, alloc });
+        }
+
+        let (tokens, _) = Lexer::run(input.as_bytes());
+        let expr: Expression = ParseStream::from(&tokens).parse().unwrap();
+        let value = module.gen_expression(&expr).unwrap();
+        module.builder.build_return(Some(&value.llvm));
+
+        llvm_func.print_to_string().to_string()
+    }
+
+    /// Generates the program `input`, and returns the IR of the module and its diagnostics.
+    /// The module is checked by LLVM if there are no errors.
+    fn gen_program(input: &str) -> (String, Vec<String>) {
+        let context = Context::create();
+        let source = SourceDatabase::new();
+        let mut module = Module::new("test", &context, &source);
+
+        let (tokens, _) = Lexer::run(input.as_bytes());
+        let program: Program = ParseStream::from(&tokens).parse().unwrap();
+        let _ = module.run(&program);
+
+        let errors: Vec<_> = module.diagnostics().iter().map(|d| d.to_string()).collect();
+        if errors.is_empty() {
+            module.module.verify().unwrap();
+        }
+
+        (module.llvm_ir(), errors)
+    }
+
+    /// Generates the program `input` as C, and returns the header, the source and the
+    /// diagnostics
+    fn gen_c(input: &str) -> (String, String, Vec<String>) {
+        let source = SourceDatabase::new();
+        let mut module = CModule::new("test", &source);
+
+        let (tokens, _) = Lexer::run(input.as_bytes());
+        let program: Program = ParseStream::from(&tokens).parse().unwrap();
+        let (header, code) = module.run(&program).unwrap_or_default();
+
+        let errors = module.diagnostics().iter().map(|d| d.to_string()).collect();
+        (header, code, errors)
+    }
+
+    #[test]
+    fn test_function() {
+        let (ir, errors) = gen_program(
+            "external void print();
+            int64 three() { return 1 + 2; }
+            int64 main() {
+                print();
+                return three();
+            }",
+        );
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert!(ir.contains("define i64 @three()"));
+        assert!(ir.contains("declare void @print()"));
+        assert!(ir.contains("call i64 @three()"));
+
+        let (_, errors) = gen_program("void f() { return 1; } int32 g() { return f(); }");
+        assert_eq!(errors.len(), 2, "{:?}", errors);
+
+        // The last expression of a function is its value, unless it ends with `;`
+        let (ir, errors) = gen_program(
+            "int32 add(int32 a, int32 b) { return a + b; }
+            external void print(int32 x);
+            int32 main() {
+                int32 x = 1;
+                x = add(x, 2);
+                print(x);
+                x
+            }",
+        );
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert!(ir.contains("define i32 @add(i32 %0, i32 %1)"));
+        assert!(ir.contains("declare void @print(i32)"));
+        assert!(ir.contains("call i32 @add(i32"));
+
+        let (_, errors) = gen_program("int32 g() { int64 x = 1; x }");
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+    }
+
+    #[test]
+    fn test_c_function() {
+        let (header, code, errors) = gen_c(
+            "external void print(int32 x);
+            int32 add(int32 a) { return a + 2; }
+            export int32 main() {
+                int32 x = add(1);
+                int32 rows[4][];
+                if x { print(x); }
+                add(x + 5u8);
+            }",
+        );
+        assert!(errors.is_empty(), "{:?}", errors);
+        // Only exported functions are in the header, with the types they might need
+        assert!(header.starts_with("#ifndef TEST_H\n"));
+        assert!(header.contains("int32_t main(void);"));
+        assert!(!header.contains("add("));
+        assert!(header.contains("typedef struct { size_t len; int32_t *ptr; } __slice_int32;"));
+
+        assert!(code.contains("void print(int32_t x);"));
+        assert!(code.contains("int32_t add(int32_t a) {\nreturn (a + 2);\n}"));
+        assert!(code.contains("__slice_int32 rows[4];"));
+        // A statement that ends with `;` is discarded, even at the end
+        assert!(code.contains("\nadd((x + ((uint8_t)5)));\n}"));
+
+        // The value of the last expression is returned
+        let (_, code, errors) = gen_c(
+            "int32 add(int32 a, int32 b) { return a + b; }
+            int32 main() {
+                int32 x = add(1, 2);
+                while x { x = x - 1; }
+                add(x, 5u8)
+            }",
+        );
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert!(code.contains("int32_t add(int32_t a, int32_t b) {\nreturn (a + b);\n}"));
+        assert!(code.contains("return add(x, ((uint8_t)5));"));
+    }
+
+    #[test]
+    fn test_c_else() {
+        let (_, code, errors) = gen_c(
+            "external void print(int32 x);
+            void f(int32 x) {
+                if x { print(1); } else if x - 1 { print(2); } else { print(3); }
+            }",
+        );
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert!(code.contains(
+            "if (x) {\nprint(1);\n} else if ((x - 1)) {\nprint(2);\n} else {\nprint(3);\n}\n"
+        ));
+    }
+
+    #[test]
+    fn test_c_pointers() {
+        let (_, code, errors) = gen_c(
+            "int32 f(@#int32 p, int32? o, [int32, int64] t) {
+                #int32 q = *p;
+                int32 x = *q;
+                @int32 r = @x;
+                *r
+            }",
+        );
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert!(code.contains("typedef struct { bool has; int32_t value; } __opt_int32;"));
+        assert!(code.contains("typedef struct { int32_t _0; int64_t _1; } __tuple2_int32_int64;"));
+        assert!(code
+            .contains("int32_t f(int32_t * const * p, __opt_int32 o, __tuple2_int32_int64 t) {"));
+        assert!(code.contains("int32_t const * r = (&x);\nreturn (*r);"));
+    }
+
+    #[test]
+    fn test_c_match() {
+        let (_, code, errors) = gen_c(
+            "external void print(int32 x);
+            int32 f(int32 n) {
+                while n {
+                    match n { 1 => { print(1); } _ => { break; } }
+                    match n { 1 => { print(1); } _ => {} }
+                }
+                match n { 0..10 => n, x => x + 1 }
+            }",
+        );
+        assert!(errors.is_empty(), "{:?}", errors);
+        // A `break` in a `switch` wouldn't leave the loop, so only the second match is one
+        assert_eq!(code.matches("switch (").count(), 1);
+        assert!(code.contains(" == 1) {\nprint(1);\ngoto __match_end_"));
+        assert!(code.contains(") {\ncase 1: {\nprint(1);\nbreak;\n}\ndefault: {\nbreak;\n}"));
+
+        // The last arm isn't tested, since the match is exhaustive
+        assert!(code.contains("return ({ __auto_type __match_"));
+        assert!(code.contains(" < 10) ? ({\nn;\n}) : ({\n__auto_type x = __match_"));
+        assert!(code.contains(";\n(x + 1);\n}); });"));
+    }
+
+    #[test]
+    fn test_c_checks() {
+        // The C backend checks what it would write wrong on its own
+        let (_, _, errors) = gen_c("void f() { break; }");
+        assert_eq!(errors.len(), 1);
+        assert!(
+            errors[0].contains("`break` outside of a loop"),
+            "{:?}",
+            errors
+        );
+
+        let (_, _, errors) = gen_c(
+            "enum Shape { Circle(int1), Square }
+            int32 f(Shape s) { match s { Shape::Circle(true) => 1, Shape::Square => 2 } }",
+        );
+        assert_eq!(errors.len(), 1);
+        assert!(
+            errors[0].contains("`Shape::Circle(false)` is not covered"),
+            "{:?}",
+            errors
+        );
+
+        // Integer matches take the type of the variable, parameter or call they're on
+        let (_, code, errors) = gen_c(
+            "int8 g() { 0 }
+            int32 f(int8 n) {
+                int8 m = n;
+                int32 a = match n { -128..0 => 1, 0..128 => 2 };
+                int32 b = match m { -128..0 => a, 0..128 => 2 };
+                match g() { -128..0 => b, 0..128 => 2 }
+            }",
+        );
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert!(code.contains("int32_t f(int8_t n)"), "{}", code);
+
+        let (_, _, errors) = gen_c("int32 f(int32 n) { match n { -128..0 => 1, 0..128 => 2 } }");
+        assert_eq!(errors.len(), 1);
+        assert!(
+            errors[0].contains("`..-128` is not covered"),
+            "{:?}",
+            errors
+        );
+
+        let (_, _, errors) = gen_c(
+            "struct Point { int32 x; int32 y; }
+            Point f() { Point { x: 1, z: 2 } }",
+        );
+        assert_eq!(errors.len(), 2, "{:?}", errors);
+        assert!(errors[0].contains("No field named `z`"), "{:?}", errors);
+        assert!(errors[1].contains("Missing fields `y`"), "{:?}", errors);
+    }
+
+    #[test]
+    fn test_compound_assign() {
+        let (ir, errors) = gen_program(
+            "external #int32 next();
+            int32 f(int32 b) { *next() += b }",
+        );
+        assert!(errors.is_empty(), "{:?}", errors);
+        // The target is evaluated once, then loaded, added to and stored through
+        let entry = ir.split("\n\n").find(|b| b.starts_with("entry:")).unwrap();
+        assert_eq!(entry.matches("call ptr @next()").count(), 1);
+        assert!(entry.contains("%1 = call ptr @next()"));
+        assert_eq!(entry.matches("load i32, ptr %1").count(), 1);
+        assert!(entry.contains("add i32"));
+        assert!(entry.contains("store i32 %4, ptr %1"));
+
+        let (_, code, errors) = gen_c(
+            "external #int32 next();
+            void f(int32 a, int32 b) { *next() <<= a = b += 1; }",
+        );
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert!(code.contains("((*next()) <<= (a = (b += 1)));"));
+    }
+
+    #[test]
+    fn test_short_circuit() {
+        // (a && b) || c
+        let ir = gen_function("a && b || c");
+        assert_eq!(ir.matches(" phi ").count(), 2);
+
+        // Only `a` is read before branching, `b` and `c` are read in blocks of their own
+        let entry = ir.split("\n\n").find(|b| b.starts_with("entry:")).unwrap();
+        assert!(entry.contains("%a"));
+        assert!(!entry.contains("%b") && !entry.contains("%c"));
+
+        let rhs: Vec<_> = ir
+            .split("\n\n")
+            .filter(|b| b.starts_with("logic.rhs"))
+            .collect();
+        assert_eq!(rhs.len(), 2);
+        assert!(rhs.iter().any(|b| b.contains("%b") && !b.contains("%c")));
+        assert!(rhs.iter().any(|b| b.contains("%c") && !b.contains("%b")));
+
+        // The division is only reached once the left side held
+        let (ir, errors) = gen_program("int1 f(int32 n, int32 total) { n != 0 && total / n > 2 }");
+        assert!(errors.is_empty(), "{:?}", errors);
+        let entry = ir.split("\n\n").find(|b| b.starts_with("entry:")).unwrap();
+        assert!(entry.contains("icmp ne i32"));
+        assert!(!entry.contains("sdiv"));
+        assert!(ir.contains("icmp sgt i32"));
+
+        // Ordering needs both sides to have the same sign
+        let (_, errors) = gen_program("int1 f(int32 a) { a < 1u32 }");
+        assert_eq!(errors.len(), 1);
+    }
+
+    #[test]
+    fn test_unary() {
+        let ir = gen_function("!a && !(b || c)");
+        assert_eq!(ir.matches(" xor i1 ").count(), 2);
+
+        let (ir, errors) = gen_program("int32 f(int32 x) { -x + ~x }");
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert!(ir.contains("sub i32 0, "));
+        assert!(ir.contains("xor i32 ") && ir.contains(", -1\n"));
+
+        // A negative literal takes the type that's expected, like any other literal
+        let (ir, errors) = gen_program(
+            "void g(int32 x) {}
+            int32 f(int32 y) { int32 x = -1; g(-2); g(y + -3); return -4; }",
+        );
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert!(ir.contains("store i32 -1, "));
+        assert!(ir.contains("call void @g(i32 -2)"));
+        assert!(ir.contains("add i32 %") && ir.contains(", -3\n"));
+        assert!(ir.contains("store i32 -4, "));
+
+        // A literal has to fit in the type it takes, one further when it's negative
+        let (ir, errors) = gen_program("int8 f() { int8 x = -128; int128 y = -1; x + 127 }");
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert!(ir.contains("store i8 -128, "));
+        assert!(ir.contains("store i128 -1, "));
+
+        let (_, errors) =
+            gen_program("void f() { int8 x = 128; int8 y = -129; int8 z = (300); int8 w = ~300; }");
+        assert_eq!(errors.len(), 4, "{:?}", errors);
+        assert!(
+            errors[0].contains("`128` doesn't fit in a signed 8-bit integer"),
+            "{:?}",
+            errors
+        );
+        assert!(errors[1].contains("`-129` doesn't fit"), "{:?}", errors);
+
+        // A suffixed literal reaches the minimum of its type only when negated
+        let (ir, errors) =
+            gen_program("void f() { int8 x = -128i8; int64 y = -9223372036854775808i64; }");
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert!(ir.contains("store i8 -128, "));
+        assert!(ir.contains("store i64 -9223372036854775808, "));
+
+        let (_, errors) = gen_program("void f() { int8 x = 128i8; int8 y = -(128i8); }");
+        assert_eq!(errors.len(), 2, "{:?}", errors);
+        assert!(
+            errors[0].contains("`128` doesn't fit in a signed 8-bit integer"),
+            "{:?}",
+            errors
+        );
+
+        let (_, code, errors) = gen_c("int64 f() { int8 x = -128i8; -9223372036854775808i64 }");
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert!(code.contains("int8_t x = ((int8_t)-128);"));
+        assert!(code.contains("return ((int64_t)(-9223372036854775807ll - 1));"));
+
+        let (_, _, errors) = gen_c("int8 f() { 128i8 }");
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+
+        // `!` only applies to booleans and `-` to signed integers
+        let (_, errors) = gen_program(
+            "int32 f(int32 x) { !x }
+            int32 g() { -1u32 }",
+        );
+        assert_eq!(errors.len(), 2, "{:?}", errors);
+
+        let (_, code, errors) = gen_c("int1 f(int1 b, int32 x) { !b && (-x + ~x) }");
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert!(code.contains("bool f(bool b, int32_t x) {\nreturn ((!b) && (((-x) + (~x))));"));
+    }
+
+    #[test]
+    fn test_if_expression() {
+        let ir = gen_function("if a { b } else if c { { a } } else { c }");
+
+        // One phi for the whole chain, with a value from each branch
+        let phis: Vec<_> = ir.lines().filter(|l| l.contains(" phi ")).collect();
+        assert_eq!(phis.len(), 1);
+        assert_eq!(phis[0].matches('[').count(), 3);
+
+        // Literals at the end of branches and blocks take the type that's expected
+        let program = "int32 f(int1 c) {
+                int32 b = if c { 1 } else { 2 };
+                int32 d = { 5 };
+                if c { b } else { d + 1 }
+            }
+            int32 g() { 0 }";
+        let (ir, errors) = gen_program(program);
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert!(ir.contains("phi i32 [ 1, %then ], [ 2, %else ]"));
+        assert!(ir.contains("store i32 5, "));
+        assert!(ir.contains("store i32 0, "));
+
+        let (_, code, errors) = gen_c(program);
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert!(code.contains("int32_t b = (c ? (1) : (2));"));
+
+        let (_, code, errors) = gen_c(
+            "external void print(int32 x);
+            int32 f(int32 a, int32 b) {
+                int32 y = if a { b } else if b { { a } } else { print(a); b };
+                if a { return y; } else { return b; }
+            }",
+        );
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert!(code.contains("int32_t y = (a ? (b) : (b ? ((a)) : ({\nprint(a);\nb;\n})));"));
+        // Branches that return on their own are a statement
+        assert!(code.contains("if (a) {\nreturn y;\n} else {\nreturn b;\n}"));
+    }
+
+    #[test]
+    fn test_strings() {
+        let program = "external void puts(str s);
+            str greet() { return \"hi\"; }
+            str name() { (\"bob\") }
+            void main() { puts(\"hi\"); str s = \"a\\n\"; puts(s); }";
+        let (ir, errors) = gen_program(program);
+        assert!(errors.is_empty(), "{:?}", errors);
+        // Each literal is a global with a nul after it, which isn't part of the length
+        assert!(ir.contains("private unnamed_addr constant [3 x i8] c\"hi\\00\""));
+        assert!(ir.contains("c\"a\\0A\\00\""));
+        assert!(ir.contains("{ i64 3, "));
+
+        let (_, code, errors) = gen_c(program);
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert!(code.contains("return ((__slice_int8){ 2, (int8_t *)\"hi\" });"));
+        assert!(code.contains("puts(((__slice_int8){ 2, (int8_t *)\"hi\" }));"));
+        assert!(code.contains("__slice_int8 s = ((__slice_int8){ 2, (int8_t *)\"a\\012\" });"));
+    }
+
+    #[test]
+    fn test_optionals() {
+        let program = "int32? first(int32? a, int32? b) { if a != none { a } else { b } }
+            int1 f() { int32? a = 5; int32? b = none; int32? c = first(a, 7); c == none }";
+        let (ir, errors) = gen_program(program);
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert!(ir.contains("store { i1, i32 } { i1 true, i32 5 }, "));
+        assert!(ir.contains("store { i1, i32 } zeroinitializer, "));
+        assert!(ir.contains("{ i1, i32 } { i1 true, i32 7 })"));
+        assert!(ir.contains("%has = extractvalue { i1, i32 } "));
+
+        let (_, code, errors) = gen_c(program);
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert!(code.contains("__opt_int32 a = __some_opt_int32(5);"));
+        assert!(code.contains("__opt_int32 b = ((__opt_int32){ false });"));
+        assert!(code.contains(
+            "first(_Generic((a), __opt_int32: __keep_opt_int32, default: __some_opt_int32)(a), \
+             __some_opt_int32(7))"
+        ));
+        assert!(code.contains("(((a).has) ? "));
+        assert!(code.contains("return (!(c).has);"));
+
+        let (_, errors) = gen_program("void f() { int32 a = none; }");
+        assert_eq!(errors.len(), 1);
+    }
+
+    #[test]
+    fn test_for_range() {
+        let (ir, errors) = gen_program(
+            "int32 sum(int32 n) {
+                int32 total = 0;
+                for i in 0..n { total += i; }
+                for i in n..10 { total += i; }
+                total
+            }",
+        );
+        assert!(errors.is_empty(), "{:?}", errors);
+        // The literal bounds count in the type of `n`
+        assert!(ir.contains("store i32 0, ptr %i"));
+        assert_eq!(ir.matches("icmp slt i32").count(), 2);
+
+        // Unsigned bounds compare unsigned, so `200u8` isn't -56
+        let (ir, errors) = gen_program("void f() { for i in 0u8..200u8 {} for i in 0..200u8 {} }");
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(ir.matches("icmp ult i8").count(), 2);
+
+        let (_, errors) = gen_program("void f(int8 n) { for i in n..200u8 {} }");
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+
+        let (_, errors) = gen_program("void f(int32 n, int64 m) { for i in n..m {} }");
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+
+        let (_, code, errors) = gen_c(
+            "external void print(int32 x);
+            void f(int32 n, int32 xs[]) {
+                for i in 0..n { print(i); }
+                for x in xs { print(x); }
+            }",
+        );
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert!(code.contains("for (__typeof__(n) i = 0, __end_i = n; i < __end_i; i++) {"));
+        assert!(code.contains("__auto_type __slice_x = xs;"));
+        assert!(code.contains("__auto_type x = __slice_x.ptr[__index_x];\nprint(x);"));
+    }
+
+    #[test]
+    fn test_loop_labels() {
+        let (ir, errors) = gen_program(
+            "void f(int32 n) {
+                'outer: while n {
+                    while n { break 'outer; }
+                }
+            }",
+        );
+        assert!(errors.is_empty(), "{:?}", errors);
+        // The inner loop's body jumps straight past the outer loop
+        let inner = ir
+            .split("\n\n")
+            .find(|b| b.starts_with("while.body2:"))
+            .unwrap();
+        assert!(inner.ends_with("br label %while.end"), "{}", inner);
+
+        let (_, errors) = gen_program("void f() { while 1 { break 'missing; } }");
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("undeclared label"), "{:?}", errors);
+
+        // C labels are per function, so each loop's are named after where it is
+        let (_, code, errors) = gen_c(
+            "void f(int32 n) {
+                'outer: while n {
+                    for i in 0..n { continue 'outer; }
+                    break;
+                }
+                'outer: while n { break 'outer; }
+            }",
+        );
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert!(code.contains("i++) {\ngoto __continue_outer_"));
+        assert!(code.contains("break;\n__continue_outer_"));
+        assert_eq!(code.matches("__break_outer_").count(), 3);
+
+        let (_, _, errors) = gen_c("void f() { while 1 { continue 'missing; } }");
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("undeclared label"), "{:?}", errors);
+    }
+
+    #[test]
+    fn test_type_aliases() {
+        let (ir, errors) = gen_program(
+            "typedef Int = int32;
+            typedef distinct Meters = int64;
+            int32 f(Int x) { x }
+            Meters g(Meters m) { Meters n = 5; n = m + 1; n }",
+        );
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert!(ir.contains("define i32 @f(i32 %0)"));
+        assert!(ir.contains("define i64 @g(i64 %0)"));
+
+        let (header, _, errors) = gen_c(
+            "typedef Int = int64;
+            typedef distinct Meters = Int;",
+        );
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert!(header.contains("typedef int64_t Int;"));
+        assert!(header.contains("typedef Int Meters;"));
+
+        // A distinct alias isn't assignable to its base type
+        let (_, errors) = gen_program(
+            "typedef distinct Meters = int64;
+            int64 f(Meters m) { m }
+            void g(Meters m) { int64 x = m; }",
+        );
+        assert_eq!(errors.len(), 2, "{:?}", errors);
+
+        // Both backends keep the fields and variants of a distinct alias under its own name
+        let program = "enum Dir { Up, Down }
+            typedef distinct Way = Dir;
+            struct Point { int32 x; int32 y; }
+            typedef distinct Pos = Point;
+            int32 f(Way w) { match w { Way::Up => 1, Way::Down => 2 } }
+            int32 g(Pos p) {
+                Pos q = Pos { x: p.x, y: 2 };
+                match q { Pos { x, y: 2 } => x, _ => q.y }
+            }";
+        let (_, errors) = gen_program(program);
+        assert!(errors.is_empty(), "{:?}", errors);
+        let (_, code, errors) = gen_c(program);
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert!(code.contains(" == Dir_Up)"), "{}", code);
+        assert!(!code.contains("Way_"), "{}", code);
+
+        let program = program.replace("Way::", "Dir::");
+        let (_, errors) = gen_program(&program);
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        let (_, _, errors) = gen_c(&program);
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+    }
+
+    #[test]
+    fn test_int_exhaustiveness() {
+        let space = Space::int(8, false);
+
+        let arms = patterns("0; 1..10; 20..256", &space);
+        assert_eq!(missing_pattern(&space, &arms).as_deref(), Some("10..20"));
+
+        let arms = patterns("0; 1..10", &space);
+        assert_eq!(missing_pattern(&space, &arms).as_deref(), Some("10.."));
+
+        let arms = patterns("0; 1..10; _", &space);
+        assert_eq!(missing_pattern(&space, &arms), None);
+
+        let space = Space::int(8, true);
+        let arms = patterns("-128..-1; 0..128", &space);
+        assert_eq!(missing_pattern(&space, &arms).as_deref(), Some("-1"));
+
+        let arms = patterns("-128..-1; -1; 0..128", &space);
+        assert_eq!(missing_pattern(&space, &arms), None);
+
+        // Widths an `i128` can't hold don't overflow
+        let space = Space::int(128, false);
+        let arms = patterns("0; 1..10", &space);
+        assert_eq!(missing_pattern(&space, &arms).as_deref(), Some("10.."));
+
+        let (_, errors) = gen_program("int32 f(int128 x) { match x { -1 => 1, _ => 2 } }");
+        assert!(errors.is_empty(), "{:?}", errors);
+    }
+
+    #[test]
+    fn test_enum_exhaustiveness() {
+        let space = Space::Enum {
+            name: "Shape".to_string(),
+            variants: vec![
+                ("Circle".to_string(), vec![Space::Bool]),
+                ("Square".to_string(), vec![]),
+            ],
+        };
+
+        let arms = patterns("Shape::Circle(true); Shape::Square", &space);
+        assert_eq!(
+            missing_pattern(&space, &arms).as_deref(),
+            Some("Shape::Circle(false)")
+        );
+
+        let arms = patterns("Shape::Circle(filled); Shape::Square", &space);
+        assert_eq!(missing_pattern(&space, &arms), None);
+    }
+
+    #[test]
+    fn test_enum_layout() {
+        let (ir, errors) = gen_program(
+            "enum Color { Red = 1, Green, Blue = 10 }
+            enum Shape: int8 { Circle(int32), Rect(int64, int8), Empty }
+            int64 f(Color c) {
+                match c { Color::Green => 1, _ => 0 }
+            }
+            int64 g(Shape s) {
+                match s { Shape::Empty => 1, _ => 0 }
+            }",
+        );
+        assert!(errors.is_empty(), "{:?}", errors);
+        // A plain enum is its tag, counting on from the last explicit discriminant
+        assert!(ir.contains("define i64 @f(i32 %0)"));
+        assert!(ir.contains("i32 2, label %match.arm"));
+        // A tagged union is its tag and storage for the largest payload, aligned like it
+        assert!(ir.contains("%Shape = type { i8, [2 x i64] }"));
+        assert!(ir.contains("icmp eq i8 %tag, 2"));
+
+        let (header, code, errors) = gen_c(
+            "enum Color { Red = 1, Green }
+            enum Shape: int8 { Circle(int32), Empty }
+            int32 g(Shape s) {
+                match s { Shape::Circle(r) => r, _ => 0 }
+            }",
+        );
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert!(
+            header.contains("enum {\nColor_Red = 1,\nColor_Green = 2,\n};\ntypedef int32_t Color;")
+        );
+        assert!(header.contains("typedef struct Shape Shape;"));
+        assert!(header.contains(
+            "struct Shape {\nint8_t tag;\nunion {\nstruct {\nint32_t _0;\n} Circle;\n} as;\n};"
+        ));
+        assert!(code.contains(".tag == Shape_Circle && (1)) ? ({\n__auto_type r = __match_"));
+        assert!(code.contains(".as.Circle._0;\nr;"));
+
+        let (_, errors) = gen_program("enum Small: int8 { A = 127, B }");
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("doesn't fit in `int8`"), "{:?}", errors);
+
+        let (_, _, errors) = gen_c("enum Small: int8 { A = 127, B }");
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("doesn't fit in `int8`"), "{:?}", errors);
+
+        // Discriminants can be negative, down to the smallest value of the tag
+        let program = "enum Sign: int8 { Negative = -128, Zero = 0, Positive }
+            int32 f(Sign s) { match s { Sign::Negative => 1, _ => 2 } }";
+        let (ir, errors) = gen_program(program);
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert!(ir.contains("i8 -128, label %match.arm"), "{}", ir);
+        let (header, _, errors) = gen_c(program);
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert!(header.contains("Sign_Negative = -128,"));
+
+        // Counting on from the largest discriminant doesn't wrap around
+        for program in [
+            "enum Low: int8 { A = -129 }",
+            "enum High: int64 { A = 9223372036854775807, B }",
+        ] {
+            let (_, errors) = gen_program(program);
+            assert_eq!(errors.len(), 1, "{:?}", errors);
+            assert!(errors[0].contains("doesn't fit"), "{:?}", errors);
+        }
+
+        let (_, errors) = gen_program("enum Twice { A = 1, B = 0, C }");
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("used more than once"), "{:?}", errors);
+
+        let (_, _, errors) = gen_c("enum Twice { A = 1, B = 0, C }");
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("used more than once"), "{:?}", errors);
+
+        for tag in ["int0", "int65"] {
+            let program = format!("enum Wide: {} {{ A, B }}", tag);
+            let (_, errors) = gen_program(&program);
+            assert_eq!(errors.len(), 1);
+            assert!(errors[0].contains("bits wide"), "{:?}", errors);
+
+            let (_, _, errors) = gen_c(&program);
+            assert_eq!(errors.len(), 1);
+            assert!(errors[0].contains("bits wide"), "{:?}", errors);
+        }
+    }
+
+    #[test]
+    fn test_match_exhaustiveness() {
+        let program = "enum Shape { Circle(int32), Square }
+            int64 f(Shape s) {
+                match s {
+                    Shape::Circle(0) => 0,
+                    Shape::Square => 1,
+                }
+            }";
+        let (_, errors) = gen_program(program);
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("`Shape::Circle("), "{:?}", errors);
+
+        let (_, errors) = gen_program(&program.replace("Circle(0)", "Circle(r)"));
+        assert!(errors.is_empty(), "{:?}", errors);
+    }
+
+    #[test]
+    fn test_match_values() {
+        let program = "int32 f(int1 c, int8 x) {
+                int32 r = match c { true => 1, false => 2 };
+                match x { -128..-1 => { 3 } -1 => r, _ => 4 }
+            }";
+        let (ir, errors) = gen_program(program);
+        assert!(errors.is_empty(), "{:?}", errors);
+        // Arms take the type that's expected, like the branches of an `if`
+        assert!(ir.contains("store i32 1, "));
+        assert!(ir.contains("store i32 3, "));
+
+        let (_, code, errors) = gen_c(program);
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert!(code.contains(" >= -128 && __match_"));
+        assert!(code.contains(" == -1) ? "));
+
+        // An arm after one with the same case can't match, and doesn't get a case of its own
+        let program = "external void print(int32 x);
+            void f(int32 x) { match x { 1 => { print(1); } 1 => { print(2); } _ => {} } }";
+        let (ir, errors) = gen_program(program);
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert!(!ir.contains("i32 2)"));
+
+        let (_, code, errors) = gen_c(program);
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(code.matches("case 1: {").count(), 1);
+        assert!(!code.contains("print(2)"));
+    }
+
+    #[test]
+    fn test_fields() {
+        let (ir, errors) = gen_program(
+            "struct Point { int32 x; int32 y; }
+            int32 f() {
+                Point p = Point { x: 1, y: 2 };
+                p.y = p.x + 3;
+                p.y
+            }",
+        );
+        assert!(errors.is_empty(), "{:?}", errors);
+        // Stores go through a pointer to the field, loads read it out of the struct
+        let gep = ir
+            .lines()
+            .find(|l| l.contains("getelementptr inbounds %Point, ptr %p, i32 0, i32 1"))
+            .unwrap();
+        let field = gep.trim().split(' ').next().unwrap();
+        let store = format!("ptr {},", field);
+        assert!(ir
+            .lines()
+            .any(|l| l.contains("store i32") && l.contains(&store)));
+        assert!(ir
+            .lines()
+            .any(|l| l.contains("extractvalue %Point") && l.ends_with(", 0")));
+        assert!(ir
+            .lines()
+            .any(|l| l.contains("extractvalue %Point") && l.ends_with(", 1")));
+
+        let (_, errors) = gen_program(
+            "struct Point { int32 x; int32 y; }
+            void f(Point p) { p.z = 1; }",
+        );
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+
+        // Types are declared before their fields, so they can point to themselves
+        let program = "struct Node { int32 value; @Node next; }
+            enum List { Cons(int32, @List), Nil }
+            int32 f(Node n, List l) {
+                match l { List::Cons(x, _) => x + n.value, List::Nil => (*n.next).value }
+            }";
+        let (ir, errors) = gen_program(program);
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert!(ir.contains("%Node = type { i32, ptr }"));
+        let (header, _, errors) = gen_c(program);
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert!(header.contains("typedef struct Node Node;\n\nstruct Node {\nint32_t value;"));
+        assert!(header.contains("typedef struct List List;"));
+
+        // Only behind a pointer, since they don't have a size yet
+        for program in ["struct Node { Node next; }", "enum List { Cons(List) }"] {
+            let (_, errors) = gen_program(program);
+            assert_eq!(errors.len(), 1, "{:?}", errors);
+            assert!(errors[0].contains("type without a size"), "{:?}", errors);
+        }
+    }
+
+    #[test]
+    fn test_c_structs() {
+        let (header, code, errors) = gen_c(
+            "struct Point { int32 x; int32 y; }
+            packed struct Pair { int8 a; int64 b; }
+            opaque struct Handle {}
+            int32 len(Point p, int32 scale) { p.x + scale }
+            int32 f([int32, int64] t) {
+                Point p = Point { x: 1, y: t.0 };
+                p.len(2)
+            }",
+        );
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert!(header.contains("typedef struct Point Point;"));
+        assert!(header.contains("struct Point {\nint32_t x;\nint32_t y;\n};"));
+        assert!(header.contains("int64_t b;\n} __attribute__((packed));"));
+        assert!(header.contains("typedef struct Handle Handle;"));
+        assert!(!header.contains("struct Handle {"));
+
+        assert!(code.contains("Point p = (Point){ .x = 1, .y = (t)._0, };"));
+        // A method call passes the receiver first
+        assert!(code.contains("return len(p, 2);"));
+    }
+
+    #[test]
+    fn test_struct_patterns() {
+        let program = "struct Point { int32 x; int32 y; }
+            int32 f(Point p) {
+                match p {
+                    Point { x: 0, y } => y,
+                    Point { x, y: 1 } => x,
+                    _ => 2,
+                }
+            }";
+        let (ir, errors) = gen_program(program);
+        assert!(errors.is_empty(), "{:?}", errors);
+        // Fields are only read where an arm tests or binds them
+        assert_eq!(ir.matches("extractvalue %Point").count(), 4);
+        // The literal arm takes the type of the first arm
+        assert!(ir.contains("store i32 2, ptr %match"));
+
+        let (_, errors) = gen_program(&program.replace("_ => 2,", ""));
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("Non-exhaustive match"), "{:?}", errors);
+    }
+}
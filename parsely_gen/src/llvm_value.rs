@@ -4,21 +4,23 @@ bitflags::bitflags! {
         const SIGNED = 0b1;
         const MUTABLE = 0b10;
         const SLICE = 0b100;
+        /// Points to a `base_type`
+        const POINTER = 0b1000;
+        /// A `base_type` or nothing, stored as a flag for whether it's there and the value
+        const OPTIONAL = 0b10000;
     }
 }
 
 #[derive(Debug)]
 pub struct Function<'a> {
-    pub fn_type: inkwell::types::FunctionType<'a>,
     pub fn_val: inkwell::values::FunctionValue<'a>,
     pub param_types: Vec<Type<'a>>,
     pub return_type: Option<Type<'a>>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Variable<'a> {
     pub ty: Type<'a>,
-    pub flags: TypeFlags,
     pub alloc: inkwell::values::PointerValue<'a>,
 }
 
@@ -27,6 +29,8 @@ pub struct Type<'a> {
     pub llvm: inkwell::types::AnyTypeEnum<'a>,
     pub flags: TypeFlags,
     pub base_type: Option<Box<Type<'a>>>,
+    /// Name the type was declared with, for enums and other named types
+    pub name: Option<String>,
 }
 
 impl<'ctx> Type<'ctx> {
@@ -39,8 +43,21 @@ impl<'ctx> Type<'ctx> {
                 .as_any_type_enum(),
             flags: TypeFlags::empty(),
             base_type: Some(Box::new(self.clone())),
+            name: None,
         }
     }
+
+    /// True if values of this type have a size, unlike `void` or a struct without a body
+    pub fn is_sized(&self) -> bool {
+        use inkwell::types::BasicType;
+        inkwell::types::BasicTypeEnum::try_from(self.llvm).is_ok_and(|ty| ty.is_sized())
+    }
+
+    /// True if a value of this type can be stored where a `target` is expected. Named types
+    /// only mix with themselves, or with values that have no named type, like literals.
+    pub fn assignable_to(&self, target: &Type<'ctx>) -> bool {
+        self.llvm == target.llvm && (self.name.is_none() || self.name == target.name)
+    }
 }
 
 pub trait TypeBuilder<'ctx> {
@@ -60,6 +77,7 @@ impl<'ctx, T: inkwell::types::AnyType<'ctx>> TypeBuilder<'ctx> for T {
                 TypeFlags::empty()
             },
             base_type: None,
+            name: None,
         }
     }
 
@@ -72,6 +90,7 @@ impl<'ctx, T: inkwell::types::AnyType<'ctx>> TypeBuilder<'ctx> for T {
                 TypeFlags::empty()
             },
             base_type: None,
+            name: None,
         }
     }
 
@@ -80,6 +99,7 @@ impl<'ctx, T: inkwell::types::AnyType<'ctx>> TypeBuilder<'ctx> for T {
             llvm: self.as_any_type_enum(),
             flags: TypeFlags::empty(),
             base_type: Some(Box::new(base)),
+            name: None,
         }
     }
 
@@ -88,6 +108,7 @@ impl<'ctx, T: inkwell::types::AnyType<'ctx>> TypeBuilder<'ctx> for T {
             llvm: self.as_any_type_enum(),
             flags: TypeFlags::empty(),
             base_type: None,
+            name: None,
         }
     }
 }
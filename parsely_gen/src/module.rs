@@ -1,18 +1,22 @@
-use std::{fs::File, io::Write};
+use std::path::Path;
 
 use inkwell::targets::{
-    CodeModel, InitializationConfig, RelocMode, Target, TargetData, TargetMachine,
+    CodeModel, FileType, InitializationConfig, RelocMode, Target, TargetData, TargetMachine,
 };
+use parsely_lexer::{SourceDatabase, Span};
 use parsely_parser::item::Program;
 
-use crate::{symbols::SymbolTable, Diagnostic, DiagnosticFmt, Result};
+use crate::{
+    check::Checker, control::LoopBlocks, llvm_value::Variable, symbols::SymbolTable, Diagnostic,
+    DiagnosticFmt, Result,
+};
 
 pub(crate) const EMPTY_NAME: &str = "";
 
 pub struct Module<'ctx> {
     pub(crate) context: &'ctx inkwell::context::Context,
     pub(crate) module: inkwell::module::Module<'ctx>,
-    pub(crate) name: String,
+    pub(crate) source: &'ctx SourceDatabase,
     pub(crate) symbol_table: SymbolTable<'ctx>,
     errors: Vec<Diagnostic>,
     pub(crate) dirty: bool,
@@ -21,21 +25,34 @@ pub struct Module<'ctx> {
     pub(crate) alloc_block: Option<inkwell::basic_block::BasicBlock<'ctx>>,
     pub(crate) basic_block: Option<inkwell::basic_block::BasicBlock<'ctx>>,
     pub(crate) return_block: Option<inkwell::basic_block::BasicBlock<'ctx>>,
-    pub(crate) return_alloc: Option<inkwell::values::PointerValue<'ctx>>,
+    /// Where the value of the function is stored before returning, unless it returns `void`
+    pub(crate) return_alloc: Option<Variable<'ctx>>,
+    /// Loops enclosing the statement being generated, innermost last
+    pub(crate) loops: Vec<LoopBlocks<'ctx>>,
 
-    pub(crate) target: Target,
     pub(crate) target_machine: TargetMachine,
     pub(crate) target_data: TargetData,
 }
 
 impl<'ctx> Module<'ctx> {
-    pub fn new(name: impl ToString, context: &'ctx inkwell::context::Context) -> Module {
-        let (target, target_machine, target_data) = Self::default_target();
+    pub fn new(
+        name: impl ToString,
+        context: &'ctx inkwell::context::Context,
+        source: &'ctx SourceDatabase,
+    ) -> Module<'ctx> {
+        let (target_machine, target_data) = Self::default_target();
+
+        let module = context.create_module(&name.to_string());
+        module.set_triple(&target_machine.get_triple());
+        module.set_data_layout(&target_data.get_data_layout());
+
+        let mut symbol_table = SymbolTable::new();
+        symbol_table.push_scope();
 
         Module {
-            module: context.create_module(&name.to_string()),
-            name: name.to_string(),
-            symbol_table: SymbolTable::new(),
+            module,
+            source,
+            symbol_table,
             errors: Vec::new(),
             dirty: false,
 
@@ -44,57 +61,49 @@ impl<'ctx> Module<'ctx> {
             basic_block: None,
             return_block: None,
             return_alloc: None,
+            loops: Vec::new(),
 
             context,
-            target,
             target_machine,
             target_data,
         }
     }
 
-    pub fn run_new(name: impl ToString, program: &Program) -> Result<()> {
-        let ctx = inkwell::context::Context::create();
-        let (target, target_machine, target_data) = Self::default_target();
-
-        let mut module = Module {
-            module: ctx.create_module(&name.to_string()),
-            name: name.to_string(),
-            symbol_table: SymbolTable::new(),
-            errors: Vec::new(),
-            dirty: false,
-
-            builder: ctx.create_builder(),
-            alloc_block: None,
-            basic_block: None,
-            return_block: None,
-            return_alloc: None,
+    /// Diagnostics produced while generating this module
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.errors
+    }
 
-            context: &ctx,
-            target,
-            target_machine,
-            target_data,
-        };
+    /// Source files the module is generated from
+    pub fn source(&self) -> &'ctx SourceDatabase {
+        self.source
+    }
 
-        module.symbol_table.push_scope();
+    /// Diagnostics of this module, formatted against its source files
+    pub fn format_diagnostics(&self) -> DiagnosticFmt<'_> {
+        DiagnosticFmt(&self.errors, self.source)
+    }
 
-        match module.run(program) {
-            Ok(_) | Err(Diagnostic::Caught(_)) => {
-                println!("{:#?}", module.symbol_table);
-                let output = module.module.to_string();
+    /// Returns true if any errors were produced during generation
+    pub fn has_errors(&self) -> bool {
+        self.dirty
+    }
 
-                // println!("{:#?}", program.tokens);
-                let fmtr = DiagnosticFmt(&module.errors, &module, program);
-                println!("{}", fmtr);
+    /// Textual LLVM IR of the module
+    pub fn llvm_ir(&self) -> String {
+        self.module.to_string()
+    }
 
-                let mut file = File::create("out.ll").unwrap();
-                write!(file, "{}", output).unwrap();
-                file.flush().unwrap();
-                println!("Here");
-            }
-            Err(e) => return Err(e),
-        }
+    /// Checks that the module is valid IR, which it is unless code generation has a bug
+    pub fn verify(&self) -> std::result::Result<(), String> {
+        self.module.verify().map_err(|e| e.to_string())
+    }
 
-        Ok(())
+    /// Write the module as a native object file for the host target
+    pub fn write_object_file(&self, path: &Path) -> std::result::Result<(), String> {
+        self.target_machine
+            .write_to_file(&self.module, FileType::Object, path)
+            .map_err(|e| e.to_string())
     }
 
     pub(crate) fn push_error(&mut self, error: Diagnostic) {
@@ -106,6 +115,13 @@ impl<'ctx> Module<'ctx> {
     }
 
     pub fn run(&mut self, program: &Program) -> Result<()> {
+        let errors = Checker::run(program);
+        if !errors.is_empty() {
+            self.errors.extend(errors);
+            self.dirty = true;
+            return Err(Diagnostic::Caught(Span::EMPTY));
+        }
+
         for item in &program.items {
             self.gen_item(item)?;
         }
@@ -114,7 +130,7 @@ impl<'ctx> Module<'ctx> {
         Ok(())
     }
 
-    fn default_target() -> (Target, TargetMachine, TargetData) {
+    fn default_target() -> (TargetMachine, TargetData) {
         Target::initialize_all(&InitializationConfig::default());
         let target = Target::from_triple(&TargetMachine::get_default_triple())
             .expect("Unable to get target");
@@ -131,6 +147,6 @@ impl<'ctx> Module<'ctx> {
             .expect("Unable to create target machine");
 
         let target_data = target_machine.get_target_data();
-        (target, target_machine, target_data)
+        (target_machine, target_data)
     }
 }
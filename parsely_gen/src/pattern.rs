@@ -0,0 +1,499 @@
+use inkwell::{
+    types::{AnyTypeEnum, BasicTypeEnum},
+    values::{IntValue, PointerValue},
+    IntPredicate,
+};
+use parsely_lexer::{tokens, AsSpan};
+use parsely_parser::pattern::{ArmBody, Match, Pattern};
+
+use crate::{
+    attempt,
+    enums::EnumLayout,
+    exhaustive::{lower_pattern, missing_pattern, Pat, Space},
+    llvm_value::{Type, TypeFlags, Value, Variable},
+    module::{Module, EMPTY_NAME},
+    raise, Diagnostic, Result,
+};
+
+impl<'ctx> Module<'ctx> {
+    /// Generates a match. Returns the value of the arm that ran, if every arm has a value.
+    ///
+    /// A match on an integer where every arm is a literal or `_` without a guard becomes a
+    /// `switch`. Anything else tests the arms one after the other. The values of the arms are
+    /// generated as `expected`, if given.
+    pub(crate) fn gen_match(
+        &mut self,
+        m: &Match,
+        expected: Option<&Type<'ctx>>,
+    ) -> Result<Option<Value<'ctx>>> {
+        let scrutinee = self.gen_expression(&m.expr)?;
+        let space = self.space_of(&scrutinee.ty);
+
+        let mut pats = Vec::new();
+        for arm in m.arms.value.iter() {
+            match lower_pattern(&arm.pattern, &space) {
+                Ok(pat) => pats.push(pat),
+                Err(e) => {
+                    self.push_error(e);
+                    return Err(Diagnostic::Caught(arm.pattern.as_span()));
+                }
+            }
+        }
+
+        // A guard can be false, so guarded arms don't cover anything
+        let covering: Vec<_> = pats
+            .iter()
+            .zip(m.arms.value.iter())
+            .filter(|(_, arm)| arm.guard.is_none())
+            .map(|(pat, _)| pat.clone())
+            .collect();
+
+        if let Some(missing) = missing_pattern(&space, &covering) {
+            let msg = format!("Non-exhaustive match, `{}` is not covered", missing);
+            raise!(@log Error => self, msg, m.expr.as_span());
+            return Err(Diagnostic::Caught(m.as_span()));
+        }
+
+        let switch = matches!(scrutinee.ty.llvm, AnyTypeEnum::IntType(_))
+            && m.arms.value.iter().zip(&pats).all(|(arm, pat)| {
+                arm.guard.is_none()
+                    && !matches!(arm.pattern, Pattern::Binding(_))
+                    && match pat {
+                        Pat::Int(lo, hi) => lo == hi,
+                        Pat::Variant(_, fields) => fields.is_empty(),
+                        Pat::Wild | Pat::Bool(_) => true,
+                        Pat::Struct(_) => false,
+                    }
+            });
+
+        let llvm_func = self
+            .bb()
+            .get_parent()
+            .expect("Basic block is not in a function!");
+        let end_block = self.context.append_basic_block(llvm_func, "match.end");
+
+        let mut result = MatchResult {
+            alloc: None,
+            expected: expected.cloned(),
+            all_valued: true,
+        };
+
+        if switch {
+            let value = scrutinee.llvm.into_int_value();
+            let default_block = self.context.append_basic_block(llvm_func, "match.default");
+
+            let mut cases = Vec::new();
+            let mut has_default = false;
+            let switch_block = *self.bb();
+
+            for (arm, pat) in m.arms.value.iter().zip(&pats) {
+                let case = match pat {
+                    Pat::Int(n, _) => Some(value.get_type().const_int(*n as u64, *n < 0)),
+                    Pat::Bool(b) => Some(value.get_type().const_int(*b as u64, false)),
+                    Pat::Variant(i, _) => {
+                        let d = self.enum_layout(&scrutinee).variants[*i].discriminant;
+                        Some(value.get_type().const_int(d as u64, d < 0))
+                    }
+                    _ => None,
+                };
+
+                // An earlier arm with the same case always matches first
+                if let Some(n) = case {
+                    if cases.iter().any(|(case, _)| *case == n) {
+                        continue;
+                    }
+                }
+
+                let arm_block = self.context.append_basic_block(llvm_func, "match.arm");
+                match case {
+                    Some(n) => cases.push((n, arm_block)),
+                    None => {
+                        self.position_at(default_block);
+                        self.builder.build_unconditional_branch(arm_block);
+                        has_default = true;
+                    }
+                }
+
+                self.position_at(arm_block);
+                self.gen_arm_body(&arm.body, &mut result);
+                self.builder.build_unconditional_branch(end_block);
+
+                // Nothing after a wildcard can match
+                if has_default {
+                    break;
+                }
+            }
+
+            if !has_default {
+                // Every value has a case, since the match is exhaustive
+                self.position_at(default_block);
+                self.builder.build_unreachable();
+            }
+
+            self.position_at(switch_block);
+            self.builder.build_switch(value, default_block, &cases);
+        } else {
+            for (arm, pat) in m.arms.value.iter().zip(&pats) {
+                let arm_block = self.context.append_basic_block(llvm_func, "match.arm");
+                let next_block = self.context.append_basic_block(llvm_func, "match.next");
+
+                match self.gen_pattern_test(&scrutinee, pat) {
+                    Some(matched) => {
+                        self.builder
+                            .build_conditional_branch(matched, arm_block, next_block);
+                    }
+                    None => {
+                        self.builder.build_unconditional_branch(arm_block);
+                    }
+                }
+
+                self.position_at(arm_block);
+                self.symbol_table.push_scope();
+                self.gen_pattern_bindings(&scrutinee, &arm.pattern);
+
+                if let Some(guard) = &arm.guard {
+                    let body_block = self.context.append_basic_block(llvm_func, "match.guard");
+                    let passed = attempt!(self, self.gen_condition(&guard.condition));
+                    if let Ok(passed) = passed {
+                        self.builder
+                            .build_conditional_branch(passed, body_block, next_block);
+                    } else {
+                        self.builder.build_unconditional_branch(next_block);
+                    }
+                    self.position_at(body_block);
+                }
+
+                self.gen_arm_body(&arm.body, &mut result);
+                self.symbol_table.pop_scope();
+                self.builder.build_unconditional_branch(end_block);
+
+                self.position_at(next_block);
+            }
+
+            // Every value matches some arm, since the match is exhaustive
+            self.builder.build_unreachable();
+        }
+
+        self.position_at(end_block);
+
+        match result {
+            MatchResult {
+                alloc: Some((alloc, ty)),
+                all_valued: true,
+                ..
+            } => {
+                let llvm: BasicTypeEnum = ty.llvm.try_into().expect("Unable to get basic type");
+                let value = self.builder.build_load(llvm, alloc, EMPTY_NAME);
+                Ok(Some(Value { llvm: value, ty }))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Space of the values of `ty`, for exhaustiveness checking
+    fn space_of(&self, ty: &Type<'ctx>) -> Space {
+        if let Some((name, layout)) = ty
+            .name
+            .as_ref()
+            .and_then(|name| Some((name, self.symbol_table.find_enum(name)?)))
+        {
+            return Space::Enum {
+                name: name.clone(),
+                variants: layout
+                    .variants
+                    .iter()
+                    .map(|v| {
+                        (
+                            v.name.clone(),
+                            v.fields.iter().map(|f| self.space_of(f)).collect(),
+                        )
+                    })
+                    .collect(),
+            };
+        }
+
+        if let Some((name, layout)) = ty
+            .name
+            .as_ref()
+            .and_then(|name| Some((name, self.symbol_table.find_struct(name)?)))
+        {
+            return Space::Struct {
+                name: name.clone(),
+                fields: layout
+                    .fields
+                    .iter()
+                    .map(|(field, ty)| (field.clone(), self.space_of(ty)))
+                    .collect(),
+            };
+        }
+
+        match ty.llvm {
+            AnyTypeEnum::IntType(i) if i.get_bit_width() == 1 => Space::Bool,
+            AnyTypeEnum::IntType(i) => {
+                Space::int(i.get_bit_width(), ty.flags.contains(TypeFlags::SIGNED))
+            }
+            _ => Space::Opaque,
+        }
+    }
+
+    /// Generates the body of an arm, storing its value into the result of the match
+    ///
+    /// Every arm needs a value of the type of the first one, otherwise the match has no value.
+    /// The first one is generated as the expected type, if there is one.
+    fn gen_arm_body(&mut self, body: &ArmBody, result: &mut MatchResult<'ctx>) {
+        let expected = match &result.alloc {
+            Some((_, ty)) => Some(ty.clone()),
+            None => result.expected.clone(),
+        };
+
+        let value = match (body, &expected) {
+            (ArmBody::Expression(expr), Some(ty)) => {
+                attempt!(self, self.gen_expression_as(expr, ty)).ok()
+            }
+            (ArmBody::Expression(expr), None) => attempt!(self, self.gen_expression(expr)).ok(),
+            (ArmBody::Block(body), ty) => self.gen_block(&body.value, ty.as_ref()),
+        };
+
+        let Some(value) = value else {
+            result.all_valued = false;
+            return;
+        };
+
+        let alloc = match &result.alloc {
+            Some((alloc, ty)) if value.ty.llvm == ty.llvm => *alloc,
+            Some(_) => {
+                result.all_valued = false;
+                return;
+            }
+            None => {
+                let alloc = self.insert_alloca(&value.ty, "match");
+                result.alloc = Some((alloc, value.ty.clone()));
+                alloc
+            }
+        };
+
+        self.builder.build_store(alloc, value.llvm);
+    }
+
+    /// Generates an `i1` that is true if `value` matches `pat`, or `None` if it always does
+    fn gen_pattern_test(&mut self, value: &Value<'ctx>, pat: &Pat) -> Option<IntValue<'ctx>> {
+        match pat {
+            Pat::Wild => return None,
+            Pat::Variant(i, fields) => return Some(self.gen_variant_test(value, *i, fields)),
+            Pat::Struct(fields) => return self.gen_struct_test(value, fields),
+            Pat::Bool(_) | Pat::Int(..) => (),
+        }
+
+        let int = value.llvm.into_int_value();
+        let ty = int.get_type();
+
+        let (ge, le) = if value.ty.flags.contains(TypeFlags::SIGNED) {
+            (IntPredicate::SGE, IntPredicate::SLE)
+        } else {
+            (IntPredicate::UGE, IntPredicate::ULE)
+        };
+
+        match pat {
+            Pat::Bool(b) => Some(self.builder.build_int_compare(
+                IntPredicate::EQ,
+                int,
+                ty.const_int(*b as u64, false),
+                EMPTY_NAME,
+            )),
+            Pat::Int(lo, hi) if lo == hi => Some(self.builder.build_int_compare(
+                IntPredicate::EQ,
+                int,
+                ty.const_int(*lo as u64, *lo < 0),
+                EMPTY_NAME,
+            )),
+            Pat::Int(lo, hi) => {
+                let above = self.builder.build_int_compare(
+                    ge,
+                    int,
+                    ty.const_int(*lo as u64, *lo < 0),
+                    EMPTY_NAME,
+                );
+                let below = self.builder.build_int_compare(
+                    le,
+                    int,
+                    ty.const_int(*hi as u64, *hi < 0),
+                    EMPTY_NAME,
+                );
+                Some(self.builder.build_and(above, below, EMPTY_NAME))
+            }
+            Pat::Wild | Pat::Variant(_, _) | Pat::Struct(_) => unreachable!("handled above"),
+        }
+    }
+
+    /// Generates an `i1` that is true if the fields of the struct `value` match `fields`, or
+    /// `None` if they always do
+    fn gen_struct_test(&mut self, value: &Value<'ctx>, fields: &[Pat]) -> Option<IntValue<'ctx>> {
+        let mut matched = None;
+        for (i, field) in fields.iter().enumerate() {
+            if let Pat::Wild = field {
+                continue;
+            }
+
+            let field_value = self.gen_struct_field(value, i);
+            let Some(field_matched) = self.gen_pattern_test(&field_value, field) else {
+                continue;
+            };
+
+            matched = Some(match matched {
+                Some(matched) => self.builder.build_and(matched, field_matched, EMPTY_NAME),
+                None => field_matched,
+            });
+        }
+
+        matched
+    }
+
+    /// Reads field `index` of the struct `value`
+    fn gen_struct_field(&mut self, value: &Value<'ctx>, index: usize) -> Value<'ctx> {
+        let layout = value
+            .ty
+            .name
+            .as_ref()
+            .and_then(|name| self.symbol_table.find_struct(name))
+            .expect("Struct pattern on a value that isn't a struct!");
+        let ty = layout.fields[index].1.clone();
+
+        let llvm = self
+            .builder
+            .build_extract_value(value.llvm.into_struct_value(), index as u32, EMPTY_NAME)
+            .expect("Field out of bounds!");
+
+        Value { llvm, ty }
+    }
+
+    /// Generates an `i1` that is true if `value` is variant `index`, and its payload matches
+    /// `fields`
+    fn gen_variant_test(
+        &mut self,
+        value: &Value<'ctx>,
+        index: usize,
+        fields: &[Pat],
+    ) -> IntValue<'ctx> {
+        let layout = self.enum_layout(value);
+        let variant = &layout.variants[index];
+
+        let tag = self.gen_tag(value);
+        let d = variant.discriminant;
+        let discriminant = tag.get_type().const_int(d as u64, d < 0);
+        let mut matched =
+            self.builder
+                .build_int_compare(IntPredicate::EQ, tag, discriminant, EMPTY_NAME);
+
+        for (i, field) in fields.iter().enumerate() {
+            // The payload is only loaded after the tag is checked, but loading it is harmless
+            let field_value = self.gen_payload_field(value, variant, i);
+            if let Some(field_matched) = self.gen_pattern_test(&field_value, field) {
+                matched = self.builder.build_and(matched, field_matched, EMPTY_NAME);
+            }
+        }
+
+        matched
+    }
+
+    /// Layout of the enum `value` is an instance of
+    fn enum_layout(&self, value: &Value<'ctx>) -> EnumLayout<'ctx> {
+        value
+            .ty
+            .name
+            .as_ref()
+            .and_then(|name| self.symbol_table.find_enum(name))
+            .cloned()
+            .expect("Variant pattern on a value that isn't an enum!")
+    }
+
+    /// Declares the variables `pattern` binds, for a `value` that matches it
+    fn gen_pattern_bindings(&mut self, value: &Value<'ctx>, pattern: &Pattern) {
+        if let Pattern::Variant(v) = pattern {
+            let layout = self.enum_layout(value);
+            let Some(variant) = layout.variants.iter().find(|l| l.name == v.variant.value) else {
+                return;
+            };
+
+            for (i, field) in v.fields.iter().flat_map(|f| f.value.iter()).enumerate() {
+                let field_value = self.gen_payload_field(value, variant, i);
+                self.gen_pattern_bindings(&field_value, field);
+            }
+        } else if let Pattern::Struct(s) = pattern {
+            let Some(layout) = self.symbol_table.find_struct(&s.ty.value) else {
+                return;
+            };
+            let indices: Vec<_> = s
+                .fields
+                .value
+                .iter()
+                .map(|field| {
+                    layout
+                        .fields
+                        .iter()
+                        .position(|(f, _)| *f == field.ident.value)
+                })
+                .collect();
+
+            for (field, index) in s.fields.value.iter().zip(indices) {
+                let Some(index) = index else {
+                    continue;
+                };
+
+                match &field.pattern {
+                    Some((_, pattern)) if has_bindings(pattern) => {
+                        let field_value = self.gen_struct_field(value, index);
+                        self.gen_pattern_bindings(&field_value, pattern);
+                    }
+                    Some(_) => (),
+                    // `Point { x }` binds the field to a variable with its name
+                    None => {
+                        let field_value = self.gen_struct_field(value, index);
+                        self.gen_binding(&field_value, &field.ident);
+                    }
+                }
+            }
+        } else if let Pattern::Binding(ident) = pattern {
+            self.gen_binding(value, ident);
+        }
+    }
+
+    /// Declares a variable holding `value`
+    fn gen_binding(&mut self, value: &Value<'ctx>, ident: &tokens::Ident) {
+        let alloc = self.insert_alloca(&value.ty, &ident.value);
+        self.builder.build_store(alloc, value.llvm);
+
+        self.symbol_table.insert_variable(
+            &ident.value,
+            Variable {
+                ty: value.ty.clone(),
+                alloc,
+            },
+        );
+    }
+}
+
+/// Returns true if `pattern` declares any variables
+fn has_bindings(pattern: &Pattern) -> bool {
+    match pattern {
+        Pattern::Binding(_) => true,
+        Pattern::Variant(v) => v
+            .fields
+            .iter()
+            .flat_map(|f| f.value.iter())
+            .any(has_bindings),
+        Pattern::Struct(s) => s.fields.value.iter().any(|field| match &field.pattern {
+            Some((_, pattern)) => has_bindings(pattern),
+            None => true,
+        }),
+        _ => false,
+    }
+}
+
+/// Where the arms of a match store their value
+struct MatchResult<'ctx> {
+    alloc: Option<(PointerValue<'ctx>, Type<'ctx>)>,
+    /// The type the values of the arms are generated as
+    expected: Option<Type<'ctx>>,
+    /// False if any arm has no value, in which case the match doesn't either
+    all_valued: bool,
+}
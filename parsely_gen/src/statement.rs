@@ -1,18 +1,23 @@
 use std::fmt::Write;
 
+use parsely_lexer::{tokens::LoopLabel, AsSpan, Span};
 use parsely_parser::{
-    expr::Expression,
-    statement::Statement,
-    types::{Type, TypeArray},
+    expression::{Expression, FieldAccess, Member},
+    item::{Enum, TopLevelItem},
+    pattern::{ArmBody, Match, MatchArm, Pattern, PatternInt, PatternVariant},
+    statement::{ElseBody, ForIter, ForLoop, IfStatement, Label, Statement},
+    types,
 };
 
 use crate::{
-    module::Module,
-    types::{array_type, GenType},
-    Result,
+    attempt,
+    c::{declared_type, write_int, CModule},
+    exhaustive::{lower_pattern, missing_pattern, Space},
+    expression::untyped_int,
+    raise, Diagnostic, Result,
 };
 
-impl Module {
+impl CModule<'_> {
     pub(crate) fn gen_statement(
         &mut self,
         buffer: &mut impl Write,
@@ -24,180 +29,805 @@ impl Module {
                 writeln!(buffer, ";")?;
             }
             Statement::VariableDeclaration(var) => {
-                let init = var.init.as_ref().map(|init| {
-                    let mut buffer = String::new();
-                    self.gen_expression(&mut buffer, &init.expression)
-                        .map(|i| (i, buffer))
-                });
-
-                match init {
-                    Some(Ok((ty @ GenType::Array(_, _), init))) => {
-                        assert!(var.arrays.len() > 0);
-
-                        self.gen_type(buffer, &var.ty)?;
-                        write!(buffer, " {}", var.ident.value)?;
-
-                        let dimensions = ty.dimensions();
-                        self.symbol_table.insert(&var.ident.value, ty);
-
-                        let decl_ty: Vec<_> = var
-                            .arrays
-                            .iter()
-                            .map(|dim| dim.dimension.value.map(|v| v.value as usize))
-                            .collect();
-
-                        if decl_ty.len() != dimensions.len() {
-                            for dim in decl_ty {
-                                write!(buffer, "[")?;
-                                if let Some(size) = dim {
-                                    write!(buffer, "{}", size)?;
-                                }
-                                write!(buffer, "]")?;
-                            }
-                        } else {
-                            for dim in dimensions {
-                                write!(buffer, "[")?;
-                                if let Some(size) = dim {
-                                    write!(buffer, "{}", size)?;
-                                }
-                                write!(buffer, "]")?;
-                            }
-                        }
+                let decl = self.gen_declaration(&var.ty, &var.arrays, &var.ident.value)?;
+                write!(buffer, "{}", decl)?;
+
+                if let Some(init) = &var.init {
+                    write!(buffer, " = ")?;
+                    self.gen_expression_as(buffer, &init.expression, &var.ty)?;
+                }
+
+                writeln!(buffer, ";")?;
+                let ty = declared_type(&var.ty, &var.arrays);
+                self.variables.push((var.ident.value.clone(), ty));
+            }
+            Statement::IfStatement(stmt) => {
+                self.gen_if(buffer, stmt)?;
+                writeln!(buffer)?;
+            }
+            Statement::WhileLoop(stmt) => {
+                write!(buffer, "while (")?;
+                self.gen_expression(buffer, &stmt.condition)?;
+
+                writeln!(buffer, ") {{")?;
+                self.gen_loop_body(buffer, stmt.label.as_ref(), &stmt.body.value)?;
+                self.gen_break_label(buffer, stmt.label.as_ref())?;
+            }
+            Statement::ReturnStatement(stmt) => {
+                write!(buffer, "return ")?;
+                match self.return_type.clone() {
+                    Some(ty) => self.gen_expression_as(buffer, &stmt.expr, &ty)?,
+                    None => self.gen_expression(buffer, &stmt.expr)?,
+                }
+                writeln!(buffer, ";")?;
+            }
+            Statement::ForLoop(stmt) => self.gen_for(buffer, stmt)?,
+            Statement::Break(stmt) => match &stmt.label {
+                Some(label) => writeln!(buffer, "goto __break_{};", self.find_label(label)?)?,
+                None => {
+                    self.check_in_loop(stmt.token.as_span(), "break")?;
+                    writeln!(buffer, "break;")?
+                }
+            },
+            Statement::Continue(stmt) => match &stmt.label {
+                Some(label) => writeln!(buffer, "goto __continue_{};", self.find_label(label)?)?,
+                None => {
+                    self.check_in_loop(stmt.token.as_span(), "continue")?;
+                    writeln!(buffer, "continue;")?
+                }
+            },
+            Statement::Match(m) => self.gen_match(buffer, m)?,
+            // Reported by the parser already
+            Statement::Error(_) => (),
+        }
+
+        Ok(())
+    }
+
+    /// Writes the statements of a block. Statements that fail are logged and skipped.
+    pub(crate) fn gen_block(&mut self, buffer: &mut impl Write, body: &[Statement]) -> Result<()> {
+        let scope = self.variables.len();
+        for stmt in body {
+            let _ = attempt!(self, self.gen_statement(buffer, stmt));
+        }
+        self.variables.truncate(scope);
+
+        Ok(())
+    }
+
+    /// Writes a for loop. The end of a range and the slice are only evaluated once, like in the
+    /// LLVM backend, and the counter has the type of the range.
+    fn gen_for(&mut self, buffer: &mut impl Write, stmt: &ForLoop) -> Result<()> {
+        let name = &stmt.ident.value;
+        self.variables.push((name.clone(), None));
+
+        match &stmt.iter {
+            ForIter::Range(range) => {
+                let (Some(start), Some(end)) = (&range.left, &range.right) else {
+                    unreachable!("for loop ranges always have both ends");
+                };
+
+                // An untyped literal bound takes the type of the other bound
+                let ty = if untyped_int(start).is_some() && untyped_int(end).is_none() {
+                    end
+                } else {
+                    start
+                };
+
+                write!(buffer, "for (__typeof__(")?;
+                self.gen_expression(buffer, ty)?;
+                write!(buffer, ") {} = ", name)?;
+                self.gen_expression(buffer, start)?;
+                write!(buffer, ", __end_{} = ", name)?;
+                self.gen_expression(buffer, end)?;
+                writeln!(buffer, "; {name} < __end_{name}; {name}++) {{")?;
+
+                self.gen_loop_body(buffer, stmt.label.as_ref(), &stmt.body.value)?;
+            }
+            ForIter::Slice(expr) => {
+                writeln!(buffer, "{{")?;
+                write!(buffer, "__auto_type __slice_{} = ", name)?;
+                self.gen_expression(buffer, expr)?;
+                writeln!(buffer, ";")?;
+
+                writeln!(
+                    buffer,
+                    "for (size_t __index_{name} = 0; \
+                     __index_{name} < __slice_{name}.len; __index_{name}++) {{"
+                )?;
+                writeln!(
+                    buffer,
+                    "__auto_type {name} = __slice_{name}.ptr[__index_{name}];"
+                )?;
+
+                self.gen_loop_body(buffer, stmt.label.as_ref(), &stmt.body.value)?;
+                // Close the block holding the slice
+                writeln!(buffer, "}}")?;
+            }
+        }
+        self.variables.pop();
+
+        self.gen_break_label(buffer, stmt.label.as_ref())
+    }
+
+    /// Writes the statements of a loop body and its closing `}`
+    ///
+    /// C has no labelled `break` or `continue`, so they jump to labels instead. The continue
+    /// label is at the end of the body, so a `for` loop still runs its increment.
+    fn gen_loop_body(
+        &mut self,
+        buffer: &mut impl Write,
+        label: Option<&Label>,
+        body: &[Statement],
+    ) -> Result<()> {
+        self.loops += 1;
+        let Some(label) = label else {
+            let result = self.gen_block(buffer, body);
+            self.loops -= 1;
+            result?;
+            writeln!(buffer, "}}")?;
+            return Ok(());
+        };
+
+        // Labels are per function in C, so loops with the same name need labels of their own
+        let suffix = format!("{}_{}", label.name.value, label.name.as_span().start.offset);
+        self.labels.push((label.name.value.clone(), suffix.clone()));
+        let result = self.gen_block(buffer, body);
+        self.labels.pop();
+        self.loops -= 1;
+        result?;
+
+        writeln!(buffer, "__continue_{}: ;", suffix)?;
+        writeln!(buffer, "}}")?;
+
+        Ok(())
+    }
+
+    /// Writes the label a labelled `break` jumps to, right after the loop
+    fn gen_break_label(&mut self, buffer: &mut impl Write, label: Option<&Label>) -> Result<()> {
+        if let Some(label) = label {
+            let offset = label.name.as_span().start.offset;
+            writeln!(buffer, "__break_{}_{}: ;", label.name.value, offset)?;
+        }
+
+        Ok(())
+    }
+
+    /// The suffix of the C labels of the loop `label` refers to
+    fn find_label(&mut self, label: &LoopLabel) -> Result<String> {
+        let found = self
+            .labels
+            .iter()
+            .rev()
+            .find(|(name, _)| *name == label.value);
+
+        match found {
+            Some((_, suffix)) => Ok(suffix.clone()),
+            None => {
+                let msg = format!("Use of undeclared label `{}`", label);
+                raise!(@log Error => self, msg, label.as_span());
+                Err(Diagnostic::Caught(label.as_span()))
+            }
+        }
+    }
+
+    /// Checks that an unlabelled `break` or `continue` is in a loop
+    fn check_in_loop(&mut self, span: Span, keyword: &str) -> Result<()> {
+        if self.loops == 0 {
+            let msg = format!("`{}` outside of a loop", keyword);
+            raise!(@log Error => self, msg, span);
+            return Err(Diagnostic::Caught(span));
+        }
+
+        Ok(())
+    }
+
+    /// Checks that the arms of a match cover every value, like the LLVM backend does
+    fn check_match(&mut self, m: &Match) -> Result<()> {
+        let Some(space) = self.pattern_space(m) else {
+            return Ok(());
+        };
+
+        // A guard can be false, so guarded arms don't cover anything
+        let mut covering = Vec::new();
+        for arm in m.arms.value.iter() {
+            match lower_pattern(&arm.pattern, &space) {
+                Ok(pat) if arm.guard.is_none() => covering.push(pat),
+                Ok(_) => (),
+                Err(e) => {
+                    self.push_error(e);
+                    return Err(Diagnostic::Caught(arm.pattern.as_span()));
+                }
+            }
+        }
+
+        if let Some(missing) = missing_pattern(&space, &covering) {
+            let msg = format!("Non-exhaustive match, `{}` is not covered", missing);
+            raise!(@log Error => self, msg, m.expr.as_span());
+            return Err(Diagnostic::Caught(m.as_span()));
+        }
+
+        Ok(())
+    }
 
-                        write!(buffer, " = {}", init)?;
+    /// The values a match could be on
+    ///
+    /// They come from the declared type of what is matched on, like in the LLVM backend. If
+    /// that isn't known, variant and struct patterns still name their type, but integer patterns
+    /// could be on any integer type, so there's nothing to check.
+    fn pattern_space(&self, m: &Match) -> Option<Space> {
+        let pattern = m
+            .arms
+            .value
+            .iter()
+            .map(|arm| &arm.pattern)
+            .find(|p| !matches!(p, Pattern::Wildcard(_) | Pattern::Binding(_)));
+        let declared = self
+            .type_of(&m.expr)
+            .map(|ty| self.space_of(&ty, &mut Vec::new()));
 
-                        writeln!(buffer, ";")?;
+        match (pattern, declared) {
+            (Some(Pattern::Wildcard(_) | Pattern::Binding(_)) | None, _) => Some(Space::Opaque),
+            (_, Some(space)) if !matches!(space, Space::Opaque) => Some(space),
+            (Some(Pattern::Variant(v)), _) => {
+                Some(self.space_of_named(&v.ty.value, &mut Vec::new()))
+            }
+            (Some(Pattern::Struct(s)), _) => {
+                Some(self.space_of_named(&s.ty.value, &mut Vec::new()))
+            }
+            (Some(Pattern::Bool(_)), _) => Some(Space::Bool),
+            (Some(Pattern::Int(_) | Pattern::Char(_) | Pattern::Range(_)), _) => None,
+        }
+    }
+
+    /// The enum `name` is, through any aliases of it
+    fn enum_named(&self, name: &str) -> Option<&Enum> {
+        let mut name = name;
+        // Aliases in a cycle never reach an enum, so give up once every item could be visited
+        for _ in 0..=self.items.len() {
+            match self.items.get(name)? {
+                TopLevelItem::Enum(e) => return Some(e),
+                TopLevelItem::TypeAlias(alias) => match &*alias.ty {
+                    types::Type::Named(ident) => name = &ident.value,
+                    _ => return None,
+                },
+                _ => return None,
+            }
+        }
+
+        None
+    }
+
+    /// The constant of the discriminant of a variant. A distinct alias of an enum uses the
+    /// constants of the enum.
+    fn variant_constant(&self, v: &PatternVariant) -> String {
+        let name = self
+            .enum_named(&v.ty.value)
+            .map_or(&v.ty.value, |e| &e.ident.value);
+        format!("{}_{}", name, v.variant.value)
+    }
+
+    /// The declared type of `expr`, if it's a variable, a call, or a field of one
+    fn type_of(&self, expr: &Expression) -> Option<types::Type> {
+        match expr {
+            Expression::Ident(ident) => self
+                .variables
+                .iter()
+                .rev()
+                .find(|(name, _)| *name == ident.value)
+                .and_then(|(_, ty)| ty.clone()),
+            Expression::Parens(p) => self.type_of(&p.value),
+            Expression::Call(call) => match &*call.expr {
+                Expression::Ident(ident) => {
+                    self.functions.get(&ident.value).map(|(_, ty)| ty.clone())
+                }
+                _ => None,
+            },
+            Expression::Field(FieldAccess {
+                expr,
+                member: Member::Named(member),
+                ..
+            }) => {
+                let mut ty = self.type_of(expr)?;
+                // Aliases of structs have their fields
+                while let types::Type::Named(ident) = &ty {
+                    match self.items.get(&ident.value)? {
+                        TopLevelItem::TypeAlias(alias) => ty = (*alias.ty).clone(),
+                        TopLevelItem::Struct(s) => {
+                            let field = s
+                                .body
+                                .value
+                                .iter()
+                                .find(|f| f.ident.value == member.value)?;
+                            return declared_type(&field.parameter_type, &field.arrays);
+                        }
+                        _ => return None,
                     }
-                    Some(Err(e)) => return Err(e),
-                    init => {
-                        if var.arrays.len() > 0 {
-                            write!(buffer, "struct slice")?;
-
-                            let ty = Type::Slice(TypeArray {
-                                arrays: var.arrays.clone(),
-                                element: var.ty.clone(),
-                            });
-                            let gt = GenType::from(&ty);
-
-                            if let Some(Ok((ty, _))) = &init {
-                                self.symbol_table.insert(&var.ident.value, ty.clone());
-                            } else {
-                                self.symbol_table.insert(&var.ident.value, gt);
-                            }
+                }
+                None
+            }
+            _ => None,
+        }
+    }
+
+    /// The values of `ty`. Types that are being expanded already, like a payload that holds its
+    /// own enum, are opaque.
+    fn space_of(&self, ty: &types::Type, expanding: &mut Vec<String>) -> Space {
+        match ty {
+            types::Type::Int(i) if i.size == 1 => Space::Bool,
+            types::Type::Int(i) => Space::int(i.size as u32, true),
+            types::Type::Named(ident) => self.space_of_named(&ident.value, expanding),
+            _ => Space::Opaque,
+        }
+    }
+
+    fn space_of_named(&self, name: &str, expanding: &mut Vec<String>) -> Space {
+        if expanding.iter().any(|n| n == name) {
+            return Space::Opaque;
+        }
+        expanding.push(name.to_string());
+
+        let space = match self.items.get(name) {
+            Some(TopLevelItem::Enum(e)) => Space::Enum {
+                name: name.to_string(),
+                variants: e
+                    .variants
+                    .value
+                    .iter()
+                    .map(|v| {
+                        let fields = v.fields.iter().flat_map(|f| f.value.iter());
+                        let fields = fields.map(|ty| self.space_of(ty, expanding)).collect();
+                        (v.ident.value.clone(), fields)
+                    })
+                    .collect(),
+            },
+            Some(TopLevelItem::Struct(s)) => Space::Struct {
+                name: name.to_string(),
+                fields: s
+                    .body
+                    .value
+                    .iter()
+                    .map(|f| {
+                        let space = if f.arrays.is_empty() {
+                            self.space_of(&f.parameter_type, expanding)
                         } else {
-                            self.gen_type(buffer, &var.ty)?;
-
-                            if let Some(Ok((ty, _))) = &init {
-                                self.symbol_table.insert(&var.ident.value, ty.clone());
-                            } else {
-                                self.symbol_table
-                                    .insert(&var.ident.value, var.ty.as_ref().into());
-                            }
-                        }
+                            Space::Opaque
+                        };
+                        (f.ident.value.clone(), space)
+                    })
+                    .collect(),
+            },
+            // A distinct alias has its own name, which its patterns use
+            Some(TopLevelItem::TypeAlias(alias)) => match self.space_of(&alias.ty, expanding) {
+                Space::Enum { variants, .. } if alias.distinct.is_some() => Space::Enum {
+                    name: name.to_string(),
+                    variants,
+                },
+                Space::Struct { fields, .. } if alias.distinct.is_some() => Space::Struct {
+                    name: name.to_string(),
+                    fields,
+                },
+                space => space,
+            },
+            _ => Space::Opaque,
+        };
 
-                        write!(buffer, " {}", var.ident.value)?;
+        expanding.pop();
+        space
+    }
 
-                        if let Some(Ok((ty, init))) = init {
-                            println!("ty: {ty:?}");
-                            for dim in ty.array_dimensions() {
-                                write!(buffer, "[{dim}]")?;
-                            }
+    /// Writes a match as a `switch` if every arm is a literal or `_`, or as a chain of ifs
+    ///
+    /// In the chain, each arm jumps past the rest once it has run, so an arm whose guard is
+    /// false falls through to the next one.
+    fn gen_match(&mut self, buffer: &mut impl Write, m: &Match) -> Result<()> {
+        self.check_match(m)?;
 
-                            write!(buffer, " = {}", init)?;
-                        }
+        let id = m.token.as_span().start.offset;
+        let value = format!("__match_{}", id);
+
+        writeln!(buffer, "{{")?;
+        write!(buffer, "__auto_type {} = ", value)?;
+        self.gen_expression(buffer, &m.expr)?;
+        writeln!(buffer, ";")?;
+
+        let switch = m.arms.value.iter().all(|arm| {
+            arm.guard.is_none()
+                && match &arm.pattern {
+                    Pattern::Int(_) | Pattern::Char(_) | Pattern::Wildcard(_) => true,
+                    Pattern::Variant(v) => self
+                        .enum_named(&v.ty.value)
+                        .is_some_and(|e| !e.is_tagged_union()),
+                    _ => false,
+                }
+                && !breaks_switch(&arm.body)
+        });
 
-                        writeln!(buffer, ";")?;
+        if switch {
+            writeln!(buffer, "switch ({}) {{", value)?;
+            let mut cases = Vec::new();
+            for arm in m.arms.value.iter() {
+                let case = match &arm.pattern {
+                    Pattern::Int(i) => {
+                        let mut case = String::new();
+                        write_pattern_int(&mut case, i)?;
+                        Some(case)
                     }
+                    Pattern::Char(c) => Some((c.value as u32).to_string()),
+                    Pattern::Variant(v) => Some(self.variant_constant(v)),
+                    _ => None,
+                };
+
+                match case {
+                    // An earlier arm with the same case always matches first
+                    Some(case) if cases.contains(&case) => continue,
+                    Some(case) => {
+                        writeln!(buffer, "case {}: {{", case)?;
+                        cases.push(case);
+                    }
+                    None => writeln!(buffer, "default: {{")?,
                 }
+                self.gen_arm_body(buffer, &arm.body)?;
+                writeln!(buffer, "break;")?;
+                writeln!(buffer, "}}")?;
 
-                // match (&var.ty, &var.init.as_ref().map(|vi| vi.expression.as_ref())) {
-                //     (_, Some(Expression::ArrayInit(a))) => {
-                //         assert!(var.arrays.len() > 0);
-
-                //         self.gen_type(buffer, &var.ty)?;
-                //         write!(buffer, " {}", var.ident.value)?;
-
-                //         let ty = Type::Array(TypeArray {
-                //             arrays: var.arrays.clone(),
-                //             element: var.ty.clone(),
-                //         });
-
-                //         self.symbol_table.insert(&var.ident.value, (&ty).into());
-
-                //         for (dim, size) in var.arrays.iter().zip(a.dimensions()) {
-                //             write!(buffer, "[")?;
-                //             if let Some(size) = dim.dimension.value.as_ref() {
-                //                 write!(buffer, "{}", size.value)?;
-                //             } else {
-                //                 write!(buffer, "{}", size)?;
-                //             }
-                //             write!(buffer, "]")?;
-                //         }
-
-                //         if let Some(init) = &var.init {
-                //             write!(buffer, " = ")?;
-                //             self.gen_expression(buffer, &init.expression)?;
-                //         }
-
-                //         writeln!(buffer, ";")?;
-                //     }
-                //     _ => {
-                //         if var.arrays.len() > 0 {
-                //             write!(buffer, "struct slice")?;
-
-                //             let ty = Type::Slice(TypeArray {
-                //                 arrays: var.arrays.clone(),
-                //                 element: var.ty.clone(),
-                //             });
-                //             let gt = GenType::from(&ty);
-                //             println!("{:?}", gt);
-
-                //             self.symbol_table.insert(&var.ident.value, gt);
-                //         } else {
-                //             self.gen_type(buffer, &var.ty)?;
-
-                //             self.symbol_table
-                //                 .insert(&var.ident.value, var.ty.as_ref().into());
-                //         }
-                //         write!(buffer, " {}", var.ident.value)?;
-                //         // for dim in &var.arrays {
-                //         //     write!(buffer, "[")?;
-                //         //     if let Some(size) = dim.dimension.value.as_ref() {
-                //         //         write!(buffer, "{}", size.value)?;
-                //         //     }
-                //         //     write!(buffer, "]")?;
-                //         // }
-
-                //         if let Some(init) = &var.init {
-                //             write!(buffer, " = ")?;
-                //             self.gen_expression(buffer, &init.expression)?;
-                //         }
-
-                //         writeln!(buffer, ";")?;
-                //     }
-                // }
+                // Nothing after a wildcard can match
+                if let Pattern::Wildcard(_) = arm.pattern {
+                    break;
+                }
             }
-            Statement::IfStatement(stmt) => {
+            writeln!(buffer, "}}")?;
+        } else {
+            for arm in m.arms.value.iter() {
                 write!(buffer, "if (")?;
-                self.gen_expression(buffer, &stmt.condition)?;
-
+                self.gen_pattern_test(buffer, &value, &arm.pattern)?;
                 writeln!(buffer, ") {{")?;
-                for stmt in stmt.body.value.iter() {
-                    self.gen_statement(buffer, stmt)?;
+                self.gen_pattern_bindings(buffer, &value, &arm.pattern)?;
+
+                if let Some(guard) = &arm.guard {
+                    write!(buffer, "if (")?;
+                    self.gen_expression(buffer, &guard.condition)?;
+                    writeln!(buffer, ") {{")?;
+                }
+
+                self.gen_arm_body(buffer, &arm.body)?;
+                writeln!(buffer, "goto __match_end_{};", id)?;
+
+                if arm.guard.is_some() {
+                    writeln!(buffer, "}}")?;
                 }
                 writeln!(buffer, "}}")?;
             }
-            Statement::WhileLoop(stmt) => {
-                write!(buffer, "while (")?;
-                self.gen_expression(buffer, &stmt.condition)?;
+            writeln!(buffer, "__match_end_{}: ;", id)?;
+        }
 
-                writeln!(buffer, ") {{")?;
-                for stmt in stmt.body.value.iter() {
-                    self.gen_statement(buffer, stmt)?;
+        writeln!(buffer, "}}")?;
+        Ok(())
+    }
+
+    /// Writes a match with a value as a statement expression holding a chain of conditional
+    /// expressions
+    ///
+    /// The match is exhaustive, so the last arm is only reached if it matches, and isn't tested.
+    pub(crate) fn gen_match_expression(
+        &mut self,
+        buffer: &mut impl Write,
+        m: &Match,
+    ) -> Result<()> {
+        self.check_match(m)?;
+
+        let value = format!("__match_{}", m.token.as_span().start.offset);
+
+        write!(buffer, "({{ __auto_type {} = ", value)?;
+        self.gen_expression(buffer, &m.expr)?;
+        write!(buffer, "; ")?;
+
+        let Some((last, arms)) = m.arms.value.split_last() else {
+            unreachable!("an exhaustive match has an arm");
+        };
+
+        for arm in arms {
+            write!(buffer, "(")?;
+            self.gen_pattern_test(buffer, &value, &arm.pattern)?;
+            if let Some(guard) = &arm.guard {
+                // The guard can use the bindings of the pattern
+                write!(buffer, ") && ({{ ")?;
+                self.gen_pattern_bindings(buffer, &value, &arm.pattern)?;
+                self.gen_expression(buffer, &guard.condition)?;
+                write!(buffer, "; }}")?;
+            }
+            write!(buffer, ") ? ")?;
+            self.gen_arm_value(buffer, &value, arm)?;
+            write!(buffer, " : ")?;
+        }
+
+        self.gen_arm_value(buffer, &value, last)?;
+        write!(buffer, "; }})")?;
+
+        Ok(())
+    }
+
+    /// Writes the body of an arm as a statement expression, with the bindings of its pattern
+    fn gen_arm_value(
+        &mut self,
+        buffer: &mut impl Write,
+        value: &str,
+        arm: &MatchArm,
+    ) -> Result<()> {
+        writeln!(buffer, "({{")?;
+        self.gen_pattern_bindings(buffer, value, &arm.pattern)?;
+        match &arm.body {
+            ArmBody::Expression(expr) => {
+                self.gen_expression(buffer, expr)?;
+                writeln!(buffer, ";")?;
+            }
+            ArmBody::Block(body) => self.gen_block_tail(buffer, &body.value)?,
+        }
+        write!(buffer, "}})")?;
+
+        Ok(())
+    }
+
+    fn gen_arm_body(&mut self, buffer: &mut impl Write, body: &ArmBody) -> Result<()> {
+        match body {
+            ArmBody::Expression(expr) => {
+                self.gen_expression(buffer, expr)?;
+                writeln!(buffer, ";")?;
+            }
+            ArmBody::Block(body) => self.gen_block(buffer, &body.value)?,
+        }
+
+        Ok(())
+    }
+
+    /// Writes a condition that is true if `value` matches `pattern`
+    ///
+    /// The payload of a variant of a tagged union is in `value.as.Variant`, with fields `_0`,
+    /// `_1`...
+    fn gen_pattern_test(
+        &mut self,
+        buffer: &mut impl Write,
+        value: &str,
+        pattern: &Pattern,
+    ) -> Result<()> {
+        match pattern {
+            Pattern::Wildcard(_) | Pattern::Binding(_) => write!(buffer, "1")?,
+            Pattern::Int(i) => {
+                write!(buffer, "{} == ", value)?;
+                write_pattern_int(buffer, i)?;
+            }
+            Pattern::Char(c) => write!(buffer, "{} == {}", value, c.value as u32)?,
+            Pattern::Bool(b) => write!(buffer, "{} == {}", value, b.value as u8)?,
+            Pattern::Range(r) => {
+                write!(buffer, "{} >= ", value)?;
+                write_pattern_int(buffer, &r.start)?;
+                write!(buffer, " && {} < ", value)?;
+                write_pattern_int(buffer, &r.end)?;
+            }
+            Pattern::Variant(v)
+                if self
+                    .enum_named(&v.ty.value)
+                    .is_some_and(|e| e.is_tagged_union()) =>
+            {
+                write!(buffer, "{}.tag == {}", value, self.variant_constant(v))?;
+
+                for (i, field) in v.fields.iter().flat_map(|f| f.value.iter()).enumerate() {
+                    write!(buffer, " && (")?;
+                    let value = format!("{}.as.{}._{}", value, v.variant.value, i);
+                    self.gen_pattern_test(buffer, &value, field)?;
+                    write!(buffer, ")")?;
                 }
-                writeln!(buffer, "}}")?;
             }
+            Pattern::Variant(v) => write!(buffer, "{} == {}", value, self.variant_constant(v))?,
+            Pattern::Struct(s) => {
+                write!(buffer, "1")?;
+
+                for field in s.fields.value.iter() {
+                    if let Some((_, pattern)) = &field.pattern {
+                        write!(buffer, " && (")?;
+                        let value = format!("{}.{}", value, field.ident.value);
+                        self.gen_pattern_test(buffer, &value, pattern)?;
+                        write!(buffer, ")")?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Declares the variables `pattern` binds, for a `value` that matches it
+    fn gen_pattern_bindings(
+        &mut self,
+        buffer: &mut impl Write,
+        value: &str,
+        pattern: &Pattern,
+    ) -> Result<()> {
+        match pattern {
+            Pattern::Binding(ident) => {
+                writeln!(buffer, "__auto_type {} = {};", ident.value, value)?;
+                self.variables.push((ident.value.clone(), None));
+            }
+            Pattern::Variant(v) => {
+                for (i, field) in v.fields.iter().flat_map(|f| f.value.iter()).enumerate() {
+                    let value = format!("{}.as.{}._{}", value, v.variant.value, i);
+                    self.gen_pattern_bindings(buffer, &value, field)?;
+                }
+            }
+            Pattern::Struct(s) => {
+                for field in s.fields.value.iter() {
+                    let value = format!("{}.{}", value, field.ident.value);
+
+                    match &field.pattern {
+                        Some((_, pattern)) => self.gen_pattern_bindings(buffer, &value, pattern)?,
+                        None => {
+                            writeln!(buffer, "__auto_type {} = {};", field.ident.value, value)?;
+                            self.variables.push((field.ident.value.clone(), None));
+                        }
+                    }
+                }
+            }
+            _ => (),
+        }
+
+        Ok(())
+    }
+
+    /// Writes a statement that has a value as an expression
+    pub(crate) fn gen_value(&mut self, buffer: &mut impl Write, stmt: &Statement) -> Result<()> {
+        match stmt {
+            Statement::Expression(expr) => self.gen_expression(buffer, &expr.expression),
+            Statement::IfStatement(stmt) => self.gen_if_expression(buffer, stmt),
+            Statement::Match(m) => self.gen_match_expression(buffer, m),
+            _ => unreachable!("statement without a value"),
+        }
+    }
+
+    /// Writes the statements of a block, with the one that gives it a value as the last
+    /// expression statement, so it's the value of a statement expression around them
+    fn gen_block_tail(&mut self, buffer: &mut impl Write, body: &[Statement]) -> Result<()> {
+        match body.split_last() {
+            Some((last, rest)) if has_value(last) => {
+                let scope = self.variables.len();
+                for stmt in rest {
+                    let _ = attempt!(self, self.gen_statement(buffer, stmt));
+                }
+                let result = self.gen_value(buffer, last);
+                self.variables.truncate(scope);
+
+                result?;
+                writeln!(buffer, ";")?;
+            }
+            _ => self.gen_block(buffer, body)?,
         }
+
         Ok(())
     }
+
+    /// Writes a block expression as a GNU statement expression. A block that is only an
+    /// expression is written as that expression.
+    pub(crate) fn gen_block_expression(
+        &mut self,
+        buffer: &mut impl Write,
+        body: &[Statement],
+    ) -> Result<()> {
+        if let [stmt] = body {
+            if has_value(stmt) {
+                write!(buffer, "(")?;
+                self.gen_value(buffer, stmt)?;
+                write!(buffer, ")")?;
+                return Ok(());
+            }
+        }
+
+        writeln!(buffer, "({{")?;
+        self.gen_block_tail(buffer, body)?;
+        write!(buffer, "}})")?;
+
+        Ok(())
+    }
+
+    /// Writes an `if` with a value as a conditional expression, with each branch as a block
+    /// expression. An `if` without an `else` has no value, so it's `void`.
+    pub(crate) fn gen_if_expression(
+        &mut self,
+        buffer: &mut impl Write,
+        stmt: &IfStatement,
+    ) -> Result<()> {
+        write!(buffer, "(")?;
+        self.gen_expression(buffer, &stmt.condition)?;
+        write!(buffer, " ? ")?;
+        self.gen_block_expression(buffer, &stmt.body.value)?;
+        write!(buffer, " : ")?;
+
+        match stmt.else_branch.as_ref().map(|e| &e.body) {
+            Some(ElseBody::If(stmt)) => self.gen_if_expression(buffer, stmt)?,
+            Some(ElseBody::Block(body)) => self.gen_block_expression(buffer, &body.value)?,
+            None => write!(buffer, "(void)0")?,
+        }
+        write!(buffer, ")")?;
+
+        Ok(())
+    }
+
+    /// Writes an if statement and its else chain, without a newline after the last `}`
+    fn gen_if(&mut self, buffer: &mut impl Write, stmt: &IfStatement) -> Result<()> {
+        write!(buffer, "if (")?;
+        self.gen_expression(buffer, &stmt.condition)?;
+
+        writeln!(buffer, ") {{")?;
+        self.gen_block(buffer, &stmt.body.value)?;
+        write!(buffer, "}}")?;
+
+        match stmt.else_branch.as_ref().map(|e| &e.body) {
+            Some(ElseBody::If(stmt)) => {
+                write!(buffer, " else ")?;
+                self.gen_if(buffer, stmt)?;
+            }
+            Some(ElseBody::Block(body)) => {
+                writeln!(buffer, " else {{")?;
+                self.gen_block(buffer, &body.value)?;
+                write!(buffer, "}}")?;
+            }
+            None => (),
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns true if `stmt` gives the block it ends a value
+///
+/// Unlike [`Statement::has_value`], an `if` or `match` only has one if all of its branches do,
+/// so one whose branches `return` on their own is written as a statement.
+pub(crate) fn has_value(stmt: &Statement) -> bool {
+    fn if_has_value(stmt: &IfStatement) -> bool {
+        stmt.body.value.last().is_some_and(has_value)
+            && match stmt.else_branch.as_ref().map(|e| &e.body) {
+                Some(ElseBody::If(stmt)) => if_has_value(stmt),
+                Some(ElseBody::Block(body)) => body.value.last().is_some_and(has_value),
+                None => false,
+            }
+    }
+
+    match stmt {
+        Statement::IfStatement(stmt) => if_has_value(stmt),
+        Statement::Match(m) => m.arms.value.iter().all(|arm| match &arm.body {
+            ArmBody::Expression(_) => true,
+            ArmBody::Block(body) => body.value.last().is_some_and(has_value),
+        }),
+        stmt => stmt.has_value(),
+    }
+}
+
+/// Returns true if `body` has a `break` that would leave a C `switch` instead of the loop
+/// around it
+fn breaks_switch(body: &ArmBody) -> bool {
+    fn breaks(stmt: &Statement) -> bool {
+        match stmt {
+            Statement::Break(stmt) => stmt.label.is_none(),
+            Statement::IfStatement(stmt) => if_breaks(stmt),
+            Statement::Match(m) => m.arms.value.iter().any(|arm| breaks_switch(&arm.body)),
+            _ => false,
+        }
+    }
+
+    fn if_breaks(stmt: &IfStatement) -> bool {
+        stmt.body.value.iter().any(breaks)
+            || match stmt.else_branch.as_ref().map(|e| &e.body) {
+                Some(ElseBody::If(stmt)) => if_breaks(stmt),
+                Some(ElseBody::Block(body)) => body.value.iter().any(breaks),
+                None => false,
+            }
+    }
+
+    match body {
+        ArmBody::Block(body) => body.value.iter().any(breaks),
+        ArmBody::Expression(_) => false,
+    }
+}
+
+/// Writes an integer of a pattern, with its `-` if it has one
+fn write_pattern_int(buffer: &mut impl Write, i: &PatternInt) -> std::fmt::Result {
+    write_int(buffer, &i.int.value, i.minus.is_some())
 }
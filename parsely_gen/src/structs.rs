@@ -0,0 +1,267 @@
+use inkwell::{
+    types::{AnyTypeEnum, BasicTypeEnum},
+    values::{BasicValue, BasicValueEnum},
+};
+use parsely_lexer::AsSpan;
+use parsely_parser::{
+    expression::{Call, Expression, FieldAccess, Member, StructLiteral},
+    item::Struct,
+};
+
+use crate::{
+    attempt,
+    expression::Place,
+    llvm_value::{Type, TypeBuilder, Value},
+    module::{Module, EMPTY_NAME},
+    raise, Diagnostic, ErrorHelper, Result,
+};
+
+/// Names and types of the fields of a struct, in declaration order
+#[derive(Debug, Clone)]
+pub(crate) struct StructLayout<'ctx> {
+    pub fields: Vec<(String, Type<'ctx>)>,
+}
+
+impl<'ctx> Module<'ctx> {
+    /// Declares the type of a struct, and its layout for field access
+    ///
+    /// The type is declared before its fields, so they can point to it. An opaque struct has no
+    /// body, so it can only be used behind a pointer.
+    pub(crate) fn gen_struct(&mut self, s: &Struct) -> Result<()> {
+        let name = s.ident.value.as_str();
+        let llvm = self.context.opaque_struct_type(name);
+
+        let ty = Type {
+            name: Some(name.to_string()),
+            ..llvm.to_type()
+        };
+        if !self.symbol_table.insert_type(name, ty) {
+            let msg = format!("Type `{}` is already defined", name);
+            raise!(@log Error => self, msg, s.ident.as_span());
+            return Err(Diagnostic::Caught(s.ident.as_span()));
+        }
+
+        let mut failed = false;
+        let mut fields: Vec<(String, Type<'ctx>)> = Vec::new();
+        for field in s.body.value.iter() {
+            let span = field.ident.as_span();
+            let Ok(ty) = attempt!(
+                self,
+                self.gen_value_type(&field.parameter_type, &field.arrays, span)
+            ) else {
+                failed = true;
+                continue;
+            };
+
+            if !ty.is_sized() {
+                let msg = format!("Field `{}` has a type without a size", field.ident.value);
+                raise!(@log Error => self, msg, span);
+                failed = true;
+                continue;
+            }
+
+            if fields.iter().any(|(f, _)| *f == field.ident.value) {
+                let msg = format!("Field `{}` is already defined", field.ident.value);
+                raise!(@log Error => self, msg, field.ident.as_span());
+                failed = true;
+                continue;
+            }
+
+            fields.push((field.ident.value.clone(), ty));
+        }
+
+        if failed {
+            return Err(Diagnostic::Caught(s.ident.as_span()));
+        }
+
+        if s.opaque.is_none() {
+            let llvm_fields: Vec<BasicTypeEnum> = fields
+                .iter()
+                .map(|(_, ty)| ty.llvm.try_into().expect("Unable to get basic type"))
+                .collect();
+            llvm.set_body(&llvm_fields, s.packed.is_some());
+        }
+
+        self.symbol_table
+            .insert_struct(name, StructLayout { fields });
+
+        Ok(())
+    }
+
+    /// Finds the index and type of the field `member` of a value of type `ty`
+    ///
+    /// Named fields come from the layout of a struct. Tuples have no layout, so their elements
+    /// get the LLVM type of the field.
+    fn field_of(&mut self, ty: &Type<'ctx>, f: &FieldAccess) -> Result<(u32, Type<'ctx>)> {
+        let layout = ty
+            .name
+            .as_ref()
+            .and_then(|name| self.symbol_table.find_struct(name));
+
+        let found = match (&f.member, layout, ty.llvm) {
+            (Member::Named(ident), Some(layout), _) => layout
+                .fields
+                .iter()
+                .position(|(name, _)| *name == ident.value)
+                .map(|i| (i as u32, layout.fields[i].1.clone())),
+            (Member::Index(i), None, AnyTypeEnum::StructType(st)) if ty.name.is_none() => {
+                let index = i.value.value as u32;
+                st.get_field_type_at_index(index)
+                    .map(|field| (index, field.to_type()))
+            }
+            _ => None,
+        };
+
+        match found {
+            Some(found) => Ok(found),
+            None => {
+                let msg = match &f.member {
+                    Member::Named(ident) => format!("No field named `{}`", ident.value),
+                    Member::Index(i) => format!("No element `{}`", i.value.value),
+                };
+                raise!(@log Error => self, msg, f.member.as_span());
+                Err(Diagnostic::Caught(f.member.as_span()))
+            }
+        }
+    }
+
+    /// Reads a field of a struct or tuple value
+    pub(crate) fn gen_field(&mut self, f: &FieldAccess) -> Result<Value<'ctx>> {
+        let value = self.gen_expression(&f.expr)?;
+        let (index, ty) = self.field_of(&value.ty, f)?;
+
+        let llvm = self
+            .builder
+            .build_extract_value(value.llvm.into_struct_value(), index, EMPTY_NAME)
+            .expect("Field out of bounds!");
+
+        Ok(Value { llvm, ty })
+    }
+
+    /// Gets a pointer to a field, through the place of the struct it's in
+    pub(crate) fn gen_field_place(&mut self, f: &FieldAccess) -> Result<Place<'ctx>> {
+        let place = self.gen_place(&f.expr)?;
+        let (index, ty) = self.field_of(&place.ty, f)?;
+
+        let ptr = self
+            .builder
+            .build_struct_gep(
+                place.ty.llvm.into_struct_type(),
+                place.ptr,
+                index,
+                EMPTY_NAME,
+            )
+            .expect("Field out of bounds!");
+
+        Ok(Place {
+            ptr,
+            ty,
+            mutable: place.mutable,
+        })
+    }
+
+    /// Builds a struct from a literal. The checker has made sure that it gives every field
+    /// exactly once.
+    pub(crate) fn gen_struct_literal(&mut self, lit: &StructLiteral) -> Result<Value<'ctx>> {
+        let Some(ty) = self.symbol_table.find_type(&lit.ident.value).cloned() else {
+            return Err(raise!(@not_found => self, lit.ident.clone())).caught();
+        };
+        let layout = self
+            .symbol_table
+            .find_struct(&lit.ident.value)
+            .cloned()
+            .expect("Struct literal of a type that isn't a struct!");
+
+        let struct_ty = ty.llvm.into_struct_type();
+        let mut llvm: BasicValueEnum = struct_ty.get_undef().into();
+        let mut failed = false;
+
+        for init in lit.fields.value.iter() {
+            let i = layout
+                .fields
+                .iter()
+                .position(|(f, _)| *f == init.ident.value)
+                .expect("Struct literal with an unknown field!");
+
+            let field_ty = &layout.fields[i].1;
+            let Ok(value) = attempt!(self, self.gen_expression_as(&init.value, field_ty)) else {
+                failed = true;
+                continue;
+            };
+
+            if !value.ty.assignable_to(field_ty) {
+                raise!(@mismatch => self, init.value.as_span());
+                failed = true;
+                continue;
+            }
+
+            llvm = self
+                .builder
+                .build_insert_value(llvm.into_struct_value(), value.llvm, i as u32, EMPTY_NAME)
+                .expect("Field out of bounds!")
+                .as_basic_value_enum();
+        }
+
+        if failed {
+            return Err(Diagnostic::Caught(lit.ident.as_span()));
+        }
+
+        Ok(Value { llvm, ty })
+    }
+
+    /// Generates a call, and returns its value unless the function returns `void`. `a.f(x)` is
+    /// a method call, which passes `a` as the first argument of `f`.
+    pub(crate) fn gen_call(&mut self, call: &Call) -> Result<Option<Value<'ctx>>> {
+        let (ident, receiver) = match &*call.expr {
+            Expression::Ident(ident) => (ident, None),
+            Expression::Field(FieldAccess {
+                expr,
+                member: Member::Named(ident),
+                ..
+            }) => (ident, Some(&**expr)),
+            expr => {
+                let msg = "Only functions can be called";
+                raise!(@log Error => self, msg, expr.as_span());
+                return Err(Diagnostic::Caught(expr.as_span()));
+            }
+        };
+
+        let Some(func) = self.symbol_table.find_function(&ident.value) else {
+            return Err(raise!(@not_found => self, ident.clone())).caught();
+        };
+        let (fn_val, param_types, return_type) = (
+            func.fn_val,
+            func.param_types.clone(),
+            func.return_type.clone(),
+        );
+
+        let args: Vec<&Expression> = receiver.into_iter().chain(call.args.value.iter()).collect();
+        if args.len() != param_types.len() {
+            let msg = format!(
+                "`{}` takes {} arguments but {} were given",
+                ident.value,
+                param_types.len(),
+                args.len()
+            );
+            raise!(@log Error => self, msg, call.as_span());
+            return Err(Diagnostic::Caught(call.as_span()));
+        }
+
+        let mut llvm_args = Vec::new();
+        for (arg, param) in args.into_iter().zip(&param_types) {
+            let value = self.gen_expression_as(arg, param)?;
+            if !value.ty.assignable_to(param) {
+                return Err(raise!(@mismatch => self, arg.as_span())).caught();
+            }
+            llvm_args.push(value.llvm.into());
+        }
+
+        let llvm = self.builder.build_call(fn_val, &llvm_args, EMPTY_NAME);
+
+        Ok(llvm
+            .try_as_basic_value()
+            .left()
+            .zip(return_type)
+            .map(|(llvm, ty)| Value { llvm, ty }))
+    }
+}
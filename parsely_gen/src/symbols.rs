@@ -1,6 +1,10 @@
 use std::collections::HashMap;
 
-use crate::llvm_value::{Function, Type, Variable};
+use crate::{
+    enums::EnumLayout,
+    llvm_value::{Function, Type, Variable},
+    structs::StructLayout,
+};
 
 #[derive(Debug)]
 pub struct SymbolTable<'ctx> {
@@ -17,10 +21,12 @@ impl<'ctx> SymbolTable<'ctx> {
             variables: HashMap::new(),
             functions: HashMap::new(),
             types: HashMap::new(),
+            enums: HashMap::new(),
+            structs: HashMap::new(),
         })
     }
 
-    pub fn pop_scope(&mut self) -> Option<Scope> {
+    pub fn pop_scope(&mut self) -> Option<Scope<'ctx>> {
         self.scopes.pop()
     }
 
@@ -43,20 +49,12 @@ impl<'ctx> SymbolTable<'ctx> {
         self.iter_variable().rev().find_map(|map| map.get(name))
     }
 
-    pub fn find_variable_in_current(&self, name: &str) -> Option<&Variable<'ctx>> {
-        self.scopes.last().and_then(|scp| scp.variables.get(name))
-    }
-
     pub fn iter_variable(
         &self,
     ) -> impl DoubleEndedIterator<Item = &HashMap<String, Variable<'ctx>>> {
         self.scopes.iter().map(|s| &s.variables)
     }
 
-    pub fn iter_all_variables(&self) -> impl Iterator<Item = (&String, &Variable<'ctx>)> {
-        self.iter_variable().rev().flat_map(|map| map.iter())
-    }
-
     /* Functions */
 
     pub fn insert_function(&mut self, name: &str, ty: Function<'ctx>) -> bool {
@@ -76,20 +74,12 @@ impl<'ctx> SymbolTable<'ctx> {
         self.iter_functions().rev().find_map(|map| map.get(name))
     }
 
-    pub fn find_function_in_current(&self, name: &str) -> Option<&Function<'ctx>> {
-        self.scopes.last().and_then(|scp| scp.functions.get(name))
-    }
-
     pub fn iter_functions(
         &self,
     ) -> impl DoubleEndedIterator<Item = &HashMap<String, Function<'ctx>>> {
         self.scopes.iter().map(|s| &s.functions)
     }
 
-    pub fn iter_all_functions(&self) -> impl Iterator<Item = (&String, &Function<'ctx>)> {
-        self.iter_functions().rev().flat_map(|map| map.iter())
-    }
-
     /* Types */
 
     pub fn insert_type(&mut self, name: &str, ty: Type<'ctx>) -> bool {
@@ -109,16 +99,50 @@ impl<'ctx> SymbolTable<'ctx> {
         self.iter_types().rev().find_map(|map| map.get(name))
     }
 
-    pub fn find_type_in_current(&self, name: &str) -> Option<&Type<'ctx>> {
-        self.scopes.last().and_then(|scp| scp.types.get(name))
-    }
-
     pub fn iter_types(&self) -> impl DoubleEndedIterator<Item = &HashMap<String, Type<'ctx>>> {
         self.scopes.iter().map(|s| &s.types)
     }
 
-    pub fn iter_all_types(&self) -> impl Iterator<Item = (&String, &Type<'ctx>)> {
-        self.iter_types().rev().flat_map(|map| map.iter())
+    /* Enums */
+
+    /// Inserts the layout of an enum. The enum's type should be inserted with
+    /// [`SymbolTable::insert_type`] as well.
+    pub fn insert_enum(&mut self, name: &str, layout: EnumLayout<'ctx>) -> bool {
+        if let Some(scope) = self.scopes.last_mut() {
+            if scope.enums.contains_key(name) {
+                return false;
+            }
+
+            scope.enums.insert(name.to_string(), layout);
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn find_enum(&self, name: &str) -> Option<&EnumLayout<'ctx>> {
+        self.scopes.iter().rev().find_map(|s| s.enums.get(name))
+    }
+
+    /* Structs */
+
+    /// Inserts the fields of a struct. The struct's type should be inserted with
+    /// [`SymbolTable::insert_type`] as well.
+    pub fn insert_struct(&mut self, name: &str, layout: StructLayout<'ctx>) -> bool {
+        if let Some(scope) = self.scopes.last_mut() {
+            if scope.structs.contains_key(name) {
+                return false;
+            }
+
+            scope.structs.insert(name.to_string(), layout);
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn find_struct(&self, name: &str) -> Option<&StructLayout<'ctx>> {
+        self.scopes.iter().rev().find_map(|s| s.structs.get(name))
     }
 }
 
@@ -127,4 +151,6 @@ pub struct Scope<'ctx> {
     variables: HashMap<String, Variable<'ctx>>,
     functions: HashMap<String, Function<'ctx>>,
     types: HashMap<String, Type<'ctx>>,
+    enums: HashMap<String, EnumLayout<'ctx>>,
+    structs: HashMap<String, StructLayout<'ctx>>,
 }
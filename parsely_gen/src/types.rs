@@ -1,19 +1,28 @@
-use parsely_parser::types;
+use inkwell::{
+    types::{BasicType, BasicTypeEnum},
+    AddressSpace,
+};
+use parsely_lexer::{AsSpan, Span};
+use parsely_parser::{item::TypeAlias, statement::ArrayDimension, types};
 
 use crate::{
     llvm_value::{Type, TypeBuilder, TypeFlags},
     module::Module,
-    raise, ErrorHelper, Result,
+    raise, Diagnostic, ErrorHelper, Result,
 };
 
 impl<'ctx> Module<'ctx> {
+    /// A slice is a length followed by a pointer to the first element
     pub fn slice_type(&self, base: Type<'ctx>) -> Type<'ctx> {
         let struct_ty = self.context.struct_type(
             &[
                 self.context
                     .ptr_sized_int_type(&self.target_data, None)
                     .into(),
-                base.llvm.try_into().expect("Unable to get basic type"),
+                BasicTypeEnum::try_from(base.llvm)
+                    .expect("Unable to get basic type")
+                    .ptr_type(AddressSpace::default())
+                    .into(),
             ],
             false,
         );
@@ -22,14 +31,18 @@ impl<'ctx> Module<'ctx> {
             llvm: struct_ty.into(),
             flags: TypeFlags::SLICE,
             base_type: Some(Box::new(base)),
+            name: None,
         }
     }
 
     pub fn gen_type(&mut self, ty: &types::Type) -> Result<Type<'ctx>> {
         match ty {
-            types::Type::Int(_) => Ok(self.context.i64_type().to_type()),
-            types::Type::Float(_) => Ok(self.context.f64_type().to_type()),
-            types::Type::Bool(_) => Ok(self.context.bool_type().to_type()),
+            types::Type::Empty | types::Type::Void(_) => Ok(self.context.void_type().to_type()),
+            types::Type::Int(i) => Ok(self
+                .context
+                .custom_width_int_type(i.size as u32)
+                .signed(true)),
+            types::Type::Str(_) => Ok(self.slice_type(self.context.i8_type().to_type())),
             types::Type::Named(i) => {
                 let Some(ty) = self.symbol_table.find_type(&i.value) else {
                     return Err(raise!(@not_found => self, i.clone()).caught());
@@ -37,10 +50,135 @@ impl<'ctx> Module<'ctx> {
 
                 Ok(ty.clone())
             }
-            types::Type::Array(a) => {
-                let ty = self.gen_type(&a.base_type)?;
-                Ok(self.slice_type(ty))
+            types::Type::Array(a) | types::Type::Slice(a) => {
+                let ty = self.gen_type(&a.element)?;
+                Ok(self.array_dims(ty, &a.arrays))
+            }
+            types::Type::Pointer(p) => {
+                let pointee = self.gen_type(&p.pointee)?;
+                Ok(self.pointer_type(pointee).mutable(p.is_mutable()))
+            }
+            types::Type::Optional(o) => {
+                let inner = self.gen_type(&o.inner)?;
+                Ok(self.optional_type(inner))
+            }
+            types::Type::Tuple(t) => {
+                let fields = t
+                    .value
+                    .iter()
+                    .map(|field| self.gen_type(field))
+                    .collect::<Result<Vec<_>>>()?;
+                Ok(self.tuple_type(fields))
+            }
+        }
+    }
+
+    /// Pointers keep the type they point to, so they can be dereferenced
+    pub fn pointer_type(&self, pointee: Type<'ctx>) -> Type<'ctx> {
+        let llvm = BasicTypeEnum::try_from(pointee.llvm)
+            .expect("Unable to get basic type")
+            .ptr_type(AddressSpace::default());
+
+        Type {
+            flags: TypeFlags::POINTER,
+            ..llvm.base(pointee)
+        }
+    }
+
+    /// An optional is a flag for whether there is a value, followed by the value
+    pub fn optional_type(&self, inner: Type<'ctx>) -> Type<'ctx> {
+        let struct_ty = self.context.struct_type(
+            &[
+                self.context.bool_type().into(),
+                BasicTypeEnum::try_from(inner.llvm).expect("Unable to get basic type"),
+            ],
+            false,
+        );
+
+        Type {
+            flags: TypeFlags::OPTIONAL,
+            ..struct_ty.base(inner)
+        }
+    }
+
+    /// A tuple is an anonymous struct with a field for each element
+    pub fn tuple_type(&self, fields: Vec<Type<'ctx>>) -> Type<'ctx> {
+        let llvm_fields: Vec<BasicTypeEnum> = fields
+            .iter()
+            .map(|f| f.llvm.try_into().expect("Unable to get basic type"))
+            .collect();
+
+        self.context.struct_type(&llvm_fields, false).to_type()
+    }
+
+    /// Generates the type of something that holds a value, like a variable or a parameter,
+    /// with the dimensions written after its name. `span` is where `void` is reported.
+    pub(crate) fn gen_value_type(
+        &mut self,
+        ty: &types::Type,
+        arrays: &[ArrayDimension],
+        span: Span,
+    ) -> Result<Type<'ctx>> {
+        let ty = self.gen_type(ty)?;
+        if ty.is_void_type() {
+            raise!(@log Error => self, "Values can't have the type `void`", span);
+            return Err(Diagnostic::Caught(span));
+        }
+
+        Ok(self.array_dims(ty, arrays))
+    }
+
+    /// Wraps `ty` in array dimensions, like the `[4][]` of `int32 x[4][]`
+    ///
+    /// The last dimension is the innermost one, like in C. Dimensions without a size are slices.
+    pub(crate) fn array_dims(&self, mut ty: Type<'ctx>, arrays: &[ArrayDimension]) -> Type<'ctx> {
+        for dim in arrays.iter().rev() {
+            ty = match &*dim.dimension.value {
+                Some(size) => ty.array_type(size.value as u32),
+                None => self.slice_type(ty),
+            };
+        }
+
+        ty
+    }
+
+    /// Declares a type alias
+    ///
+    /// A plain alias shares the type, name included. A distinct alias is a copy with its own
+    /// name, so it doesn't mix with other named types. It keeps the layout of an enum, so it can
+    /// still be matched. Either one keeps the fields of a struct, so struct literals can name it.
+    pub(crate) fn gen_type_alias(&mut self, alias: &TypeAlias) -> Result<()> {
+        let name = alias.ident.value.as_str();
+        let mut ty = self.gen_type(&alias.ty)?;
+
+        let layout = ty
+            .name
+            .as_ref()
+            .and_then(|base| self.symbol_table.find_struct(base))
+            .cloned();
+        if let Some(layout) = layout {
+            self.symbol_table.insert_struct(name, layout);
+        }
+
+        if alias.distinct.is_some() {
+            let layout = ty
+                .name
+                .as_ref()
+                .and_then(|base| self.symbol_table.find_enum(base))
+                .cloned();
+            if let Some(layout) = layout {
+                self.symbol_table.insert_enum(name, layout);
             }
+
+            ty.name = Some(name.to_string());
         }
+
+        if !self.symbol_table.insert_type(name, ty) {
+            let msg = format!("Type `{}` is already defined", name);
+            raise!(@log Error => self, msg, alias.ident.as_span());
+            return Err(Diagnostic::Caught(alias.ident.as_span()));
+        }
+
+        Ok(())
     }
 }
@@ -0,0 +1,196 @@
+use std::ops::Range;
+
+use crate::{
+    build_tree, tokens::Token, AsSpan, FileId, FlatToken, LexError, Lexer, Position, Span,
+};
+
+/// How far past the end of a token the lexer can look. Tokens that end closer than this to an
+/// edit are lexed again, since the edit could change them.
+const LOOKAHEAD: usize = 4;
+
+/// The flat tokens of a buffer that is being edited
+///
+/// After an edit only the changed part of the buffer is lexed again. Tokens before it are kept
+/// as they are, and tokens after it are moved to their new position.
+#[derive(Debug, Clone)]
+pub struct TokenBuffer {
+    tokens: Vec<FlatToken>,
+    errors: Vec<LexError>,
+    file: FileId,
+}
+
+impl TokenBuffer {
+    /// Lex all of `source`
+    pub fn new(file: FileId, source: &str) -> TokenBuffer {
+        let mut lexer = Lexer::new(file, source);
+        let tokens = lexer.by_ref().collect();
+
+        TokenBuffer {
+            tokens,
+            errors: lexer.take_errors(),
+            file,
+        }
+    }
+
+    pub fn tokens(&self) -> &[FlatToken] {
+        &self.tokens
+    }
+
+    pub fn errors(&self) -> &[LexError] {
+        &self.errors
+    }
+
+    /// Nested token tree of the buffer, along with every error. See [`build_tree`].
+    pub fn tree(&self) -> (Vec<Token>, Vec<LexError>) {
+        let mut errors = self.errors.clone();
+        let tree = build_tree(self.tokens.iter().cloned(), &mut errors);
+        (tree, errors)
+    }
+
+    /// Update the tokens after the bytes in `edit` were replaced with `inserted` bytes. `source`
+    /// is the whole buffer after the edit.
+    ///
+    /// Lexing restarts a little before the edit, and stops as soon as it produces a token that
+    /// was already there before the edit. Returns the range of tokens that were lexed again.
+    pub fn relex(&mut self, source: &str, edit: Range<usize>, inserted: usize) -> Range<usize> {
+        // Unterminated strings and comments depend on everything after them
+        let unterminated = self
+            .errors
+            .iter()
+            .filter(|e| {
+                matches!(
+                    e,
+                    LexError::UnterminatedString { .. } | LexError::UnterminatedComment { .. }
+                )
+            })
+            .map(|e| e.as_span().start.offset)
+            .min()
+            .unwrap_or(usize::MAX);
+
+        let kept = self.tokens.partition_point(|token| {
+            let end = token.as_span().end.offset;
+            end + LOOKAHEAD <= edit.start && end <= unterminated
+        });
+
+        let restart = match kept {
+            0 => Position {
+                file: self.file,
+                line: 0,
+                column: 0,
+                offset: 0,
+            },
+            n => self.tokens[n - 1].as_span().end,
+        };
+
+        let new_edit_end = edit.start + inserted;
+
+        // Old tokens from here on are after the edit, and can be reused
+        let mut old = self
+            .tokens
+            .partition_point(|token| token.as_span().start.offset < edit.end);
+
+        let mut lexer = Lexer::resume(source, restart);
+        let mut relexed = Vec::new();
+        // Same place in the old and new text, once the lexer has caught up with the old tokens
+        let mut synced = None;
+
+        for token in lexer.by_ref() {
+            let start = token.as_span().start;
+
+            if start.offset >= new_edit_end {
+                let old_offset = start.offset - new_edit_end + edit.end;
+
+                while self
+                    .tokens
+                    .get(old)
+                    .is_some_and(|old_token| old_token.as_span().start.offset < old_offset)
+                {
+                    old += 1;
+                }
+
+                if let Some(old_token) = self.tokens.get(old) {
+                    let from = old_token.as_span().start;
+
+                    let mut moved = old_token.clone();
+                    shift_span(moved.span_mut(), from, start);
+
+                    if from.offset == old_offset && moved == token {
+                        synced = Some((from, start));
+                        break;
+                    }
+                }
+            }
+
+            relexed.push(token);
+        }
+
+        let mut errors: Vec<_> = self
+            .errors
+            .iter()
+            .filter(|e| e.as_span().start.offset < restart.offset)
+            .cloned()
+            .collect();
+
+        let range = kept..kept + relexed.len();
+
+        match synced {
+            Some((from, to)) => {
+                errors.extend(
+                    lexer
+                        .take_errors()
+                        .into_iter()
+                        .filter(|e| e.as_span().start.offset < to.offset),
+                );
+                errors.extend(
+                    self.errors
+                        .iter()
+                        .filter(|e| e.as_span().start.offset >= from.offset)
+                        .cloned()
+                        .map(|mut e| {
+                            shift_span(e.span_mut(), from, to);
+                            e
+                        }),
+                );
+
+                let rest: Vec<_> = self
+                    .tokens
+                    .drain(old..)
+                    .map(|mut token| {
+                        shift_span(token.span_mut(), from, to);
+                        token
+                    })
+                    .collect();
+
+                self.tokens.truncate(kept);
+                self.tokens.extend(relexed);
+                self.tokens.extend(rest);
+            }
+            None => {
+                errors.extend(lexer.take_errors());
+
+                self.tokens.truncate(kept);
+                self.tokens.extend(relexed);
+            }
+        }
+
+        self.errors = errors;
+
+        range
+    }
+}
+
+/// Moves `position` from the old text to the new text. `from` and `to` are the same place in the
+/// old and new text, and `position` has to be after it.
+fn shift_position(position: &mut Position, from: Position, to: Position) {
+    if position.line == from.line {
+        position.column = position.column - from.column + to.column;
+    }
+
+    position.line = position.line - from.line + to.line;
+    position.offset = position.offset - from.offset + to.offset;
+}
+
+fn shift_span(span: &mut Span, from: Position, to: Position) {
+    shift_position(&mut span.start, from, to);
+    shift_position(&mut span.end, from, to);
+}
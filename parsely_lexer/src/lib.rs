@@ -1,29 +1,72 @@
+use std::{borrow::Cow, fmt::Display};
+
 use tokens::*;
+use unicode_xid::UnicodeXID;
+
+pub use incremental::TokenBuffer;
+pub use report::{Label, Level, Report, ReportFmt};
+pub use source::{SourceDatabase, SourceFile};
+pub use tree::{build_tree, FlatToken};
 
+pub mod incremental;
+pub mod report;
+pub mod source;
 #[macro_use]
 pub mod tokens;
+mod tree;
 
 pub trait AsSpan {
     fn as_span(&self) -> Span;
 }
 
+/// Like [`AsSpan`], but for things that might not cover any source (e.g. an empty list)
+pub trait TryAsSpan {
+    fn try_as_span(&self) -> Option<Span>;
+}
+
+/// Identifies a source file. Every span records the file it points into.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FileId(pub(crate) u32);
+
 /// Represents a single position in a source file
 ///
+/// `file` the position is in
 /// `line` line in the file (starting at 0)
-/// `column` in the line (starting at 0)
+/// `column` in the line, in characters (starting at 0)
+/// `offset` from the start of the file, in bytes
 ///
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Position {
+    pub file: FileId,
     pub line: usize,
     pub column: usize,
+    pub offset: usize,
 }
 
 impl Position {
+    /// Span of `len` single byte characters starting at this position
     pub fn to_span(self, len: usize) -> Span {
         Span {
             end: Position {
-                line: self.line,
                 column: self.column + len,
+                offset: self.offset + len,
+                ..self
+            },
+            start: self,
+        }
+    }
+
+    /// Span of `raw` starting at this position. `raw` must not contain newlines.
+    pub fn span_over(self, raw: impl IntoIterator<Item = char>) -> Span {
+        let (columns, bytes) = raw.into_iter().fold((0, 0), |(columns, bytes), c| {
+            (columns + 1, bytes + c.len_utf8())
+        });
+
+        Span {
+            end: Position {
+                column: self.column + columns,
+                offset: self.offset + bytes,
+                ..self
             },
             start: self,
         }
@@ -38,6 +81,9 @@ impl Position {
 }
 
 /// Represents a range in a file
+///
+/// The range can cover several lines. `start.offset..end.offset` is the range of bytes in the
+/// source.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Span {
     pub start: Position,
@@ -93,11 +139,31 @@ impl AsSpan for (Position, Position) {
     }
 }
 
+/// Span from the first to the last element. Empty vectors give [`Span::EMPTY`], use
+/// [`TryAsSpan`] to tell them apart.
 impl<T: AsSpan> AsSpan for Vec<T> {
     fn as_span(&self) -> Span {
-        let first = self.first().unwrap();
-        let last = self.last().unwrap();
-        first.as_span().join(last.as_span())
+        self.try_as_span().unwrap_or_default()
+    }
+}
+
+impl<T: AsSpan> TryAsSpan for [T] {
+    fn try_as_span(&self) -> Option<Span> {
+        let first = self.first()?;
+        let last = self.last()?;
+        Some(first.as_span().join(last.as_span()))
+    }
+}
+
+impl<T: AsSpan> TryAsSpan for Vec<T> {
+    fn try_as_span(&self) -> Option<Span> {
+        self.as_slice().try_as_span()
+    }
+}
+
+impl<T: AsSpan> TryAsSpan for Option<T> {
+    fn try_as_span(&self) -> Option<Span> {
+        self.as_ref().map(AsSpan::as_span)
     }
 }
 
@@ -116,17 +182,32 @@ impl<L: AsSpan, R: AsSpan> AsSpan for (L, R) {
     }
 }
 
+/// Builds a span in the default file
+///
+/// `span!(line:start-end)` is a span on the first line (where offsets and columns are the same).
+/// `span!(line:start-end @ offset)` gives the byte offset of `start` for spans on later lines, and
+/// `span!(line:start-end @ start_offset-end_offset)` gives both offsets for non ascii text.
 #[macro_export]
 macro_rules! span {
     ($line:literal:$s:literal-$e:literal) => {
+        $crate::span!($line:$s-$e @ $s)
+    };
+    ($line:literal:$s:literal-$e:literal @ $o:literal) => {
+        $crate::span!($line:$s-$e @ $o-($o + $e - $s))
+    };
+    ($line:literal:$s:literal-$e:literal @ $so:literal-$eo:expr) => {
         $crate::Span {
             start: $crate::Position {
+                file: $crate::FileId::default(),
                 line: $line,
                 column: $s,
+                offset: $so,
             },
             end: $crate::Position {
+                file: $crate::FileId::default(),
                 line: $line,
                 column: $e,
+                offset: $eo,
             },
         }
     };
@@ -134,19 +215,43 @@ macro_rules! span {
 
 impl Span {
     pub const EMPTY: Span = Span {
-        start: Position { line: 0, column: 0 },
-        end: Position { line: 0, column: 0 },
+        start: Position {
+            file: FileId(0),
+            line: 0,
+            column: 0,
+            offset: 0,
+        },
+        end: Position {
+            file: FileId(0),
+            line: 0,
+            column: 0,
+            offset: 0,
+        },
     };
 
-    /// Length of span on a single line
+    /// File the span points into
+    pub fn file(&self) -> FileId {
+        self.start.file
+    }
+
+    /// Length of the span in bytes
     pub fn len(&self) -> usize {
-        self.end.column - self.start.column
+        self.end.offset.saturating_sub(self.start.offset)
     }
 
     pub fn is_empty(&self) -> bool {
         self.len() == 0
     }
 
+    /// Range of bytes covered by the span, for slicing the source
+    pub fn range(&self) -> std::ops::Range<usize> {
+        self.start.offset..self.end.offset.max(self.start.offset)
+    }
+
+    pub fn is_multiline(&self) -> bool {
+        self.start.line != self.end.line
+    }
+
     pub fn join(self, other: Span) -> Span {
         Span {
             start: self.start,
@@ -155,8 +260,221 @@ impl Span {
     }
 }
 
+/// Returns true if `c` can start an identifier
+fn is_ident_start(c: char) -> bool {
+    c == '_' || UnicodeXID::is_xid_start(c)
+}
+
+/// Returns the keyword token for `ident`, or `None` if `ident` isn't a keyword
+fn keyword(ident: &str, start: Position) -> Option<Token> {
+    let token = match ident {
+        "true" => Bool::from_value(true, ident, start),
+        "false" => Bool::from_value(false, ident, start),
+
+        "const" => Const::from_span_start(start),
+        "continue" => Continue::from_span_start(start),
+        "break" => Break::from_span_start(start),
+        "else" => Else::from_span_start(start),
+        "enum" => Enum::from_span_start(start),
+        "export" => Export::from_span_start(start),
+        "external" => External::from_span_start(start),
+        "for" => For::from_span_start(start),
+        "if" => If::from_span_start(start),
+        "in" => In::from_span_start(start),
+        "match" => Match::from_span_start(start),
+        "none" => Nones::from_span_start(start),
+        "opaque" => Opaque::from_span_start(start),
+        "packed" => Packed::from_span_start(start),
+        "persist" => Persist::from_span_start(start),
+        "return" => Return::from_span_start(start),
+        "struct" => Struct::from_span_start(start),
+        "typedef" => Typedef::from_span_start(start),
+        "typeof" => Typeof::from_span_start(start),
+        "void" => Void::from_span_start(start),
+        "while" => While::from_span_start(start),
+        _ => return None,
+    };
+
+    Some(token)
+}
+
+/// Represents an error while lexing
+///
+/// The lexer keeps going after an error so that every error in the input is reported.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LexError {
+    /// Encountered a character that doesn't start any token
+    UnknownCharacter { found: char, span: Span },
+    /// A string literal wasn't closed before the end of the line
+    UnterminatedString { span: Span },
+    /// A block comment wasn't closed before the end of input
+    UnterminatedComment { span: Span },
+    /// The input contained a byte sequence that isn't valid utf8
+    InvalidUtf8 { span: Span },
+    /// A number literal had a suffix that isn't a type
+    InvalidSuffix {
+        suffix: std::string::String,
+        span: Span,
+    },
+    /// A base prefix (`0x`) wasn't followed by any digits
+    MissingDigits { span: Span },
+    /// An integer literal doesn't fit in its type
+    IntegerOutOfRange { ty: &'static str, span: Span },
+    /// A float literal doesn't fit in its type
+    FloatOutOfRange { ty: &'static str, span: Span },
+    /// Unknown or malformed escape sequence in a string or char literal
+    InvalidEscape { span: Span },
+    /// A char literal wasn't closed
+    UnterminatedChar { span: Span },
+    /// A char literal without a character (`''`)
+    EmptyChar { span: Span },
+    /// A non ascii character in a byte string or byte literal
+    NonAsciiByte { found: char, span: Span },
+    /// A bracket that was never closed. `span` is the opening bracket, `expected` is where the
+    /// closing bracket was probably meant to go.
+    UnclosedDelimiter {
+        bracket: GroupBracket,
+        span: Span,
+        expected: Span,
+    },
+    /// A closing bracket that doesn't match the innermost open bracket (at `open_span`), or any
+    /// other open bracket
+    MismatchedDelimiter {
+        found: GroupBracket,
+        span: Span,
+        open: GroupBracket,
+        open_span: Span,
+    },
+    /// A closing bracket outside of any brackets
+    UnexpectedDelimiter { found: GroupBracket, span: Span },
+}
+
+impl Display for LexError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LexError::UnknownCharacter { found, .. } => {
+                write!(f, "Unknown character `{}`", found.escape_debug())
+            }
+            LexError::UnterminatedString { .. } => write!(f, "Unterminated string literal"),
+            LexError::UnterminatedComment { .. } => write!(f, "Unterminated block comment"),
+            LexError::InvalidUtf8 { .. } => write!(f, "Invalid utf8 in source"),
+            LexError::InvalidSuffix { suffix, .. } => {
+                write!(f, "Invalid suffix `{}` on number literal", suffix)
+            }
+            LexError::MissingDigits { .. } => write!(f, "Missing digits after base prefix"),
+            LexError::IntegerOutOfRange { ty, .. } => {
+                write!(f, "Integer literal is out of range for `{}`", ty)
+            }
+            LexError::FloatOutOfRange { ty, .. } => {
+                write!(f, "Float literal is out of range for `{}`", ty)
+            }
+            LexError::InvalidEscape { .. } => write!(f, "Invalid escape sequence"),
+            LexError::UnterminatedChar { .. } => write!(f, "Unterminated char literal"),
+            LexError::EmptyChar { .. } => write!(f, "Empty char literal"),
+            LexError::NonAsciiByte { found, .. } => {
+                write!(f, "Non ascii character `{}` in byte literal", found)
+            }
+            LexError::UnclosedDelimiter {
+                bracket, expected, ..
+            } => write!(
+                f,
+                "Unclosed `{}`, expected `{}` at {}:{}",
+                bracket.open_char(),
+                bracket.close_char(),
+                expected.start.line + 1,
+                expected.start.column + 1
+            ),
+            LexError::MismatchedDelimiter {
+                found,
+                open,
+                open_span,
+                ..
+            } => write!(
+                f,
+                "Mismatched closing `{}`, expected `{}` to close `{}` at {}:{}",
+                found.close_char(),
+                open.close_char(),
+                open.open_char(),
+                open_span.start.line + 1,
+                open_span.start.column + 1
+            ),
+            LexError::UnexpectedDelimiter { found, .. } => {
+                write!(f, "Unexpected closing `{}`", found.close_char())
+            }
+        }
+    }
+}
+
+impl std::error::Error for LexError {}
+
+impl LexError {
+    /// Report for rendering the error with the source it points to
+    pub fn to_report(&self) -> Report {
+        let report = Report::error(self.to_string(), self.as_span());
+
+        match self {
+            LexError::UnclosedDelimiter {
+                bracket, expected, ..
+            } => report.with_message("unclosed").with_label(
+                *expected,
+                format!("`{}` expected here", bracket.close_char()),
+            ),
+            LexError::MismatchedDelimiter {
+                open, open_span, ..
+            } => report.with_label(*open_span, format!("`{}` opened here", open.open_char())),
+            _ => report,
+        }
+    }
+
+    pub(crate) fn span_mut(&mut self) -> &mut Span {
+        match self {
+            LexError::UnknownCharacter { span, .. } => span,
+            LexError::UnterminatedString { span } => span,
+            LexError::UnterminatedComment { span } => span,
+            LexError::InvalidUtf8 { span } => span,
+            LexError::InvalidSuffix { span, .. } => span,
+            LexError::MissingDigits { span } => span,
+            LexError::IntegerOutOfRange { span, .. } => span,
+            LexError::FloatOutOfRange { span, .. } => span,
+            LexError::InvalidEscape { span } => span,
+            LexError::UnterminatedChar { span } => span,
+            LexError::EmptyChar { span } => span,
+            LexError::NonAsciiByte { span, .. } => span,
+            LexError::UnclosedDelimiter { span, .. } => span,
+            LexError::MismatchedDelimiter { span, .. } => span,
+            LexError::UnexpectedDelimiter { span, .. } => span,
+        }
+    }
+}
+
+impl AsSpan for LexError {
+    fn as_span(&self) -> Span {
+        match self {
+            LexError::UnknownCharacter { span, .. } => *span,
+            LexError::UnterminatedString { span } => *span,
+            LexError::UnterminatedComment { span } => *span,
+            LexError::InvalidUtf8 { span } => *span,
+            LexError::InvalidSuffix { span, .. } => *span,
+            LexError::MissingDigits { span } => *span,
+            LexError::IntegerOutOfRange { span, .. } => *span,
+            LexError::FloatOutOfRange { span, .. } => *span,
+            LexError::InvalidEscape { span } => *span,
+            LexError::UnterminatedChar { span } => *span,
+            LexError::EmptyChar { span } => *span,
+            LexError::NonAsciiByte { span, .. } => *span,
+            LexError::UnclosedDelimiter { span, .. } => *span,
+            LexError::MismatchedDelimiter { span, .. } => *span,
+            LexError::UnexpectedDelimiter { span, .. } => *span,
+        }
+    }
+}
+
 /// Main lexing struct. This is an iterator
 ///
+/// The lexer works directly on a `&str` and produces [`FlatToken`]s lazily, so nothing but the
+/// tokens themselves is allocated. Brackets are produced as separate open and close tokens, use
+/// [`build_tree`] (or [`Lexer::run`]) to get the nested token tree the parser uses.
+///
 /// ## Example
 ///
 /// ```ignore
@@ -164,8 +482,9 @@ impl Span {
 ///
 /// let input = "int32 i;";
 ///
-/// let tokens = Lexer::run(input.as_bytes());
+/// let (tokens, errors) = Lexer::run(input.as_bytes());
 ///
+/// assert!(errors.is_empty());
 /// assert_eq!(
 ///     &[
 ///         Token::Ident(Ident {
@@ -181,267 +500,757 @@ impl Span {
 ///     tokens.as_slice(),
 /// );
 /// ```
-pub struct Lexer {
+pub struct Lexer<'a> {
+    file: FileId,
+    source: &'a str,
     line: usize,
     column: usize,
+    /// Byte index into `source`
     index: usize,
-    chars: Vec<char>,
+    /// Replacement characters that were inserted into `source` for invalid utf8. Holds the index
+    /// of each one, and how many bytes the source has shrunk by after it.
+    invalid: Vec<(usize, usize)>,
+    errors: Vec<LexError>,
 }
 
-impl Lexer {
-    /// Returns a `Lexer` as an iterator
-    ///
-    /// `buffer` should be a u8 ref
-    pub fn run(buffer: impl AsRef<[u8]>) -> Vec<Token> {
-        let str = std::str::from_utf8(buffer.as_ref()).expect("Unable to decode buffer as utf8!");
-
-        let mut lexer = Lexer {
+impl<'a> Lexer<'a> {
+    /// Create a lexer over `source`, with every span pointing into `file`
+    pub fn new(file: FileId, source: &'a str) -> Lexer<'a> {
+        Lexer {
+            file,
+            source,
             line: 0,
             column: 0,
             index: 0,
-            chars: str.chars().collect(),
-        };
+            invalid: Vec::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    /// Create a lexer that starts at `position` in `source`. `position` has to be the start or
+    /// the end of a token.
+    pub(crate) fn resume(source: &'a str, position: Position) -> Lexer<'a> {
+        Lexer {
+            line: position.line,
+            column: position.column,
+            index: position.offset,
+            ..Lexer::new(position.file, source)
+        }
+    }
+
+    /// Errors found so far
+    pub fn errors(&self) -> &[LexError] {
+        &self.errors
+    }
+
+    /// Take the errors found so far
+    pub fn take_errors(&mut self) -> Vec<LexError> {
+        std::mem::take(&mut self.errors)
+    }
+
+    /// Position of the next character
+    pub fn position(&self) -> Position {
+        self.make_position()
+    }
+}
+
+impl<'a> Lexer<'a> {
+    /// Lexes `buffer` into a token tree
+    ///
+    /// `buffer` should be a u8 ref. Lexing doesn't stop at errors, so the tokens are returned along
+    /// with every error that was found.
+    pub fn run(buffer: impl AsRef<[u8]>) -> (Vec<Token>, Vec<LexError>) {
+        Lexer::run_file(FileId::default(), buffer)
+    }
+
+    /// Lexes `file` from the source database
+    pub fn run_source(source: &SourceDatabase, file: FileId) -> (Vec<Token>, Vec<LexError>) {
+        Lexer::run_file(file, source.file(file).contents())
+    }
+
+    /// Lexes `buffer` like [`Lexer::run`], with every span pointing into `file`
+    pub fn run_file(file: FileId, buffer: impl AsRef<[u8]>) -> (Vec<Token>, Vec<LexError>) {
+        let bytes = buffer.as_ref();
+
+        // Only copies the input if it has to be repaired
+        let source = std::string::String::from_utf8_lossy(bytes);
+
+        let mut lexer = Lexer::new(file, &source);
+        if let Cow::Owned(_) = source {
+            lexer.invalid = Lexer::find_invalid(bytes);
+        }
+
+        let mut tokens = Vec::new();
+        while let Some(token) = lexer.next_token() {
+            tokens.extend(token);
+        }
+
+        let tree = build_tree(tokens, &mut lexer.errors);
+
+        (tree, lexer.errors)
+    }
+
+    /// Finds the invalid utf8 sequences in `bytes`. Each one is replaced with a single
+    /// `char::REPLACEMENT_CHARACTER` by `String::from_utf8_lossy`.
+    fn find_invalid(bytes: &[u8]) -> Vec<(usize, usize)> {
+        const REPLACEMENT_LEN: usize = char::REPLACEMENT_CHARACTER.len_utf8();
+
+        let mut invalid = Vec::new();
+        let mut rest = bytes;
+        // How much longer the repaired string is than the input so far
+        let mut grown = 0;
+
+        while let Err(e) = std::str::from_utf8(rest) {
+            let index = bytes.len() - rest.len() + e.valid_up_to() + grown;
+            let len = e.error_len().unwrap_or(rest.len() - e.valid_up_to());
+
+            grown += REPLACEMENT_LEN - len;
+            invalid.push((index, grown));
+
+            rest = &rest[e.valid_up_to() + len..];
+        }
 
-        lexer.collect(None)
+        invalid
+    }
+
+    /// Byte offset in the original input of `index` in `source`
+    fn source_offset(&self, index: usize) -> usize {
+        let before = self.invalid.partition_point(|(i, _)| *i < index);
+        match before {
+            0 => index,
+            n => index - self.invalid[n - 1].1,
+        }
     }
 
     /// Returns a position from the current state of the lexer.
     fn make_position(&self) -> Position {
         Position {
+            file: self.file,
             line: self.line,
             column: self.column,
+            offset: self.source_offset(self.index),
         }
     }
 
-    fn collect(&mut self, group: Option<GroupBracket>) -> Vec<Token> {
-        let mut tokens = Vec::new();
-        while let Some(token) = self.next(group) {
-            if let Some(token) = token {
-                tokens.push(token)
+    /// The input that hasn't been lexed yet
+    fn rest(&self) -> &'a str {
+        &self.source[self.index..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn peek_nth(&self, n: usize) -> Option<char> {
+        self.rest().chars().nth(n)
+    }
+
+    /// Move past `len` bytes on the current line
+    fn advance(&mut self, len: usize) {
+        self.column += self.source[self.index..self.index + len].chars().count();
+        self.index += len;
+    }
+
+    /// Move past a line ending of `len` bytes
+    fn newline(&mut self, len: usize) {
+        self.index += len;
+        self.column = 0;
+        self.line += 1;
+    }
+
+    /// Trys and parse a number
+    ///
+    /// Integers can be decimal, hex (`0x`), binary (`0b`) or octal (`0o`). Decimal numbers with a
+    /// fraction or an exponent are floats. Digits can be separated with `_`, and the number can
+    /// be followed by a type suffix (`10u8`, `1.5f32`).
+    ///
+    /// Returns `None` if the end of the buffer has been reached.
+    ///
+    fn try_number(&mut self) -> Option<Token> {
+        let rest = self.rest();
+        let bytes = rest.as_bytes();
+        if bytes.is_empty() {
+            return None;
+        }
+
+        let start = self.make_position();
+
+        let digits_from = |from: usize, radix: u32| {
+            bytes[from..]
+                .iter()
+                .position(|b| !(*b as char).is_digit(radix) && *b != b'_')
+                .map(|i| i + from)
+                .unwrap_or(bytes.len())
+        };
+
+        let (radix, prefix) = match bytes {
+            [b'0', b'x', ..] => (16, 2),
+            [b'0', b'b', ..] => (2, 2),
+            [b'0', b'o', ..] => (8, 2),
+            _ => (10, 0),
+        };
+
+        let mut len = digits_from(prefix, radix);
+        let mut is_float = false;
+
+        if radix == 10 {
+            // A digit has to follow the dot so that ranges (`0..5`) are still lexed as ranges
+            if bytes.get(len) == Some(&b'.') && bytes.get(len + 1).is_some_and(u8::is_ascii_digit) {
+                len = digits_from(len + 1, 10);
+                is_float = true;
             }
+
+            if let Some(b'e' | b'E') = bytes.get(len) {
+                let sign = matches!(bytes.get(len + 1), Some(b'+' | b'-')) as usize;
+
+                if bytes.get(len + 1 + sign).is_some_and(u8::is_ascii_digit) {
+                    len = digits_from(len + 1 + sign, 10);
+                    is_float = true;
+                }
+            }
+        }
+
+        let number_len = len;
+        len = rest[number_len..]
+            .char_indices()
+            .find(|(_, c)| !UnicodeXID::is_xid_continue(*c))
+            .map(|(i, _)| i + number_len)
+            .unwrap_or(rest.len());
+
+        let digits: std::string::String = rest[prefix..number_len]
+            .chars()
+            .filter(|c| *c != '_')
+            .collect();
+        let suffix = rest[number_len..len].to_string();
+
+        self.advance(len);
+
+        let span = start.join(self.make_position());
+
+        let float_suffix = FloatSuffix::parse(&suffix);
+        if is_float || (radix == 10 && float_suffix.is_some()) {
+            if float_suffix.is_none() && !suffix.is_empty() {
+                self.errors.push(LexError::InvalidSuffix { suffix, span });
+            }
+
+            let value: f64 = digits.parse().unwrap_or_default();
+            let out_of_range = match float_suffix {
+                Some(FloatSuffix::F32) => value.abs() > f32::MAX as f64,
+                _ => value.is_infinite(),
+            };
+
+            if out_of_range {
+                self.errors.push(LexError::FloatOutOfRange {
+                    ty: float_suffix.unwrap_or(FloatSuffix::F64).as_str(),
+                    span,
+                });
+            }
+
+            return Some(Token::Float(Float {
+                value,
+                suffix: float_suffix,
+                span,
+            }));
         }
 
-        tokens
+        let int_suffix = IntSuffix::parse(&suffix);
+        if int_suffix.is_none() && !suffix.is_empty() {
+            self.errors.push(LexError::InvalidSuffix { suffix, span });
+        }
+
+        if digits.is_empty() {
+            self.errors.push(LexError::MissingDigits { span });
+        }
+
+        // Signed types go one further, so `-128i8` lexes. Negating is left to check that.
+        let max = int_suffix.map_or(u64::MAX, |suffix| {
+            suffix.max_value() + suffix.is_signed() as u64
+        });
+        let value = match u64::from_str_radix(&digits, radix) {
+            Ok(value) if value <= max => value,
+            Ok(_) | Err(_) if !digits.is_empty() => {
+                self.errors.push(LexError::IntegerOutOfRange {
+                    ty: int_suffix.unwrap_or(IntSuffix::U64).as_str(),
+                    span,
+                });
+                0
+            }
+            _ => 0,
+        };
+
+        Some(Token::Int(Int {
+            value,
+            suffix: int_suffix,
+            span,
+        }))
     }
 
-    /// Trys and parse keyword, ident, or number
+    /// Trys and parse keyword or ident
+    ///
+    /// Identifiers follow the unicode XID_Start/XID_Continue rules, and may also start with `_`.
+    /// Raw identifiers (`r#match`) are never keywords, which allows keywords to be used as names.
     ///
     /// Returns `None` if the end of the buffer has been reached.
     ///
     fn try_keyword_or_ident(&mut self) -> Option<Token> {
-        let slice = self.chars.get(self.index..)?;
+        let rest = self.rest();
 
-        let int_ind = slice
-            .iter()
-            .position(|c| !c.is_ascii_digit())
-            .unwrap_or(slice.len());
+        let prefix = match rest.strip_prefix("r#").and_then(|s| s.chars().next()) {
+            Some(c) if is_ident_start(c) => 2,
+            _ => 0,
+        };
 
-        let float_ind = slice
-            .iter()
-            .position(|c| !c.is_ascii_digit())
-            .unwrap_or(slice.len());
+        let first = rest[prefix..].chars().next()?.len_utf8() + prefix;
+        let end = rest[first..]
+            .char_indices()
+            .find(|(_, c)| !UnicodeXID::is_xid_continue(*c))
+            .map(|(i, _)| i + first)
+            .unwrap_or(rest.len());
+
+        let ident = &rest[prefix..end];
+        let start = self.make_position();
+
+        let token = if prefix > 0 {
+            Token::Ident(Ident {
+                value: ident.to_string(),
+                span: start.span_over(rest[..end].chars()),
+            })
+        } else {
+            keyword(ident, start).unwrap_or_else(|| Ident::from_span_start(ident, start))
+        };
 
-        let kw_ind = slice
-            .iter()
-            .position(|c| !c.is_alphanumeric() && *c != '_')
-            .unwrap_or(slice.len());
+        self.advance(end);
 
-        let int_slice = &slice[..int_ind];
-        let float_slice = &slice[..float_ind];
-        let kw_slice = &slice[..kw_ind];
+        Some(token)
+    }
 
-        if !int_slice.is_empty() {
-            let token = Some(Int::from_span_start(int_slice, self.make_position()));
+    /// Trys and parse a string or char literal
+    ///
+    /// Handles plain (`"..."`), raw (`r"..."`, `r#"..."#`) and byte (`b"..."`, `br"..."`) strings,
+    /// as well as char (`'a'`) and byte (`b'a'`) literals. Byte literals are `u8` integers.
+    ///
+    /// Returns `None` if the input isn't a string or char literal.
+    ///
+    fn try_string(&mut self) -> Option<Token> {
+        let rest = self.rest();
+        let bytes = rest.as_bytes();
+        let start = self.make_position();
+
+        let (byte, raw, prefix) = match bytes {
+            [b'b', b'r', ..] => (true, true, 2),
+            [b'r', ..] => (false, true, 1),
+            [b'b', ..] => (true, false, 1),
+            _ => (false, false, 0),
+        };
 
-            self.index += int_slice.len();
-            self.column += int_slice.len();
+        let hashes = bytes[prefix..].iter().take_while(|b| **b == b'#').count();
+        let quote = *bytes.get(prefix + hashes)?;
 
-            return token;
+        match (quote, raw) {
+            (b'"', _) if raw || hashes == 0 => (),
+            (b'\'', false) if hashes == 0 => (),
+            _ => return None,
         }
 
-        if !float_slice.is_empty() {
-            let token = Some(Float::from_span_start(float_slice, self.make_position()));
+        let saved = (self.index, self.line, self.column);
+        self.advance(prefix + hashes + 1);
 
-            self.index += float_slice.len();
-            self.column += float_slice.len();
+        if quote == b'\'' {
+            if !byte {
+                if let Some(label) = self.loop_label(start) {
+                    return Some(label);
+                }
+            }
 
-            return token;
+            return Some(self.char_literal(start, byte));
         }
 
-        let token = match kw_slice {
-            /* true */
-            ['t', 'r', 'u', 'e'] => Some(Bool::from_value(true, slice, self.make_position())),
-            /* false */
-            ['f', 'a', 'l', 's', 'e'] => Some(Bool::from_value(false, slice, self.make_position())),
+        let Some(value) = self.string_body(raw.then_some(hashes), byte) else {
+            // Recover by treating the rest of the line as the string
+            (self.index, self.line, self.column) = saved;
+
+            let line = &rest[..rest.find('\n').unwrap_or(rest.len())];
+            let value = &line[prefix + hashes + 1..];
+
+            self.advance(line.len());
+
+            let span = start.join(self.make_position());
+            self.errors.push(LexError::UnterminatedString { span });
+
+            return Some(if byte {
+                Token::ByteString(ByteString {
+                    value: value.chars().map(|c| c as u8).collect(),
+                    span,
+                })
+            } else {
+                Token::String(String {
+                    value: value.to_string(),
+                    span,
+                })
+            });
+        };
 
-            /* Const */
-            ['c', 'o', 'n', 's', 't'] => Some(Const::from_span_start(self.make_position())),
-            /* Continue */
-            ['c', 'o', 'n', 't', 'i', 'n', 'u', 'e'] => {
-                Some(Continue::from_span_start(self.make_position()))
-            }
-            /* Break */
-            ['b', 'r', 'e', 'a', 'k'] => Some(Break::from_span_start(self.make_position())),
-            /* Else */
-            ['e', 'l', 's', 'e'] => Some(Else::from_span_start(self.make_position())),
-            /* Enum */
-            ['e', 'n', 'u', 'm'] => Some(Enum::from_span_start(self.make_position())),
-            /* Export */
-            ['e', 'x', 'p', 'o', 'r', 't'] => Some(Export::from_span_start(self.make_position())),
-            /* External */
-            ['e', 'x', 't', 'e', 'r', 'n', 'a', 'l'] => {
-                Some(External::from_span_start(self.make_position()))
+        let span = start.join(self.make_position());
+
+        if byte {
+            Some(Token::ByteString(ByteString {
+                value: value.chars().map(|c| c as u8).collect(),
+                span,
+            }))
+        } else {
+            Some(Token::String(String { value, span }))
+        }
+    }
+
+    /// Lexes the contents of a string up to and including the closing quote. `self.index` should
+    /// be just past the opening quote.
+    ///
+    /// `raw` is the number of `#`s if this is a raw string. Escapes are only processed in
+    /// non raw strings. Strings can span multiple lines.
+    ///
+    /// Returns `None` if the end of input was reached before the string was closed.
+    ///
+    fn string_body(&mut self, raw: Option<usize>, byte: bool) -> Option<std::string::String> {
+        let mut value = std::string::String::new();
+
+        while let Some(c) = self.peek() {
+            match c {
+                '"' if raw.is_none_or(|hashes| self.closes_raw_string(hashes)) => {
+                    self.advance(1 + raw.unwrap_or(0));
+
+                    return Some(value);
+                }
+                '\\' if raw.is_none() => {
+                    if let Some(c) = self.escape(byte) {
+                        value.push(c);
+                    }
+                }
+                '\n' => {
+                    value.push(c);
+                    self.newline(1);
+                }
+                _ => {
+                    if byte && !c.is_ascii() {
+                        self.errors.push(LexError::NonAsciiByte {
+                            found: c,
+                            span: self.make_position().span_over([c]),
+                        });
+                    }
+
+                    value.push(c);
+                    self.advance(c.len_utf8());
+                }
             }
-            /* For */ ['f', 'o', 'r'] => Some(For::from_span_start(self.make_position())),
-            /* If */ ['i', 'f'] => Some(If::from_span_start(self.make_position())),
-            /* Match */
-            ['m', 'a', 't', 'c', 'h'] => Some(Match::from_span_start(self.make_position())),
-            /* Nones */
-            ['n', 'o', 'n', 'e'] => Some(Nones::from_span_start(self.make_position())),
-            ['o', 'p', 'a', 'q', 'u', 'e'] => Some(Opaque::from_span_start(self.make_position())),
-            ['p', 'a', 'c', 'k', 'e', 'd'] => Some(Packed::from_span_start(self.make_position())),
-            /* Persist */
-            ['p', 'e', 'r', 's', 'i', 's', 't'] => {
-                Some(Persist::from_span_start(self.make_position()))
+        }
+
+        None
+    }
+
+    /// Returns true if the quote at the current index is followed by `hashes` `#`s
+    fn closes_raw_string(&self, hashes: usize) -> bool {
+        self.rest()
+            .as_bytes()
+            .get(1..1 + hashes)
+            .is_some_and(|slice| slice.iter().all(|b| *b == b'#'))
+    }
+
+    /// Lexes a loop label (`'outer`) after the opening quote
+    ///
+    /// A quote followed by an identifier is a label unless the identifier is followed by another
+    /// quote, so `'a'` is still a char. Returns `None` without advancing if this isn't a label.
+    ///
+    fn loop_label(&mut self, start: Position) -> Option<Token> {
+        let rest = self.rest();
+
+        let first = rest.chars().next().filter(|c| is_ident_start(*c))?;
+        let end = rest[first.len_utf8()..]
+            .char_indices()
+            .find(|(_, c)| !UnicodeXID::is_xid_continue(*c))
+            .map(|(i, _)| i + first.len_utf8())
+            .unwrap_or(rest.len());
+
+        if rest[end..].starts_with('\'') {
+            return None;
+        }
+
+        self.advance(end);
+
+        Some(Token::LoopLabel(LoopLabel {
+            value: rest[..end].to_string(),
+            span: start.join(self.make_position()),
+        }))
+    }
+
+    /// Lexes a char literal after the opening quote
+    fn char_literal(&mut self, start: Position, byte: bool) -> Token {
+        let value = match self.peek() {
+            Some('\\') => self.escape(byte),
+            Some('\'' | '\n') | None => {
+                self.errors.push(LexError::EmptyChar {
+                    span: start.join(self.make_position()),
+                });
+                None
             }
-            /* Return */
-            ['r', 'e', 't', 'u', 'r', 'n'] => Some(Return::from_span_start(self.make_position())),
-            /* Struct */
-            ['s', 't', 'r', 'u', 'c', 't'] => Some(Struct::from_span_start(self.make_position())),
-            /* Typedef */
-            ['t', 'y', 'p', 'e', 'd', 'e', 'f'] => {
-                Some(Typedef::from_span_start(self.make_position()))
+            Some(c) => {
+                if byte && !c.is_ascii() {
+                    self.errors.push(LexError::NonAsciiByte {
+                        found: c,
+                        span: self.make_position().span_over([c]),
+                    });
+                }
+
+                self.advance(c.len_utf8());
+                Some(c)
             }
-            /* Typeof */
-            ['t', 'y', 'p', 'e', 'o', 'f'] => Some(Typeof::from_span_start(self.make_position())),
-            /* Void */
-            ['v', 'o', 'i', 'd'] => Some(Void::from_span_start(self.make_position())),
-            /* While */
-            ['w', 'h', 'i', 'l', 'e'] => Some(While::from_span_start(self.make_position())),
-            c => Some(Ident::from_span_start(c, self.make_position())),
         };
 
-        self.index += kw_slice.len();
-        self.column += kw_slice.len();
+        if self.peek() == Some('\'') {
+            self.advance(1);
+        } else {
+            self.errors.push(LexError::UnterminatedChar {
+                span: start.join(self.make_position()),
+            });
+        }
 
-        token
+        let span = start.join(self.make_position());
+        let value = value.unwrap_or_default();
+
+        if byte {
+            Token::Int(Int {
+                value: value as u64 & 0xff,
+                suffix: Some(IntSuffix::U8),
+                span,
+            })
+        } else {
+            Token::Char(Char { value, span })
+        }
     }
 
-    fn try_string(&mut self) -> Option<Token> {
-        let char = *self.chars.get(self.index)?;
-        match char {
-            '"' => {
-                let open = self.make_position();
-                self.index += 1;
+    /// Lexes an escape sequence starting at the current backslash
+    ///
+    /// Returns the escaped character, or `None` if the escape was invalid or was a line
+    /// continuation (a backslash at the end of a line skips the newline and leading whitespace
+    /// on the next line).
+    ///
+    fn escape(&mut self, byte: bool) -> Option<char> {
+        let start = self.make_position();
+        let next = self.peek_nth(1);
 
-                let slice = self.chars.get(self.index..)?;
+        self.advance(1);
 
-                let ind = slice.iter().position(|c| *c == '"').unwrap();
+        let Some(next) = next else {
+            self.errors.push(LexError::InvalidEscape {
+                span: start.to_span(1),
+            });
+            return None;
+        };
+
+        if next == '\n' || (next == '\r' && self.peek_nth(1) == Some('\n')) {
+            self.newline(if next == '\r' { 2 } else { 1 });
 
-                let close = self.make_position();
-                let slice = &slice[..ind];
+            while let Some(' ' | '\t') = self.peek() {
+                self.advance(1);
+            }
 
-                self.index += slice.len() + 1;
-                self.column += slice.len() + 1;
+            return None;
+        }
 
-                Some(Token::String(String {
-                    value: slice.iter().collect(),
-                    span: open.join(close),
-                }))
+        self.advance(next.len_utf8());
+
+        let value = match next {
+            'n' => Some('\n'),
+            't' => Some('\t'),
+            'r' => Some('\r'),
+            '0' => Some('\0'),
+            '\\' => Some('\\'),
+            '"' => Some('"'),
+            '\'' => Some('\''),
+            'x' => {
+                let digits = self
+                    .rest()
+                    .get(..2)
+                    .filter(|digits| digits.bytes().all(|b| b.is_ascii_hexdigit()))
+                    .unwrap_or_default();
+
+                match u8::from_str_radix(digits, 16) {
+                    Ok(value) if byte || value <= 0x7f => {
+                        self.advance(2);
+                        Some(value as char)
+                    }
+                    _ => None,
+                }
+            }
+            'u' if !byte && self.peek() == Some('{') => {
+                let rest = self.rest().as_bytes();
+                let len = rest[1..]
+                    .iter()
+                    .take_while(|b| b.is_ascii_hexdigit())
+                    .count();
+
+                if len <= 6 && rest.get(1 + len) == Some(&b'}') {
+                    let digits = &self.rest()[1..1 + len];
+                    let value = u32::from_str_radix(digits, 16)
+                        .ok()
+                        .and_then(char::from_u32);
+
+                    self.advance(len + 2);
+                    value
+                } else {
+                    None
+                }
             }
             _ => None,
+        };
+
+        if value.is_none() {
+            self.errors.push(LexError::InvalidEscape {
+                span: start.join(self.make_position()),
+            });
+        }
+
+        value
+    }
+
+    /// Skips a `//` comment up to (but not including) the end of the line
+    fn skip_line_comment(&mut self) {
+        let rest = self.rest();
+        let len = match rest.find('\n') {
+            Some(i) if rest[..i].ends_with('\r') => i - 1,
+            Some(i) => i,
+            None => rest.len(),
+        };
+
+        self.advance(len);
+    }
+
+    /// Skips a `/* */` comment. Block comments can be nested.
+    fn skip_block_comment(&mut self) {
+        let start = self.make_position();
+        let mut depth = 0;
+
+        while let Some(c) = self.peek() {
+            match (c, self.rest().as_bytes().get(1)) {
+                ('/', Some(b'*')) => {
+                    depth += 1;
+                    self.advance(2);
+                }
+                ('*', Some(b'/')) => {
+                    depth -= 1;
+                    self.advance(2);
+
+                    if depth == 0 {
+                        break;
+                    }
+                }
+                ('\n', _) => self.newline(1),
+                _ => self.advance(c.len_utf8()),
+            }
+        }
+
+        if depth > 0 {
+            self.errors.push(LexError::UnterminatedComment {
+                span: start.to_span(2),
+            });
         }
     }
+
+    /// Lexes a `///` comment into a doc comment token
+    fn doc_comment(&mut self) -> Token {
+        let start = self.make_position();
+        self.advance(3);
+
+        let value_start = self.index;
+        self.skip_line_comment();
+
+        Token::DocComment(DocComment {
+            value: self.source[value_start..self.index].to_string(),
+            span: start.join(self.make_position()),
+        })
+    }
 }
 
-impl Lexer {
+impl Iterator for Lexer<'_> {
+    type Item = FlatToken;
+
+    fn next(&mut self) -> Option<FlatToken> {
+        loop {
+            if let Some(token) = self.next_token()? {
+                return Some(token);
+            }
+        }
+    }
+}
+
+impl Lexer<'_> {
     /// Try and get next token.
     ///
     /// If a valid token was found, `Some` is returned. Otherwise, if no tokens are left, `None` is returned.
     ///
     /// White space is ignored
-    fn next(&mut self, group: Option<GroupBracket>) -> Option<Option<Token>> {
-        let char = *self.chars.get(self.index)?;
-        let char_1 = self.chars.get(self.index + 1).copied();
-        let char_2 = self.chars.get(self.index + 2).copied();
-
-        match (char, group) {
-            ('(', _) => {
-                let open = self.make_position().to_span(1);
-                self.index += 1;
-                self.column += 1;
-
-                let tokens = self.collect(Some(GroupBracket::Paren));
-
-                let close = self.make_position().to_span(1);
-                self.index += 1;
-                self.column += 1;
-
-                return Some(Some(Token::Group(Group {
-                    open,
-                    close,
-                    bracket: GroupBracket::Paren,
-                    tokens,
-                })));
-            }
-            (')', Some(GroupBracket::Paren)) => return None,
-            ('[', _) => {
-                let open = self.make_position().to_span(1);
-                self.index += 1;
-                self.column += 1;
-
-                let tokens = self.collect(Some(GroupBracket::Bracket));
-
-                let close = self.make_position().to_span(1);
-                self.index += 1;
-                self.column += 1;
-
-                return Some(Some(Token::Group(Group {
-                    open,
-                    close,
-                    bracket: GroupBracket::Bracket,
-                    tokens,
-                })));
-            }
-            (']', Some(GroupBracket::Bracket)) => return None,
-            ('{', _) => {
-                let open = self.make_position().to_span(1);
-                self.index += 1;
-                self.column += 1;
-
-                let tokens = self.collect(Some(GroupBracket::Brace));
+    fn next_token(&mut self) -> Option<Option<FlatToken>> {
+        let mut chars = self.rest().chars();
+        let char = chars.next()?;
+        let char_1 = chars.next();
+        let char_2 = chars.next();
+
+        let bracket = match char {
+            '(' | ')' => Some(GroupBracket::Paren),
+            '[' | ']' => Some(GroupBracket::Bracket),
+            '{' | '}' => Some(GroupBracket::Brace),
+            _ => None,
+        };
 
-                let close = self.make_position().to_span(1);
-                self.index += 1;
-                self.column += 1;
+        if let Some(bracket) = bracket {
+            let span = self.make_position().to_span(1);
+            self.advance(1);
 
-                return Some(Some(Token::Group(Group {
-                    open,
-                    close,
-                    bracket: GroupBracket::Brace,
-                    tokens,
-                })));
-            }
-            ('}', Some(GroupBracket::Brace)) => return None,
-            _ => (),
+            return Some(Some(match char {
+                '(' | '[' | '{' => FlatToken::Open { bracket, span },
+                _ => FlatToken::Close { bracket, span },
+            }));
         }
 
         let token = match (char, char_1, char_2) {
             // Keywords and identifiers
-            ('a'..='z' | '0'..='9', _, _) => return Some(self.try_keyword_or_ident()),
-            ('"', _, _) => return Some(self.try_string()),
+            ('0'..='9', _, _) => return Some(self.try_number().map(FlatToken::Token)),
+            ('"' | '\'', _, _) => return Some(self.try_string().map(FlatToken::Token)),
+            ('r' | 'b', Some('"' | '\'' | '#' | 'r'), _) => {
+                let token = self.try_string().or_else(|| self.try_keyword_or_ident());
+
+                return Some(token.map(FlatToken::Token));
+            }
+            (c, _, _) if is_ident_start(c) => {
+                return Some(self.try_keyword_or_ident().map(FlatToken::Token))
+            }
+
+            // Comments
+            ('/', Some('/'), Some('/')) if self.peek_nth(3) != Some('/') => {
+                return Some(Some(FlatToken::Token(self.doc_comment())))
+            }
+            ('/', Some('/'), _) => {
+                self.skip_line_comment();
+                return Some(None);
+            }
+            ('/', Some('*'), _) => {
+                self.skip_block_comment();
+                return Some(None);
+            }
 
             // Punctuation
             (';', _, _) => Some(Semi::from_span_start(self.make_position())),
+            (':', Some(':'), _) => Some(PathSep::from_span_start(self.make_position())),
             (':', _, _) => Some(Colon::from_span_start(self.make_position())),
             (',', _, _) => Some(Comma::from_span_start(self.make_position())),
             ('#', _, _) => Some(Pound::from_span_start(self.make_position())),
+            ('@', _, _) => Some(At::from_span_start(self.make_position())),
+            ('?', _, _) => Some(Question::from_span_start(self.make_position())),
 
             // Operators
             ('&', Some('='), _) => Some(AndEq::from_span_start(self.make_position())),
             ('&', Some('&'), _) => Some(LogicalAnd::from_span_start(self.make_position())),
             ('&', _, _) => Some(And::from_span_start(self.make_position())),
             ('=', Some('='), _) => Some(Eq::from_span_start(self.make_position())),
+            ('=', Some('>'), _) => Some(FatArrow::from_span_start(self.make_position())),
             ('=', _, _) => Some(Assign::from_span_start(self.make_position())),
             ('.', Some('.'), _) => Some(Range::from_span_start(self.make_position())),
             ('.', _, _) => Some(Dot::from_span_start(self.make_position())),
@@ -470,44 +1279,56 @@ impl Lexer {
             ('/', _, _) => Some(Slash::from_span_start(self.make_position())),
             ('*', Some('='), _) => Some(StarEq::from_span_start(self.make_position())),
             ('*', _, _) => Some(Star::from_span_start(self.make_position())),
+            ('~', _, _) => Some(Tilde::from_span_start(self.make_position())),
             ('^', Some('='), _) => Some(XorEq::from_span_start(self.make_position())),
             ('^', _, _) => Some(Xor::from_span_start(self.make_position())),
 
             // Whitespace
             ('\r', Some('\n'), _) => {
-                self.index += 2;
-                self.column = 0;
-                self.line += 1;
-
+                self.newline(2);
                 None
             }
             ('\n', _, _) => {
-                self.index += 1;
-                self.column = 0;
-                self.line += 1;
-
+                self.newline(1);
                 None
             }
             (' ' | '\x09'..='\x0d', _, _) => {
-                self.index += 1;
-                self.column += 1;
+                self.advance(1);
+                None
+            }
+            _ => {
+                let index = self.index;
+                let start = self.make_position();
+                self.advance(char.len_utf8());
+
+                // Measured from the source so invalid utf8 covers the original bytes
+                let span = start.join(self.make_position());
+
+                if char == char::REPLACEMENT_CHARACTER
+                    && self.invalid.iter().any(|(i, _)| *i == index)
+                {
+                    self.errors.push(LexError::InvalidUtf8 { span });
+                } else {
+                    self.errors
+                        .push(LexError::UnknownCharacter { found: char, span });
+                }
 
                 None
             }
-            _ => panic!("Unknown token `{char}`"),
         };
 
         if let Some(tok) = token.as_ref() {
-            self.column += tok.len();
-            self.index += tok.len();
+            self.advance(tok.len());
         }
 
-        Some(token)
+        Some(token.map(FlatToken::Token))
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::path::Path;
+
     use super::*;
 
     #[test]
@@ -522,53 +1343,55 @@ external void main() {
 }
 ";
 
-        let tokens = Lexer::run(input.as_bytes());
+        let (tokens, errors) = Lexer::run(input.as_bytes());
         println!("{:#?}", tokens);
+        assert!(errors.is_empty());
 
         assert_eq!(
             vec![
-                Token::Export(Export(span!(1:0-6))),
-                Token::Struct(Struct(span!(1:7-13))),
+                Token::Export(Export(span!(1:0-6 @ 1))),
+                Token::Struct(Struct(span!(1:7-13 @ 8))),
                 Token::Ident(Ident {
                     value: "data".to_string(),
-                    span: span!(1:14-18)
+                    span: span!(1:14-18 @ 15)
                 }),
                 Token::Group(Group {
-                    open: span!(1:19-20),
-                    close: span!(3:0-1),
+                    open: span!(1:19-20 @ 20),
+                    close: span!(3:0-1 @ 39),
                     bracket: GroupBracket::Brace,
                     tokens: vec![
                         Token::Ident(Ident {
                             value: "int32".to_string(),
-                            span: span!(2:4-9)
+                            span: span!(2:4-9 @ 26)
                         }),
                         Token::Ident(Ident {
                             value: "i".to_string(),
-                            span: span!(2:10-11)
+                            span: span!(2:10-11 @ 32)
                         }),
-                        Token::Assign(Assign(span!(2:12-13))),
+                        Token::Assign(Assign(span!(2:12-13 @ 34))),
                         Token::Int(Int {
                             value: 8,
-                            span: span!(2:14-15)
+                            suffix: None,
+                            span: span!(2:14-15 @ 36)
                         }),
-                        Token::Semi(Semi(span!(2:15-16))),
+                        Token::Semi(Semi(span!(2:15-16 @ 37))),
                     ]
                 }),
-                Token::External(External(span!(5:0-8))),
-                Token::Void(Void(span!(5:9-13))),
+                Token::External(External(span!(5:0-8 @ 42))),
+                Token::Void(Void(span!(5:9-13 @ 51))),
                 Token::Ident(Ident {
                     value: "main".to_string(),
-                    span: span!(5:14-18)
+                    span: span!(5:14-18 @ 56)
                 }),
                 Token::Group(Group {
-                    open: span!(5:18-19),
-                    close: span!(5:19-20),
+                    open: span!(5:18-19 @ 60),
+                    close: span!(5:19-20 @ 61),
                     bracket: GroupBracket::Paren,
                     tokens: vec![]
                 }),
                 Token::Group(Group {
-                    open: span!(5:21-22),
-                    close: span!(7:0-1),
+                    open: span!(5:21-22 @ 63),
+                    close: span!(7:0-1 @ 66),
                     bracket: GroupBracket::Brace,
                     tokens: vec![]
                 }),
@@ -576,4 +1399,529 @@ external void main() {
             tokens.as_slice()
         );
     }
+
+    #[test]
+    pub fn comments() {
+        let input = "/* outer /* inner\n */ still comment */ a // line\n/// doc\nb";
+
+        let (tokens, errors) = Lexer::run(input.as_bytes());
+        assert!(errors.is_empty());
+
+        assert_eq!(
+            vec![
+                Token::Ident(Ident {
+                    value: "a".to_string(),
+                    span: span!(1:21-22 @ 39)
+                }),
+                Token::DocComment(DocComment {
+                    value: " doc".to_string(),
+                    span: span!(2:0-7 @ 49)
+                }),
+                Token::Ident(Ident {
+                    value: "b".to_string(),
+                    span: span!(3:0-1 @ 57)
+                }),
+            ],
+            tokens
+        );
+    }
+
+    #[test]
+    pub fn recovers_from_errors() {
+        let mut input = b"a $ `\n\"open\nb ".to_vec();
+        input.push(0xff);
+
+        let (tokens, errors) = Lexer::run(input);
+
+        assert_eq!(
+            vec![
+                LexError::UnknownCharacter {
+                    found: '$',
+                    span: span!(0:2-3)
+                },
+                LexError::UnknownCharacter {
+                    found: '`',
+                    span: span!(0:4-5)
+                },
+                LexError::UnterminatedString {
+                    span: span!(1:0-5 @ 6)
+                },
+                LexError::InvalidUtf8 {
+                    span: span!(2:2-3 @ 14)
+                },
+            ],
+            errors
+        );
+
+        assert_eq!(
+            vec![
+                Token::Ident(Ident {
+                    value: "a".to_string(),
+                    span: span!(0:0-1)
+                }),
+                Token::String(String {
+                    value: "open".to_string(),
+                    span: span!(1:0-5 @ 6)
+                }),
+                Token::Ident(Ident {
+                    value: "b".to_string(),
+                    span: span!(2:0-1 @ 12)
+                }),
+            ],
+            tokens
+        );
+    }
+
+    #[test]
+    pub fn identifiers() {
+        let input = "Vec3 _tmp r#match match größe _";
+
+        let (tokens, errors) = Lexer::run(input.as_bytes());
+        assert!(errors.is_empty());
+
+        assert_eq!(
+            vec![
+                Token::Ident(Ident {
+                    value: "Vec3".to_string(),
+                    span: span!(0:0-4)
+                }),
+                Token::Ident(Ident {
+                    value: "_tmp".to_string(),
+                    span: span!(0:5-9)
+                }),
+                Token::Ident(Ident {
+                    value: "match".to_string(),
+                    span: span!(0:10-17)
+                }),
+                Token::Match(Match(span!(0:18-23))),
+                Token::Ident(Ident {
+                    value: "größe".to_string(),
+                    span: span!(0:24-29 @ 24-31)
+                }),
+                Token::Ident(Ident {
+                    value: "_".to_string(),
+                    span: span!(0:30-31 @ 32)
+                }),
+            ],
+            tokens
+        );
+    }
+
+    #[test]
+    pub fn numbers() {
+        let input = "3.25 1e3 2.5E-2f32 0xff_ff 0b1010 0o17 1_000u16 10u8 1f64 0..5";
+
+        let (tokens, errors) = Lexer::run(input.as_bytes());
+        assert!(errors.is_empty());
+
+        assert_eq!(
+            vec![
+                Token::Float(Float {
+                    value: 3.25,
+                    suffix: None,
+                    span: span!(0:0-4)
+                }),
+                Token::Float(Float {
+                    value: 1000.0,
+                    suffix: None,
+                    span: span!(0:5-8)
+                }),
+                Token::Float(Float {
+                    value: 0.025,
+                    suffix: Some(FloatSuffix::F32),
+                    span: span!(0:9-18)
+                }),
+                Token::Int(Int {
+                    value: 0xffff,
+                    suffix: None,
+                    span: span!(0:19-26)
+                }),
+                Token::Int(Int {
+                    value: 0b1010,
+                    suffix: None,
+                    span: span!(0:27-33)
+                }),
+                Token::Int(Int {
+                    value: 0o17,
+                    suffix: None,
+                    span: span!(0:34-38)
+                }),
+                Token::Int(Int {
+                    value: 1000,
+                    suffix: Some(IntSuffix::U16),
+                    span: span!(0:39-47)
+                }),
+                Token::Int(Int {
+                    value: 10,
+                    suffix: Some(IntSuffix::U8),
+                    span: span!(0:48-52)
+                }),
+                Token::Float(Float {
+                    value: 1.0,
+                    suffix: Some(FloatSuffix::F64),
+                    span: span!(0:53-57)
+                }),
+                Token::Int(Int {
+                    value: 0,
+                    suffix: None,
+                    span: span!(0:58-59)
+                }),
+                Token::Range(Range(span!(0:59-61))),
+                Token::Int(Int {
+                    value: 5,
+                    suffix: None,
+                    span: span!(0:61-62)
+                }),
+            ],
+            tokens
+        );
+    }
+
+    #[test]
+    pub fn number_errors() {
+        let input = "256u8 18446744073709551616 10foo 0x 1e999";
+
+        let (_, errors) = Lexer::run(input.as_bytes());
+
+        assert_eq!(
+            vec![
+                LexError::IntegerOutOfRange {
+                    ty: "u8",
+                    span: span!(0:0-5)
+                },
+                LexError::IntegerOutOfRange {
+                    ty: "u64",
+                    span: span!(0:6-26)
+                },
+                LexError::InvalidSuffix {
+                    suffix: "foo".to_string(),
+                    span: span!(0:27-32)
+                },
+                LexError::MissingDigits {
+                    span: span!(0:33-35)
+                },
+                LexError::FloatOutOfRange {
+                    ty: "f64",
+                    span: span!(0:36-41)
+                },
+            ],
+            errors
+        );
+
+        let (_, errors) = Lexer::run(b"128i8 9223372036854775808i64 129i8");
+
+        assert_eq!(
+            vec![LexError::IntegerOutOfRange {
+                ty: "i8",
+                span: span!(0:29-34)
+            }],
+            errors
+        );
+    }
+
+    #[test]
+    pub fn strings() {
+        let input = "\"a\\n\\t\\\\\\\"\\u{e9}\\x41\" r#\"raw \\n \"quoted\"\"# b\"\\xff\" 'c' '\\'' b'a'\n\"two\nlines\" x";
+
+        let (tokens, errors) = Lexer::run(input.as_bytes());
+        assert!(errors.is_empty());
+
+        assert_eq!(
+            vec![
+                Token::String(String {
+                    value: "a\n\t\\\"\u{e9}A".to_string(),
+                    span: span!(0:0-21)
+                }),
+                Token::String(String {
+                    value: "raw \\n \"quoted\"".to_string(),
+                    span: span!(0:22-42)
+                }),
+                Token::ByteString(ByteString {
+                    value: vec![0xff],
+                    span: span!(0:43-50)
+                }),
+                Token::Char(Char {
+                    value: 'c',
+                    span: span!(0:51-54)
+                }),
+                Token::Char(Char {
+                    value: '\'',
+                    span: span!(0:55-59)
+                }),
+                Token::Int(Int {
+                    value: b'a' as u64,
+                    suffix: Some(IntSuffix::U8),
+                    span: span!(0:60-64)
+                }),
+                Token::String(String {
+                    value: "two\nlines".to_string(),
+                    span: Position {
+                        file: FileId::default(),
+                        line: 1,
+                        column: 0,
+                        offset: 65
+                    }
+                    .join(Position {
+                        file: FileId::default(),
+                        line: 2,
+                        column: 6,
+                        offset: 76
+                    })
+                }),
+                Token::Ident(Ident {
+                    value: "x".to_string(),
+                    span: span!(2:7-8 @ 77)
+                }),
+            ],
+            tokens
+        );
+    }
+
+    #[test]
+    pub fn loop_labels() {
+        let input = "'outer: 'a' 'a";
+
+        let (tokens, errors) = Lexer::run(input.as_bytes());
+        assert!(errors.is_empty());
+
+        assert_eq!(
+            vec![
+                Token::LoopLabel(LoopLabel {
+                    value: "outer".to_string(),
+                    span: span!(0:0-6)
+                }),
+                Token::Colon(Colon(span!(0:6-7))),
+                Token::Char(Char {
+                    value: 'a',
+                    span: span!(0:8-11)
+                }),
+                Token::LoopLabel(LoopLabel {
+                    value: "a".to_string(),
+                    span: span!(0:12-14)
+                }),
+            ],
+            tokens
+        );
+    }
+
+    #[test]
+    pub fn string_errors() {
+        let input = r"'\q' '' b'é' '+b";
+
+        let (_, errors) = Lexer::run(input.as_bytes());
+
+        assert_eq!(
+            vec![
+                LexError::InvalidEscape { span: span!(0:1-3) },
+                LexError::EmptyChar { span: span!(0:5-6) },
+                LexError::NonAsciiByte {
+                    found: 'é',
+                    span: span!(0:10-11 @ 10-12)
+                },
+                LexError::UnterminatedChar {
+                    span: span!(0:13-15 @ 14)
+                },
+            ],
+            errors
+        );
+    }
+
+    #[test]
+    pub fn spans() {
+        let input = "é = \"multi\nline\";";
+
+        let (tokens, errors) = Lexer::run(input.as_bytes());
+        assert!(errors.is_empty());
+
+        let string = tokens[2].as_span();
+        assert!(string.is_multiline());
+        assert_eq!(&input[string.range()], "\"multi\nline\"");
+        assert_eq!(string.len(), 12);
+        assert_eq!(&input[tokens[0].as_span().range()], "é");
+
+        assert_eq!(Vec::<Ident>::new().try_as_span(), None);
+        assert_eq!(Vec::<Ident>::new().as_span(), Span::EMPTY);
+        assert_eq!(
+            tokens.try_as_span(),
+            Some(tokens[0].as_span().join(tokens[3].as_span()))
+        );
+    }
+
+    #[test]
+    pub fn source_database() {
+        let mut source = SourceDatabase::new();
+        let first = source.add("first.pl", "a");
+        let second = source.add("second.pl", "x\r\n  größe = 1;\n");
+
+        let (tokens, _) = Lexer::run_source(&source, second);
+        let ident = tokens[1].as_span();
+
+        assert_eq!(ident.file(), second);
+        assert_eq!(source.path(ident.file()), Path::new("second.pl"));
+        assert_eq!(source.slice(ident), "größe");
+        assert_eq!(source.position(second, ident.end.offset), ident.end);
+        assert_eq!(source.file(second).line(1).unwrap(), "  größe = 1;");
+        assert_eq!(source.file(second).line(0).unwrap(), "x");
+
+        let (tokens, _) = Lexer::run_source(&source, first);
+        assert_eq!(tokens[0].as_span().file(), first);
+    }
+
+    #[test]
+    pub fn unbalanced_brackets() {
+        let (tokens, errors) = Lexer::run("a)".as_bytes());
+        assert_eq!(1, tokens.len());
+        assert_eq!(
+            vec![LexError::UnexpectedDelimiter {
+                found: GroupBracket::Paren,
+                span: span!(0:1-2)
+            }],
+            errors
+        );
+
+        let (_, errors) = Lexer::run("(a]".as_bytes());
+        assert_eq!(
+            vec![
+                LexError::MismatchedDelimiter {
+                    found: GroupBracket::Bracket,
+                    span: span!(0:2-3),
+                    open: GroupBracket::Paren,
+                    open_span: span!(0:0-1)
+                },
+                LexError::UnclosedDelimiter {
+                    bracket: GroupBracket::Paren,
+                    span: span!(0:0-1),
+                    expected: span!(0:2-2)
+                },
+            ],
+            errors
+        );
+
+        // The `}` closes the outer group, so the missing `)` goes after `;`
+        let (tokens, errors) = Lexer::run("{ f(a; }".as_bytes());
+        assert_eq!(
+            vec![LexError::UnclosedDelimiter {
+                bracket: GroupBracket::Paren,
+                span: span!(0:3-4),
+                expected: span!(0:6-6)
+            }],
+            errors
+        );
+        let Token::Group(group) = &tokens[0] else {
+            panic!("expected a group, found {:?}", tokens[0]);
+        };
+        assert_eq!(span!(0:7-8), group.close);
+        assert_eq!(2, group.tokens.len());
+
+        // The `}` at the start of a line closes the function, not the `if`
+        let input = "fn a() {\n    if x {\n        foo();\n    bar();\n}\nfn b() {}";
+        let (tokens, errors) = Lexer::run(input.as_bytes());
+        assert_eq!(
+            vec![LexError::UnclosedDelimiter {
+                bracket: GroupBracket::Brace,
+                span: span!(1:9-10 @ 18),
+                expected: span!(2:14-14 @ 34)
+            }],
+            errors
+        );
+        assert_eq!(8, tokens.len());
+        let Token::Group(body) = &tokens[3] else {
+            panic!("expected a group, found {:?}", tokens[3]);
+        };
+        assert_eq!(6, body.tokens.len());
+
+        // A group that's never closed ends when the indentation drops back
+        let input = "fn a() {\n    foo();\nfn b() {\n}";
+        let (tokens, errors) = Lexer::run(input.as_bytes());
+        assert_eq!(
+            vec![LexError::UnclosedDelimiter {
+                bracket: GroupBracket::Brace,
+                span: span!(0:7-8),
+                expected: span!(1:10-10 @ 19)
+            }],
+            errors
+        );
+        assert_eq!(8, tokens.len());
+    }
+
+    #[test]
+    pub fn render_report() {
+        colored::control::set_override(false);
+
+        let mut source = SourceDatabase::new();
+        let file = source.add("main.pl", "int32 main() {\n    foo(1;\n}\n");
+
+        let (_, errors) = Lexer::run_source(&source, file);
+        let report = errors[0]
+            .to_report()
+            .with_note("brackets are matched by indentation");
+
+        assert_eq!(
+            report.display(&source).to_string(),
+            "error: Unclosed `(`, expected `)` at 2:11
+ --> main.pl:2:8
+  |
+2 |     foo(1;
+  |        ^ unclosed
+  |           - `)` expected here
+  |
+  = note: brackets are matched by indentation
+"
+        );
+    }
+
+    #[test]
+    pub fn lazy_tokens() {
+        let mut lexer = Lexer::new(FileId::default(), "f(x)");
+
+        assert!(matches!(
+            lexer.next(),
+            Some(FlatToken::Token(Token::Ident(_)))
+        ));
+        assert!(matches!(
+            lexer.next(),
+            Some(FlatToken::Open {
+                bracket: GroupBracket::Paren,
+                ..
+            })
+        ));
+        assert_eq!(lexer.position().offset, 2);
+        assert_eq!(lexer.count(), 2);
+    }
+
+    /// Applies each edit to the buffer and checks it matches lexing the new source from scratch
+    fn check_relex(source: &str, edits: &[(std::ops::Range<usize>, &str)]) {
+        let mut source = source.to_string();
+        let mut buffer = TokenBuffer::new(FileId::default(), &source);
+
+        for (edit, text) in edits {
+            source.replace_range(edit.clone(), text);
+            buffer.relex(&source, edit.clone(), text.len());
+
+            let fresh = TokenBuffer::new(FileId::default(), &source);
+            assert_eq!(fresh.tokens(), buffer.tokens(), "{source:?}");
+            assert_eq!(fresh.errors(), buffer.errors(), "{source:?}");
+            assert_eq!(fresh.tree(), buffer.tree(), "{source:?}");
+        }
+    }
+
+    #[test]
+    pub fn relex() {
+        let source = "fn main() {\n    let x = 1;\n    call(x, \"a\");\n}\n";
+
+        // Rename an identifier
+        check_relex(source, &[(20..21, "größe")]);
+        // Insert and remove a line
+        check_relex(source, &[(12..12, "    let y = 2;\n"), (12..27, "")]);
+        // Open a string and close it again
+        check_relex(source, &[(24..24, "\""), (26..26, "\"")]);
+        // Open and close a comment, and unbalance the brackets
+        check_relex(source, &[(16..16, "/*"), (40..40, "*/"), (47..48, "")]);
+
+        let mut buffer = TokenBuffer::new(FileId::default(), source);
+        let mut edited = source.to_string();
+        edited.replace_range(29..33, "print");
+        // `;` ends too close to the edit to be kept
+        assert_eq!(buffer.relex(&edited, 29..33, 5), 9..11);
+    }
 }
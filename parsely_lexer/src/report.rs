@@ -0,0 +1,317 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display, Write},
+};
+
+use colored::{ColoredString, Colorize};
+
+use crate::{source::SourceFile, SourceDatabase, Span};
+
+/// Severity of a [`Report`]
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Info,
+    Warning,
+    Error,
+}
+
+impl Level {
+    fn name(self) -> ColoredString {
+        match self {
+            Level::Info => "info".bold().cyan(),
+            Level::Warning => "warning".bold().yellow(),
+            Level::Error => "error".bold().red(),
+        }
+    }
+
+    fn paint(self, text: &str) -> ColoredString {
+        match self {
+            Level::Info => text.green(),
+            Level::Warning => text.yellow(),
+            Level::Error => text.red(),
+        }
+        .bold()
+    }
+}
+
+/// A span of source with a message, shown under the lines it covers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+}
+
+impl Label {
+    pub fn new(span: Span, message: impl Into<String>) -> Label {
+        Label {
+            span,
+            message: message.into(),
+        }
+    }
+}
+
+/// A message about the source, rendered along with the lines it points to
+///
+/// Errors from the lexer, parser and code generator are all rendered through this, so they
+/// look the same.
+///
+/// Each line is shown once, in order, with the underlines of every label on it.
+///
+/// ```text
+/// error: Expected `;`, found end of input
+///  --> main.pl:3:1
+///   |
+/// 1 | int32 main() {
+///   |              - group starts here
+/// 3 | }
+///   | ^
+///   |
+/// ```
+#[derive(Debug, Clone)]
+pub struct Report {
+    pub level: Level,
+    pub message: String,
+    /// Where the problem is. Underlined with `^`.
+    pub primary: Label,
+    /// Related places, such as where a bracket was opened. Underlined with `-`.
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
+}
+
+impl Report {
+    pub fn new(level: Level, message: impl Into<String>, span: Span) -> Report {
+        Report {
+            level,
+            message: message.into(),
+            primary: Label::new(span, ""),
+            labels: Vec::new(),
+            notes: Vec::new(),
+        }
+    }
+
+    pub fn error(message: impl Into<String>, span: Span) -> Report {
+        Report::new(Level::Error, message, span)
+    }
+
+    /// Set the message shown under the primary span
+    pub fn with_message(mut self, message: impl Into<String>) -> Report {
+        self.primary.message = message.into();
+        self
+    }
+
+    pub fn with_label(mut self, span: Span, message: impl Into<String>) -> Report {
+        self.labels.push(Label::new(span, message));
+        self
+    }
+
+    pub fn with_labels(mut self, labels: impl IntoIterator<Item = Label>) -> Report {
+        self.labels.extend(labels);
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Report {
+        self.notes.push(note.into());
+        self
+    }
+
+    /// Format the report into `f`. `source` should contain the files the spans point into.
+    pub fn render(&self, source: &SourceDatabase, f: &mut dyn Write) -> fmt::Result {
+        writeln!(
+            f,
+            "{}{}{}",
+            self.level.name(),
+            ": ".bold(),
+            self.message.bold()
+        )?;
+
+        let labels = std::iter::once(&self.primary).chain(&self.labels);
+
+        let pad = labels
+            .clone()
+            .map(|label| (label.span.end.line + 1).to_string().len())
+            .max()
+            .unwrap_or(1);
+
+        let span = self.primary.span;
+        let Some(file) = source.get(span.file()) else {
+            return Ok(());
+        };
+
+        writeln!(
+            f,
+            "{:width$}{} {}:{}:{}",
+            "",
+            "-->".bold().cyan(),
+            file.path().display(),
+            span.start.line + 1,
+            span.start.column + 1,
+            width = pad,
+        )?;
+
+        writeln!(f, "{:width$} {}", "", "|".bold().cyan(), width = pad)?;
+
+        // Files in the order their first label appears in, starting with the primary one
+        let mut files = Vec::new();
+        for label in labels.clone() {
+            if !files.contains(&label.span.file()) {
+                files.push(label.span.file());
+            }
+        }
+
+        for id in files {
+            let Some(label_file) = source.get(id) else {
+                continue;
+            };
+
+            if id != span.file() {
+                let first = labels
+                    .clone()
+                    .find(|label| label.span.file() == id)
+                    .expect("File without a label!");
+
+                writeln!(
+                    f,
+                    "{:width$}{} {}:{}:{}",
+                    "",
+                    ":::".bold().cyan(),
+                    label_file.path().display(),
+                    first.span.start.line + 1,
+                    first.span.start.column + 1,
+                    width = pad,
+                )?;
+            }
+
+            let file_labels = labels
+                .clone()
+                .enumerate()
+                .filter(|(_, label)| label.span.file() == id)
+                .map(|(i, label)| (label, i == 0));
+            self.render_lines(f, label_file, file_labels, pad)?;
+        }
+
+        writeln!(f, "{:width$} {}", "", "|".bold().cyan(), width = pad)?;
+
+        for note in &self.notes {
+            writeln!(
+                f,
+                "{:width$} {} {} {}",
+                "",
+                "=".bold().cyan(),
+                "note:".bold(),
+                note,
+                width = pad
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Format the lines the labels cover, each shown once with the underlines of every label
+    /// on it. `primary` is true for the label underlined with `^`.
+    fn render_lines<'l>(
+        &self,
+        f: &mut dyn Write,
+        file: &SourceFile,
+        labels: impl Iterator<Item = (&'l Label, bool)>,
+        pad: usize,
+    ) -> fmt::Result {
+        let mut lines: BTreeMap<usize, Vec<Underline>> = BTreeMap::new();
+
+        for (label, primary) in labels {
+            let span = label.span;
+
+            for line in span.start.line..=span.end.line {
+                let Some(text) = file.line(line) else {
+                    break;
+                };
+
+                // Columns are in characters, so the underline lines up with the text
+                let start = if line == span.start.line {
+                    span.start.column
+                } else {
+                    0
+                };
+                let end = if line == span.end.line {
+                    span.end.column
+                } else {
+                    text.chars().count()
+                };
+
+                lines.entry(line).or_default().push(Underline {
+                    start,
+                    end,
+                    primary,
+                    // The message goes after the last line of the label
+                    message: if line == span.end.line {
+                        &label.message
+                    } else {
+                        ""
+                    },
+                });
+            }
+        }
+
+        for (line, mut underlines) in lines {
+            let text = file.line(line).expect("Line was just read!");
+            writeln!(
+                f,
+                "{:>width$} {} {}",
+                (line + 1).to_string().bold().white(),
+                "|".bold().cyan(),
+                text,
+                width = pad
+            )?;
+
+            underlines.sort_by_key(|u| u.start);
+            for u in underlines {
+                let mut underline =
+                    if u.primary { "^" } else { "-" }.repeat(u.end.saturating_sub(u.start).max(1));
+                if !u.message.is_empty() {
+                    underline.push(' ');
+                    underline.push_str(u.message);
+                }
+
+                let underline = if u.primary {
+                    self.level.paint(&underline)
+                } else {
+                    underline.bold().cyan()
+                };
+
+                writeln!(
+                    f,
+                    "{:width$} {} {:start$}{}",
+                    "",
+                    "|".bold().cyan(),
+                    "",
+                    underline,
+                    width = pad,
+                    start = u.start,
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Helper for formatting the report with `{}`
+    pub fn display<'a>(&'a self, source: &'a SourceDatabase) -> ReportFmt<'a> {
+        ReportFmt(self, source)
+    }
+}
+
+/// Part of a line covered by a label
+struct Underline<'a> {
+    start: usize,
+    end: usize,
+    primary: bool,
+    message: &'a str,
+}
+
+/// Helper struct for formatting a [`Report`] with `{}`
+pub struct ReportFmt<'a>(pub &'a Report, pub &'a SourceDatabase);
+
+impl Display for ReportFmt<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.render(self.1, f)
+    }
+}
@@ -0,0 +1,160 @@
+use std::{
+    borrow::Cow,
+    path::{Path, PathBuf},
+};
+
+use crate::{FileId, Position, Span};
+
+/// A single source file owned by a [`SourceDatabase`]
+#[derive(Debug, Clone)]
+pub struct SourceFile {
+    path: PathBuf,
+    contents: Vec<u8>,
+    /// Byte offset of the start of every line
+    line_starts: Vec<usize>,
+}
+
+impl SourceFile {
+    fn new(path: PathBuf, contents: Vec<u8>) -> SourceFile {
+        let line_starts = std::iter::once(0)
+            .chain(
+                contents
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, b)| **b == b'\n')
+                    .map(|(i, _)| i + 1),
+            )
+            .collect();
+
+        SourceFile {
+            path,
+            contents,
+            line_starts,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Raw contents of the file. This is what the lexer runs on.
+    pub fn contents(&self) -> &[u8] {
+        &self.contents
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// Byte range of `line` (starting at 0), without the line ending
+    pub fn line_range(&self, line: usize) -> Option<std::ops::Range<usize>> {
+        let start = *self.line_starts.get(line)?;
+        let mut end = self
+            .line_starts
+            .get(line + 1)
+            .map(|next| next - 1)
+            .unwrap_or(self.contents.len());
+
+        if end > start && self.contents[end - 1] == b'\r' {
+            end -= 1;
+        }
+
+        Some(start..end)
+    }
+
+    /// Text of `line` (starting at 0), without the line ending
+    pub fn line(&self, line: usize) -> Option<Cow<'_, str>> {
+        let range = self.line_range(line)?;
+        Some(String::from_utf8_lossy(&self.contents[range]))
+    }
+
+    /// Text covered by `range`. Invalid utf8 is replaced with `char::REPLACEMENT_CHARACTER`.
+    pub fn slice(&self, range: std::ops::Range<usize>) -> Cow<'_, str> {
+        let end = range.end.min(self.contents.len());
+        let start = range.start.min(end);
+        String::from_utf8_lossy(&self.contents[start..end])
+    }
+
+    /// Line and column (starting at 0) of a byte offset. Columns are counted in characters, the
+    /// same way the lexer counts them.
+    pub fn line_column(&self, offset: usize) -> (usize, usize) {
+        let offset = offset.min(self.contents.len());
+        let line = match self.line_starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(next) => next - 1,
+        };
+
+        let start = self.line_starts[line];
+        let column = String::from_utf8_lossy(&self.contents[start..offset])
+            .chars()
+            .count();
+
+        (line, column)
+    }
+}
+
+/// Owns the contents of every file in a build
+///
+/// Files are identified by the [`FileId`] returned when they're added, and every span produced by
+/// the lexer records the id of the file it's in. The lexer, parser and code generator all borrow
+/// the same database, so diagnostics can be reported against the right file.
+#[derive(Debug, Clone, Default)]
+pub struct SourceDatabase {
+    files: Vec<SourceFile>,
+}
+
+impl SourceDatabase {
+    pub fn new() -> SourceDatabase {
+        SourceDatabase::default()
+    }
+
+    /// Add a file with the given contents
+    pub fn add(&mut self, path: impl Into<PathBuf>, contents: impl Into<Vec<u8>>) -> FileId {
+        let id = FileId(self.files.len() as u32);
+        self.files
+            .push(SourceFile::new(path.into(), contents.into()));
+        id
+    }
+
+    /// Read the file at `path` and add it
+    pub fn load(&mut self, path: impl AsRef<Path>) -> std::io::Result<FileId> {
+        let contents = std::fs::read(path.as_ref())?;
+        Ok(self.add(path.as_ref(), contents))
+    }
+
+    /// Get a file. Panics if `id` wasn't created by this database.
+    pub fn file(&self, id: FileId) -> &SourceFile {
+        &self.files[id.0 as usize]
+    }
+
+    pub fn get(&self, id: FileId) -> Option<&SourceFile> {
+        self.files.get(id.0 as usize)
+    }
+
+    pub fn files(&self) -> impl Iterator<Item = (FileId, &SourceFile)> {
+        self.files
+            .iter()
+            .enumerate()
+            .map(|(i, file)| (FileId(i as u32), file))
+    }
+
+    pub fn path(&self, id: FileId) -> &Path {
+        self.file(id).path()
+    }
+
+    /// Position of a byte offset in `file`
+    pub fn position(&self, file: FileId, offset: usize) -> Position {
+        let (line, column) = self.file(file).line_column(offset);
+        Position {
+            file,
+            line,
+            column,
+            offset,
+        }
+    }
+
+    /// Text covered by `span`
+    pub fn slice(&self, span: Span) -> Cow<'_, str> {
+        self.file(span.file()).slice(span.range())
+    }
+}
@@ -11,13 +11,7 @@ macro_rules! define_tokens {
                 pub const NAME: &str = stringify!($st);
 
                 pub fn from_span_start(start: $crate::Position) -> $token_enum {
-                    $token_enum::$struct_name($struct_name($crate::Span {
-                        end: $crate::Position {
-                            line: start.line,
-                            column: start.column + $struct_name::len(),
-                        },
-                        start,
-                    }))
+                    $token_enum::$struct_name($struct_name(start.to_span($struct_name::len())))
                 }
 
                 pub fn len() -> usize {
@@ -49,7 +43,10 @@ macro_rules! define_tokens {
             Float(Float),
             Bool(Bool),
             String(String),
+            ByteString(ByteString),
             Char(Char),
+            LoopLabel(LoopLabel),
+            DocComment(DocComment),
             Group(Group),
            $(
                 $struct_name($struct_name)
@@ -64,7 +61,10 @@ macro_rules! define_tokens {
                     $token_enum::Float(i) => i.span.len(),
                     $token_enum::Bool(i) => i.span.len(),
                     $token_enum::String(i) => i.span.len(),
+                    $token_enum::ByteString(i) => i.span.len(),
                     $token_enum::Char(i) => i.span.len(),
+                    $token_enum::LoopLabel(i) => i.span.len(),
+                    $token_enum::DocComment(i) => i.span.len(),
                     $token_enum::Group(_) => 0,
                     $(
                         $token_enum::$struct_name(_) => $struct_name::len()
@@ -76,6 +76,25 @@ macro_rules! define_tokens {
                 self.len() == 0
             }
 
+            /// Mutable span of the token. Groups have two spans, so they return `None`.
+            pub(crate) fn span_mut(&mut self) -> Option<&mut $crate::Span> {
+                match self {
+                    $token_enum::Ident(i) => Some(&mut i.span),
+                    $token_enum::Int(i) => Some(&mut i.span),
+                    $token_enum::Float(i) => Some(&mut i.span),
+                    $token_enum::Bool(i) => Some(&mut i.span),
+                    $token_enum::String(i) => Some(&mut i.span),
+                    $token_enum::ByteString(i) => Some(&mut i.span),
+                    $token_enum::Char(i) => Some(&mut i.span),
+                    $token_enum::LoopLabel(i) => Some(&mut i.span),
+                    $token_enum::DocComment(i) => Some(&mut i.span),
+                    $token_enum::Group(_) => None,
+                    $(
+                        $token_enum::$struct_name(tok) => Some(&mut tok.0)
+                    ),*
+                }
+            }
+
             pub fn as_str(&self) -> &'static str {
                 match self {
                     $(
@@ -86,6 +105,26 @@ macro_rules! define_tokens {
             }
         }
 
+        impl $crate::AsSpan for $token_enum {
+            fn as_span(&self) -> $crate::Span {
+                match self {
+                    $token_enum::Ident(i) => i.span,
+                    $token_enum::Int(i) => i.span,
+                    $token_enum::Float(i) => i.span,
+                    $token_enum::Bool(i) => i.span,
+                    $token_enum::String(i) => i.span,
+                    $token_enum::ByteString(i) => i.span,
+                    $token_enum::Char(i) => i.span,
+                    $token_enum::LoopLabel(i) => i.span,
+                    $token_enum::DocComment(i) => i.span,
+                    $token_enum::Group(i) => $crate::AsSpan::as_span(i),
+                    $(
+                        $token_enum::$struct_name(tok) => tok.0
+                    ),*
+                }
+            }
+        }
+
         impl std::fmt::Display for $token_enum {
             fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
                 match self {
@@ -94,7 +133,10 @@ macro_rules! define_tokens {
                     $token_enum::Float(i) => std::fmt::Display::fmt(i, f),
                     $token_enum::Bool(i) => std::fmt::Display::fmt(i, f),
                     $token_enum::String(i) => std::fmt::Display::fmt(i, f),
+                    $token_enum::ByteString(i) => std::fmt::Display::fmt(i, f),
                     $token_enum::Char(i) => std::fmt::Display::fmt(i, f),
+                    $token_enum::LoopLabel(i) => std::fmt::Display::fmt(i, f),
+                    $token_enum::DocComment(i) => std::fmt::Display::fmt(i, f),
                     $token_enum::Group(i) => std::fmt::Display::fmt(i, f),
                     $(
                         $token_enum::$struct_name(tok) => std::fmt::Display::fmt(tok, f)
@@ -127,16 +169,10 @@ pub struct Ident {
 impl Ident {
     pub const NAME: &str = "Identifier";
 
-    pub fn from_span_start(raw: &[char], start: crate::Position) -> Token {
+    pub fn from_span_start(raw: &str, start: crate::Position) -> Token {
         Token::Ident(Ident {
-            value: raw.iter().collect(),
-            span: crate::Span {
-                end: crate::Position {
-                    line: start.line,
-                    column: start.column + raw.len(),
-                },
-                start,
-            },
+            value: raw.to_string(),
+            span: start.span_over(raw.chars()),
         })
     }
 }
@@ -147,69 +183,157 @@ impl Display for Ident {
     }
 }
 
+/// Type suffix of an integer literal (`10u8`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IntSuffix {
+    U8,
+    U16,
+    U32,
+    U64,
+    I8,
+    I16,
+    I32,
+    I64,
+}
+
+impl IntSuffix {
+    pub fn parse(suffix: &str) -> Option<IntSuffix> {
+        match suffix {
+            "u8" => Some(IntSuffix::U8),
+            "u16" => Some(IntSuffix::U16),
+            "u32" => Some(IntSuffix::U32),
+            "u64" => Some(IntSuffix::U64),
+            "i8" => Some(IntSuffix::I8),
+            "i16" => Some(IntSuffix::I16),
+            "i32" => Some(IntSuffix::I32),
+            "i64" => Some(IntSuffix::I64),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            IntSuffix::U8 => "u8",
+            IntSuffix::U16 => "u16",
+            IntSuffix::U32 => "u32",
+            IntSuffix::U64 => "u64",
+            IntSuffix::I8 => "i8",
+            IntSuffix::I16 => "i16",
+            IntSuffix::I32 => "i32",
+            IntSuffix::I64 => "i64",
+        }
+    }
+
+    /// Size of the type in bits
+    pub fn bits(self) -> u32 {
+        match self {
+            IntSuffix::U8 | IntSuffix::I8 => 8,
+            IntSuffix::U16 | IntSuffix::I16 => 16,
+            IntSuffix::U32 | IntSuffix::I32 => 32,
+            IntSuffix::U64 | IntSuffix::I64 => 64,
+        }
+    }
+
+    pub fn is_signed(self) -> bool {
+        matches!(
+            self,
+            IntSuffix::I8 | IntSuffix::I16 | IntSuffix::I32 | IntSuffix::I64
+        )
+    }
+
+    /// Largest value that fits in the type
+    pub fn max_value(self) -> u64 {
+        let bits = if self.is_signed() {
+            self.bits() - 1
+        } else {
+            self.bits()
+        };
+
+        u64::MAX >> (64 - bits)
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Int {
     pub value: u64,
+    pub suffix: Option<IntSuffix>,
     pub(crate) span: crate::Span,
 }
 
 impl Int {
     pub const NAME: &str = "Integer";
 
-    pub fn from_span_start(raw: &[char], start: crate::Position) -> Token {
+    pub fn from_span_start(raw: &str, start: crate::Position) -> Token {
         Token::Int(Int {
-            value: raw
-                .iter()
-                .collect::<std::string::String>()
-                .parse()
-                .expect("Unexpected value in int!"),
-            span: crate::Span {
-                end: crate::Position {
-                    line: start.line,
-                    column: start.column + raw.len(),
-                },
-                start,
-            },
+            value: raw.parse().expect("Unexpected value in int!"),
+            suffix: None,
+            span: start.span_over(raw.chars()),
         })
     }
 }
 
 impl Display for Int {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.value)
+        write!(f, "{}", self.value)?;
+        if let Some(suffix) = self.suffix {
+            write!(f, "{}", suffix.as_str())?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Type suffix of a float literal (`1.5f32`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FloatSuffix {
+    F32,
+    F64,
+}
+
+impl FloatSuffix {
+    pub fn parse(suffix: &str) -> Option<FloatSuffix> {
+        match suffix {
+            "f32" => Some(FloatSuffix::F32),
+            "f64" => Some(FloatSuffix::F64),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FloatSuffix::F32 => "f32",
+            FloatSuffix::F64 => "f64",
+        }
     }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Float {
     pub value: f64,
+    pub suffix: Option<FloatSuffix>,
     pub(crate) span: crate::Span,
 }
 
 impl Float {
     pub const NAME: &str = "Float";
 
-    pub fn from_span_start(raw: &[char], start: crate::Position) -> Token {
+    pub fn from_span_start(raw: &str, start: crate::Position) -> Token {
         Token::Float(Float {
-            value: raw
-                .iter()
-                .collect::<std::string::String>()
-                .parse()
-                .expect("Unexpected value in float!"),
-            span: crate::Span {
-                end: crate::Position {
-                    line: start.line,
-                    column: start.column + raw.len(),
-                },
-                start,
-            },
+            value: raw.parse().expect("Unexpected value in float!"),
+            suffix: None,
+            span: start.span_over(raw.chars()),
         })
     }
 }
 
 impl Display for Float {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.value)
+        write!(f, "{}", self.value)?;
+        if let Some(suffix) = self.suffix {
+            write!(f, "{}", suffix.as_str())?;
+        }
+
+        Ok(())
     }
 }
 
@@ -222,29 +346,17 @@ pub struct Bool {
 impl Bool {
     pub const NAME: &str = "Boolean";
 
-    pub fn from_value(value: bool, raw: &[char], start: crate::Position) -> Token {
+    pub fn from_value(value: bool, raw: &str, start: crate::Position) -> Token {
         Token::Bool(Bool {
             value,
-            span: crate::Span {
-                end: crate::Position {
-                    line: start.line,
-                    column: start.column + raw.len(),
-                },
-                start,
-            },
+            span: start.span_over(raw.chars()),
         })
     }
 
     pub fn from_span_start(raw: &str, start: crate::Position) -> Token {
         Token::Bool(Bool {
             value: raw.parse().expect("Unexpected value in bool!"),
-            span: crate::Span {
-                end: crate::Position {
-                    line: start.line,
-                    column: start.column + raw.len(),
-                },
-                start,
-            },
+            span: start.span_over(raw.chars()),
         })
     }
 }
@@ -267,20 +379,30 @@ impl String {
     pub fn from_span_start(raw: &str, start: crate::Position) -> Token {
         Token::String(String {
             value: raw[1..raw.len() - 1].to_string(),
-            span: crate::Span {
-                end: crate::Position {
-                    line: start.line,
-                    column: start.column + raw.len(),
-                },
-                start,
-            },
+            span: start.span_over(raw.chars()),
         })
     }
 }
 
 impl Display for String {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "\"{}\"", self.value)
+        write!(f, "\"{}\"", self.value.escape_debug())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ByteString {
+    pub value: Vec<u8>,
+    pub(crate) span: crate::Span,
+}
+
+impl ByteString {
+    pub const NAME: &str = "Byte String";
+}
+
+impl Display for ByteString {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "b\"{}\"", self.value.escape_ascii())
     }
 }
 
@@ -296,23 +418,65 @@ impl Char {
     pub fn from_span_start(raw: &str, start: crate::Position) -> Token {
         Token::Char(Char {
             value: raw[1..raw.len() - 1].to_string().chars().next().unwrap(),
-            span: crate::Span {
-                end: crate::Position {
-                    line: start.line,
-                    column: start.column + raw.len(),
-                },
-                start,
-            },
+            span: start.span_over(raw.chars()),
         })
     }
 }
 
 impl Display for Char {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "'{}'", self.value)
+        write!(f, "'{}'", self.value.escape_debug())
+    }
+}
+
+/// A loop label such as `'outer`. `value` is the name without the quote.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoopLabel {
+    pub value: std::string::String,
+    pub(crate) span: crate::Span,
+}
+
+impl LoopLabel {
+    pub const NAME: &str = "Loop Label";
+}
+
+impl Display for LoopLabel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "'{}", self.value)
+    }
+}
+
+/// A `///` comment. `value` is the text following the slashes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DocComment {
+    pub value: std::string::String,
+    pub(crate) span: crate::Span,
+}
+
+impl DocComment {
+    pub const NAME: &str = "Doc Comment";
+}
+
+impl Display for DocComment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "///{}", self.value)
     }
 }
 
+macro_rules! impl_data_span {
+    ($($name:ident),*) => {
+        $(
+            impl crate::AsSpan for $name {
+                fn as_span(&self) -> crate::Span {
+                    self.span
+                }
+            }
+        )*
+    };
+}
+
+impl_data_span!(Ident, Int, Float, Bool, String, ByteString, Char, LoopLabel, DocComment);
+
 define_tokens! {
     Tok;
     pub enum Token {
@@ -326,6 +490,7 @@ define_tokens! {
         External = external,
         For = for,
         If = if,
+        In = in,
         Match = match,
         Nones = none,
         Opaque = opaque,
@@ -341,14 +506,18 @@ define_tokens! {
         // Punctuation
         Semi = ;,
         Colon = :,
+        PathSep = ::,
         Comma = ,,
         Pound = #,
+        At = @,
+        Question = ?,
 
         // Operators
         And = &,
         AndEq = &=,
         Assign = =,
         Eq = ==,
+        FatArrow = =>,
         Dot = .,
         Gt = >,
         GtEq = >=,
@@ -375,6 +544,7 @@ define_tokens! {
         SlashEq = /=,
         Star = *,
         StarEq = *=,
+        Tilde = ~,
         Xor = ^,
         XorEq = ^=,
     }
@@ -410,6 +580,12 @@ impl Group {
     }
 }
 
+impl crate::AsSpan for Group {
+    fn as_span(&self) -> crate::Span {
+        self.open.join(self.close)
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum GroupBracket {
     Paren,
@@ -417,6 +593,24 @@ pub enum GroupBracket {
     Bracket,
 }
 
+impl GroupBracket {
+    pub fn open_char(self) -> char {
+        match self {
+            GroupBracket::Paren => '(',
+            GroupBracket::Brace => '{',
+            GroupBracket::Bracket => '[',
+        }
+    }
+
+    pub fn close_char(self) -> char {
+        match self {
+            GroupBracket::Paren => ')',
+            GroupBracket::Brace => '}',
+            GroupBracket::Bracket => ']',
+        }
+    }
+}
+
 impl Display for Group {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self.bracket {
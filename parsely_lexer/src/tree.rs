@@ -0,0 +1,196 @@
+use crate::{
+    tokens::{Group, GroupBracket, Token},
+    AsSpan, LexError, Span,
+};
+
+/// A token produced by the [`Lexer`](crate::Lexer) iterator
+///
+/// Brackets are produced as `Open` and `Close` tokens, the tokens between them aren't nested
+/// into a [`Group`] until the tree is built with [`build_tree`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum FlatToken {
+    Token(Token),
+    Open { bracket: GroupBracket, span: Span },
+    Close { bracket: GroupBracket, span: Span },
+}
+
+impl FlatToken {
+    pub(crate) fn span_mut(&mut self) -> &mut Span {
+        match self {
+            FlatToken::Token(token) => token.span_mut().expect("Flat tokens can't be groups"),
+            FlatToken::Open { span, .. } | FlatToken::Close { span, .. } => span,
+        }
+    }
+}
+
+impl AsSpan for FlatToken {
+    fn as_span(&self) -> Span {
+        match self {
+            FlatToken::Token(token) => token.as_span(),
+            FlatToken::Open { span, .. } | FlatToken::Close { span, .. } => *span,
+        }
+    }
+}
+
+/// A group that hasn't been closed yet
+struct OpenGroup {
+    bracket: GroupBracket,
+    span: Span,
+    /// Indentation of the line the group was opened on
+    indent: usize,
+    tokens: Vec<Token>,
+}
+
+impl OpenGroup {
+    fn close(self, close: Span) -> Token {
+        Token::Group(Group {
+            open: self.span,
+            close,
+            bracket: self.bracket,
+            tokens: self.tokens,
+        })
+    }
+
+    /// Closes a group that's missing its closing bracket, and reports it
+    ///
+    /// Indentation is used to guess where the bracket should have been. The group ends before
+    /// the first line that isn't indented more than the line it was opened on. The tokens from
+    /// there on are returned, and belong to the enclosing group.
+    fn close_unclosed(mut self, errors: &mut Vec<LexError>) -> (Token, Vec<Token>) {
+        let mut previous_line = self.span.end.line;
+        let split = self.tokens.iter().position(|token| {
+            let span = token.as_span();
+            let dedented = span.start.line > previous_line && span.start.column <= self.indent;
+            previous_line = span.end.line;
+            dedented
+        });
+
+        let rest = match split {
+            Some(split) => self.tokens.split_off(split),
+            None => Vec::new(),
+        };
+
+        let end = self
+            .tokens
+            .last()
+            .map(|token| token.as_span().end)
+            .unwrap_or(self.span.end);
+        let expected = end.to_span(0);
+
+        errors.push(LexError::UnclosedDelimiter {
+            bracket: self.bracket,
+            span: self.span,
+            expected,
+        });
+
+        (self.close(expected), rest)
+    }
+}
+
+/// Adds a finished token to the innermost open group, or the top level
+fn push_token(open: &mut [OpenGroup], tokens_out: &mut Vec<Token>, token: Token) {
+    match open.last_mut() {
+        Some(group) => group.tokens.push(token),
+        None => tokens_out.push(token),
+    }
+}
+
+/// Closes every group above `depth`, reporting each one as unclosed
+fn close_above(
+    open: &mut Vec<OpenGroup>,
+    depth: usize,
+    tokens_out: &mut Vec<Token>,
+    errors: &mut Vec<LexError>,
+) {
+    while open.len() > depth {
+        let group = open.pop().unwrap();
+        let (group, rest) = group.close_unclosed(errors);
+
+        push_token(open, tokens_out, group);
+        for token in rest {
+            push_token(open, tokens_out, token);
+        }
+    }
+}
+
+/// Builds the nested token tree from flat tokens
+///
+/// Brackets that don't match up are reported to `errors`, and
+/// indentation is used to guess which brackets are missing, so one typo doesn't break up the
+/// rest of the file:
+///
+/// - A closing bracket at the start of a line closes the innermost matching group that was opened
+///   on a line indented no more than it. Any groups inside that one are missing their closing
+///   bracket.
+/// - Groups that aren't closed end before the first line that's indented no more than the line
+///   they were opened on.
+/// - Closing brackets that don't match any open group are skipped.
+pub fn build_tree(
+    tokens: impl IntoIterator<Item = FlatToken>,
+    errors: &mut Vec<LexError>,
+) -> Vec<Token> {
+    let mut open: Vec<OpenGroup> = Vec::new();
+    let mut tokens_out = Vec::new();
+
+    // Indentation of the current line, which is the column of its first token
+    let mut indent = 0;
+    let mut last_line = None;
+
+    for token in tokens {
+        let span = token.as_span();
+        let line_start = last_line != Some(span.start.line);
+        if line_start {
+            indent = span.start.column;
+        }
+        last_line = Some(span.end.line);
+
+        match token {
+            FlatToken::Token(token) => push_token(&mut open, &mut tokens_out, token),
+            FlatToken::Open { bracket, span } => open.push(OpenGroup {
+                bracket,
+                span,
+                indent,
+                tokens: Vec::new(),
+            }),
+            FlatToken::Close { bracket, span } => {
+                let mut matching = open
+                    .iter()
+                    .enumerate()
+                    .rev()
+                    .filter(|(_, group)| group.bracket == bracket);
+
+                let target = if line_start {
+                    matching
+                        .clone()
+                        .find(|(_, group)| group.indent <= span.start.column)
+                        .or_else(|| matching.next())
+                } else {
+                    matching.next()
+                };
+
+                match (target, open.last()) {
+                    (Some((depth, _)), _) => {
+                        close_above(&mut open, depth + 1, &mut tokens_out, errors);
+
+                        let group = open.pop().unwrap().close(span);
+                        push_token(&mut open, &mut tokens_out, group);
+                    }
+                    (None, Some(innermost)) => errors.push(LexError::MismatchedDelimiter {
+                        found: bracket,
+                        span,
+                        open: innermost.bracket,
+                        open_span: innermost.span,
+                    }),
+                    (None, None) => errors.push(LexError::UnexpectedDelimiter {
+                        found: bracket,
+                        span,
+                    }),
+                }
+            }
+        }
+    }
+
+    close_above(&mut open, 0, &mut tokens_out, errors);
+
+    tokens_out
+}
@@ -1,24 +1,35 @@
-use parsely_lexer::tokens::{self, Group, GroupBracket, Token};
+use parsely_lexer::{
+    tokens::{self, Group, GroupBracket, Token},
+    AsSpan, Span,
+};
 
-use crate::{Brackets, Parens, Parse, ParseError, ParseStream, Punctuation, Result};
+use crate::{
+    pattern::Match,
+    statement::{IfStatement, Statement},
+    Braces, Brackets, Parens, Parse, ParseError, ParseStream, Punctuation, Result,
+};
 
 #[derive(Debug, Clone)]
 pub enum Literal {
     Int(LiteralInt),
     Float(LiteralFloat),
     String(LiteralString),
+    ByteString(LiteralByteString),
+    Char(LiteralChar),
+    /// `none`, an optional without a value
+    None(tokens::Tok![none]),
 }
 
 impl Parse for Literal {
     fn parse(stream: &'_ crate::ParseStream<'_>) -> crate::Result<Self> {
-        match stream.peek()? {
-            Token::Int(_) => Ok(Literal::Int(stream.parse()?)),
-            Token::Float(_) => Ok(Literal::Float(stream.parse()?)),
-            Token::String(_) => Ok(Literal::String(stream.parse()?)),
-            tok => Err(ParseError::UnexpectedToken {
-                found: tok.clone(),
-                expected: tokens::Int::NAME.to_string(),
-            }),
+        match stream.peek() {
+            Ok(Token::Int(_)) => Ok(Literal::Int(stream.parse()?)),
+            Ok(Token::Float(_)) => Ok(Literal::Float(stream.parse()?)),
+            Ok(Token::String(_)) => Ok(Literal::String(stream.parse()?)),
+            Ok(Token::ByteString(_)) => Ok(Literal::ByteString(stream.parse()?)),
+            Ok(Token::Char(_)) => Ok(Literal::Char(stream.parse()?)),
+            Ok(tokens::Tok![enum none]) => Ok(Literal::None(stream.parse()?)),
+            _ => Err(stream.unexpected("Literal")),
         }
     }
 }
@@ -30,14 +41,11 @@ pub struct LiteralInt {
 
 impl Parse for LiteralInt {
     fn parse(stream: &'_ crate::ParseStream<'_>) -> crate::Result<Self> {
-        match stream.peek()? {
-            Token::Int(i) => Ok(LiteralInt {
+        match stream.peek() {
+            Ok(Token::Int(i)) => Ok(LiteralInt {
                 value: stream.next_ref(i),
             }),
-            tok => Err(ParseError::UnexpectedToken {
-                found: tok.clone(),
-                expected: tokens::Int::NAME.to_string(),
-            }),
+            _ => Err(stream.unexpected(tokens::Int::NAME)),
         }
     }
 }
@@ -49,14 +57,11 @@ pub struct LiteralFloat {
 
 impl Parse for LiteralFloat {
     fn parse(stream: &'_ crate::ParseStream<'_>) -> crate::Result<Self> {
-        match stream.peek()? {
-            Token::Float(i) => Ok(LiteralFloat {
+        match stream.peek() {
+            Ok(Token::Float(i)) => Ok(LiteralFloat {
                 value: stream.next_ref(i),
             }),
-            tok => Err(ParseError::UnexpectedToken {
-                found: tok.clone(),
-                expected: tokens::Int::NAME.to_string(),
-            }),
+            _ => Err(stream.unexpected(tokens::Float::NAME)),
         }
     }
 }
@@ -68,14 +73,43 @@ pub struct LiteralString {
 
 impl Parse for LiteralString {
     fn parse(stream: &'_ crate::ParseStream<'_>) -> crate::Result<Self> {
-        match stream.peek()? {
-            Token::String(i) => Ok(LiteralString {
+        match stream.peek() {
+            Ok(Token::String(i)) => Ok(LiteralString {
                 value: stream.next_ref(i),
             }),
-            tok => Err(ParseError::UnexpectedToken {
-                found: tok.clone(),
-                expected: tokens::Int::NAME.to_string(),
+            _ => Err(stream.unexpected(tokens::String::NAME)),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LiteralByteString {
+    pub value: tokens::ByteString,
+}
+
+impl Parse for LiteralByteString {
+    fn parse(stream: &'_ crate::ParseStream<'_>) -> crate::Result<Self> {
+        match stream.peek() {
+            Ok(Token::ByteString(i)) => Ok(LiteralByteString {
+                value: stream.next_ref(i),
+            }),
+            _ => Err(stream.unexpected(tokens::ByteString::NAME)),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LiteralChar {
+    pub value: tokens::Char,
+}
+
+impl Parse for LiteralChar {
+    fn parse(stream: &'_ crate::ParseStream<'_>) -> crate::Result<Self> {
+        match stream.peek() {
+            Ok(Token::Char(i)) => Ok(LiteralChar {
+                value: stream.next_ref(i),
             }),
+            _ => Err(stream.unexpected(tokens::Char::NAME)),
         }
     }
 }
@@ -87,58 +121,147 @@ pub enum Expression {
     ArrayInit(ArrayInit),
     Parens(Parens<Expression>),
     BinOp(BinOp),
+    Assign(Assign),
     Index(Index),
     Slice(Slice),
     Call(Call),
+    Field(FieldAccess),
+    StructLiteral(StructLiteral),
+    Match(Match),
+    /// An `if` with a value, which is the value of the branch that ran
+    If(Box<IfStatement>),
+    /// A block, whose value is its last expression
+    Block(Braces<Vec<Statement>>),
+    Unary(UnaryOp),
+    AddressOf(AddressOf),
+    Deref(Deref),
+}
+
+impl AsSpan for Literal {
+    fn as_span(&self) -> Span {
+        match self {
+            Literal::Int(i) => i.value.as_span(),
+            Literal::Float(f) => f.value.as_span(),
+            Literal::String(s) => s.value.as_span(),
+            Literal::ByteString(s) => s.value.as_span(),
+            Literal::Char(c) => c.value.as_span(),
+            Literal::None(n) => n.as_span(),
+        }
+    }
+}
+
+impl AsSpan for Expression {
+    fn as_span(&self) -> Span {
+        match self {
+            Expression::Literal(lit) => lit.as_span(),
+            Expression::Ident(ident) => ident.as_span(),
+            Expression::ArrayInit(a) => a.elements.as_span(),
+            Expression::Parens(p) => p.as_span(),
+            Expression::BinOp(b) => b.left.as_span().join(b.right.as_span()),
+            Expression::Assign(a) => a.target.as_span().join(a.value.as_span()),
+            Expression::Index(i) => i.expr.as_span().join(i.index.as_span()),
+            Expression::Slice(s) => s.expr.as_span().join(s.range.as_span()),
+            Expression::Call(c) => c.as_span(),
+            Expression::Field(f) => f.expr.as_span().join(f.member.as_span()),
+            Expression::StructLiteral(s) => s.as_span(),
+            Expression::Match(m) => m.as_span(),
+            Expression::If(i) => i.as_span(),
+            Expression::Block(b) => b.as_span(),
+            Expression::Unary(u) => u.op.as_span().join(u.expr.as_span()),
+            Expression::AddressOf(a) => a.token.as_span().join(a.expr.as_span()),
+            Expression::Deref(d) => d.star.as_span().join(d.expr.as_span()),
+        }
+    }
 }
 
 impl Expression {
+    /// Returns true if the expression refers to somewhere a value is stored, so it can be
+    /// assigned to
+    pub fn is_place(&self) -> bool {
+        match self {
+            Expression::Ident(_)
+            | Expression::Index(_)
+            | Expression::Field(_)
+            | Expression::Deref(_) => true,
+            Expression::Parens(p) => p.value.is_place(),
+            _ => false,
+        }
+    }
+
+    /// Returns true if the expression ends with a block, so it doesn't need a `;` as a statement
+    pub fn ends_with_block(&self) -> bool {
+        matches!(
+            self,
+            Expression::If(_) | Expression::Block(_) | Expression::Match(_)
+        )
+    }
+
     fn parse_primary_expression(stream: &'_ ParseStream<'_>) -> Result<Expression> {
         match stream.peek()? {
             Token::Group(tokens::Group {
                 bracket: tokens::GroupBracket::Paren,
                 ..
-            }) => stream.parse().map(|parens| Expression::Parens(parens)),
+            }) => stream.parse().map(Expression::Parens),
             Token::Group(tokens::Group {
                 bracket: tokens::GroupBracket::Bracket,
                 ..
-            }) => stream.parse().map(|array| Expression::ArrayInit(array)),
+            }) => stream.parse().map(Expression::ArrayInit),
+            Token::Group(tokens::Group {
+                bracket: tokens::GroupBracket::Brace,
+                ..
+            }) => Braces::parse_with(stream, Statement::parse_list).map(Expression::Block),
+            Token::Ident(_)
+                if stream.allows_struct_literals()
+                    && matches!(
+                        stream.peekn(1),
+                        Ok(Token::Group(Group {
+                            bracket: GroupBracket::Brace,
+                            ..
+                        }))
+                    ) =>
+            {
+                stream.parse().map(Expression::StructLiteral)
+            }
             Token::Ident(ident) => Ok(Expression::Ident(stream.next_ref(ident))),
-            _ => stream.parse().map(|tok| Expression::Literal(tok)),
+            tokens::Tok![enum match] => stream.parse().map(Expression::Match),
+            tokens::Tok![enum if] => stream.parse().map(|i| Expression::If(Box::new(i))),
+            _ => stream.parse().map(Expression::Literal),
         }
     }
-}
 
-impl Parse for Expression {
-    fn parse(stream: &'_ ParseStream<'_>) -> Result<Self> {
-        BinOp::parse_binop(stream, 0)
+    /// Parses prefix operators, which bind tighter than any binary operator but looser than
+    /// indexing and calls, so `-x[0]` negates the element
+    fn parse_prefix_expression(stream: &'_ ParseStream<'_>) -> Result<Expression> {
+        match stream.peek()? {
+            tokens::Tok![enum -] | tokens::Tok![enum !] | tokens::Tok![enum ~] => {
+                Ok(Expression::Unary(UnaryOp {
+                    op: stream.next(),
+                    expr: Box::new(Expression::parse_prefix_expression(stream)?),
+                }))
+            }
+            tokens::Tok![enum @] | tokens::Tok![enum #] => Ok(Expression::AddressOf(AddressOf {
+                token: stream.next(),
+                expr: Box::new(Expression::parse_prefix_expression(stream)?),
+            })),
+            tokens::Tok![enum *] => Ok(Expression::Deref(Deref {
+                star: stream.parse()?,
+                expr: Box::new(Expression::parse_prefix_expression(stream)?),
+            })),
+            _ => Expression::parse_postfix_expression(stream),
+        }
     }
-}
 
-#[derive(Debug, Clone)]
-pub struct BinOp {
-    pub left: Box<Expression>,
-    pub op: tokens::Token,
-    pub right: Box<Expression>,
-}
+    /// Parses a primary expression followed by any number of indices, slices, calls and field
+    /// accesses. A method call `a.f(x)` is a call of the field access `a.f`.
+    fn parse_postfix_expression(stream: &'_ ParseStream<'_>) -> Result<Expression> {
+        let mut expr = Expression::parse_primary_expression(stream)?;
 
-enum IndexOrSlice {
-    Index(Box<Expression>),
-    Slice(Range),
-}
-
-impl BinOp {
-    fn parse_binop(stream: &'_ ParseStream<'_>, last_prec: usize) -> Result<Expression> {
-        let mut left = Expression::parse_primary_expression(stream)?;
-
-        while stream.has_next() {
-            let op = stream.peek()?;
-
-            match op {
-                Token::Group(Group {
+        loop {
+            match stream.peek() {
+                Ok(Token::Group(Group {
                     bracket: GroupBracket::Bracket,
                     ..
-                }) => {
+                })) => {
                     let ios = stream.parse_with(|stream| {
                         Brackets::parse_with(stream, |stream| match stream.peek()? {
                             tokens::Tok![enum ..] => Ok(IndexOrSlice::Slice(Range::parse(stream)?)),
@@ -156,42 +279,70 @@ impl BinOp {
                         })
                     })?;
 
-                    match *ios.value {
-                        IndexOrSlice::Index(value) => {
-                            left = Expression::Index(Index {
-                                expr: Box::new(left),
-                                index: Brackets {
-                                    parens: ios.parens,
-                                    value,
-                                },
-                            })
-                        }
-                        IndexOrSlice::Slice(s) => {
-                            left = Expression::Slice(Slice {
-                                expr: Box::new(left),
-                                range: Brackets {
-                                    parens: ios.parens,
-                                    value: Box::new(s),
-                                },
-                            })
-                        }
-                    }
-
-                    continue;
+                    expr = match *ios.value {
+                        IndexOrSlice::Index(value) => Expression::Index(Index {
+                            expr: Box::new(expr),
+                            index: Brackets {
+                                parens: ios.parens,
+                                value,
+                            },
+                        }),
+                        IndexOrSlice::Slice(s) => Expression::Slice(Slice {
+                            expr: Box::new(expr),
+                            range: Brackets {
+                                parens: ios.parens,
+                                value: Box::new(s),
+                            },
+                        }),
+                    };
                 }
-                Token::Group(Group {
+                Ok(Token::Group(Group {
                     bracket: GroupBracket::Paren,
                     ..
-                }) => {
-                    left = Expression::Call(Call {
-                        expr: Box::new(left),
+                })) => {
+                    expr = Expression::Call(Call {
+                        expr: Box::new(expr),
                         args: stream.parse()?,
                     });
                 }
-                _ => (),
+                Ok(tokens::Tok![enum .]) => {
+                    expr = Expression::Field(FieldAccess {
+                        expr: Box::new(expr),
+                        dot: stream.parse()?,
+                        member: stream.parse()?,
+                    });
+                }
+                _ => return Ok(expr),
             }
+        }
+    }
+}
+
+impl Parse for Expression {
+    fn parse(stream: &'_ ParseStream<'_>) -> Result<Self> {
+        BinOp::parse_binop(stream, 0)
+    }
+}
 
-            let prec = BinOp::precedence(&op);
+#[derive(Debug, Clone)]
+pub struct BinOp {
+    pub left: Box<Expression>,
+    pub op: tokens::Token,
+    pub right: Box<Expression>,
+}
+
+enum IndexOrSlice {
+    Index(Box<Expression>),
+    Slice(Range),
+}
+
+impl BinOp {
+    fn parse_binop(stream: &'_ ParseStream<'_>, last_prec: usize) -> Result<Expression> {
+        let mut left = Expression::parse_prefix_expression(stream)?;
+
+        while stream.has_next() {
+            let op = stream.peek()?;
+            let prec = BinOp::precedence(op);
 
             if prec < last_prec || prec == 0 {
                 break;
@@ -199,7 +350,29 @@ impl BinOp {
 
             let op = stream.next();
 
-            let right = BinOp::parse_binop(stream, prec)?;
+            if Assign::is_assignment(&op) {
+                if !left.is_place() {
+                    let error = ParseError::InvalidAssignment {
+                        span: left.as_span(),
+                        labels: Vec::new(),
+                    };
+                    return Err(error.with_label(op.as_span(), "assignment here"));
+                }
+
+                // Assignments associate to the right, so the value takes any assignment after it
+                let value = BinOp::parse_binop(stream, prec)?;
+
+                left = Expression::Assign(Assign {
+                    target: Box::new(left),
+                    op,
+                    value: Box::new(value),
+                });
+                continue;
+            }
+
+            // Operators of the same precedence on the right are left for this loop, so they
+            // associate to the left
+            let right = BinOp::parse_binop(stream, prec + 1)?;
 
             left = Expression::BinOp(BinOp {
                 left: Box::new(left),
@@ -208,55 +381,6 @@ impl BinOp {
             });
         }
 
-        // while stream.has_next() {
-        //     match stream.peek() {
-        //         Ok(Token::Group(Group {
-        //             bracket: GroupBracket::Bracket,
-        //             ..
-        //         })) => {
-        //             let ios = stream.parse_with(|stream| {
-        //                 Brackets::parse_with(stream, |stream| match stream.peek()? {
-        //                     tokens::Tok![enum ..] => Ok(IndexOrSlice::Slice(Range::parse(stream)?)),
-        //                     _ => {
-        //                         let left = stream.parse()?;
-        //                         match stream.peek() {
-        //                             Ok(tokens::Tok![enum ..]) => Ok(IndexOrSlice::Slice(Range {
-        //                                 left: Some(Box::new(left)),
-        //                                 token: stream.parse()?,
-        //                                 right: stream.parse()?,
-        //                             })),
-        //                             _ => Ok(IndexOrSlice::Index(Box::new(left))),
-        //                         }
-        //                     }
-        //                 })
-        //             })?;
-
-        //             match *ios.value {
-        //                 IndexOrSlice::Index(value) => {
-        //                     left = Expression::Index(Index {
-        //                         expr: Box::new(left),
-        //                         index: Brackets {
-        //                             parens: ios.parens,
-        //                             value,
-        //                         },
-        //                     })
-        //                 }
-        //                 IndexOrSlice::Slice(s) => {
-        //                     left = Expression::Slice(Slice {
-        //                         expr: Box::new(left),
-        //                         range: Brackets {
-        //                             parens: ios.parens,
-        //                             value: Box::new(s),
-        //                         },
-        //                     })
-        //                 }
-        //             }
-        //         }
-
-        //         _ => break,
-        //     }
-        // }
-
         Ok(left)
     }
 
@@ -291,6 +415,65 @@ impl BinOp {
     }
 }
 
+/// `target = value`, or a compound assignment like `target += value`
+#[derive(Debug, Clone)]
+pub struct Assign {
+    pub target: Box<Expression>,
+    pub op: tokens::Token,
+    pub value: Box<Expression>,
+}
+
+impl Assign {
+    pub fn is_assignment(op: &tokens::Token) -> bool {
+        matches!(
+            op,
+            tokens::Tok![enum =]
+                | tokens::Tok![enum +=]
+                | tokens::Tok![enum -=]
+                | tokens::Tok![enum *=]
+                | tokens::Tok![enum /=]
+                | tokens::Tok![enum %=]
+                | tokens::Tok![enum <<=]
+                | tokens::Tok![enum >>=]
+                | tokens::Tok![enum &=]
+                | tokens::Tok![enum ^=]
+                | tokens::Tok![enum |=]
+        )
+    }
+
+    /// Returns true for `+=` and the other operators that also read the target
+    pub fn is_compound(&self) -> bool {
+        !matches!(self.op, tokens::Tok![enum =])
+    }
+}
+
+/// `-expr`, `!expr` or `~expr`
+#[derive(Debug, Clone)]
+pub struct UnaryOp {
+    pub op: tokens::Token,
+    pub expr: Box<Expression>,
+}
+
+/// `@expr`, a pointer to a place. `#expr` gives a pointer that can be written through.
+#[derive(Debug, Clone)]
+pub struct AddressOf {
+    pub token: tokens::Token,
+    pub expr: Box<Expression>,
+}
+
+impl AddressOf {
+    pub fn is_mutable(&self) -> bool {
+        matches!(self.token, tokens::Tok![enum #])
+    }
+}
+
+/// `*expr`, the value a pointer points to
+#[derive(Debug, Clone)]
+pub struct Deref {
+    pub star: tokens::Tok![*],
+    pub expr: Box<Expression>,
+}
+
 #[derive(Debug, Clone)]
 pub struct ArrayInit {
     pub elements: Brackets<Punctuation<Expression, tokens::Tok![,]>>,
@@ -301,12 +484,9 @@ impl ArrayInit {
         let mut v = vec![self.elements.value.len()];
 
         for f in self.elements.value.iter() {
-            match f {
-                Expression::ArrayInit(a) => {
-                    v.extend(a.dimensions());
-                    break;
-                }
-                _ => (),
+            if let Expression::ArrayInit(a) = f {
+                v.extend(a.dimensions());
+                break;
             }
         }
 
@@ -357,31 +537,262 @@ pub struct Call {
     pub args: Parens<Punctuation<Expression, tokens::Tok![,]>>,
 }
 
+impl AsSpan for Call {
+    fn as_span(&self) -> Span {
+        self.expr.as_span().join(self.args.as_span())
+    }
+}
+
+/// `expr.field`, or `expr.0` for an element of a tuple
+#[derive(Debug, Clone)]
+pub struct FieldAccess {
+    pub expr: Box<Expression>,
+    pub dot: tokens::Tok![.],
+    pub member: Member,
+}
+
+#[derive(Debug, Clone)]
+pub enum Member {
+    Named(tokens::Ident),
+    Index(LiteralInt),
+}
+
+impl Parse for Member {
+    fn parse(stream: &'_ ParseStream<'_>) -> Result<Self> {
+        match stream.peek() {
+            Ok(Token::Int(_)) => stream.parse().map(Member::Index),
+            _ => stream.parse().map(Member::Named),
+        }
+    }
+}
+
+impl AsSpan for Member {
+    fn as_span(&self) -> Span {
+        match self {
+            Member::Named(ident) => ident.as_span(),
+            Member::Index(i) => i.value.as_span(),
+        }
+    }
+}
+
+/// `Point { x: 1, y: 2 }`
+///
+/// Not allowed in the condition of an `if`, `while`, `for` or `match`, where the braces are the
+/// body instead.
+#[derive(Debug, Clone)]
+pub struct StructLiteral {
+    pub ident: tokens::Ident,
+    pub fields: Braces<Punctuation<FieldInit, tokens::Tok![,]>>,
+}
+
+impl AsSpan for StructLiteral {
+    fn as_span(&self) -> Span {
+        self.ident.as_span().join(self.fields.as_span())
+    }
+}
+
+impl Parse for StructLiteral {
+    fn parse(stream: &'_ ParseStream<'_>) -> Result<Self> {
+        Ok(StructLiteral {
+            ident: stream.parse()?,
+            fields: stream.parse()?,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FieldInit {
+    pub ident: tokens::Ident,
+    pub colon: tokens::Tok![:],
+    pub value: Expression,
+}
+
+impl Parse for FieldInit {
+    fn parse(stream: &'_ ParseStream<'_>) -> Result<Self> {
+        Ok(FieldInit {
+            ident: stream.parse()?,
+            colon: stream.parse()?,
+            value: stream.parse()?,
+        })
+    }
+}
+
 #[cfg(test)]
 mod test {
     use parsely_lexer::Lexer;
 
+    use crate::pattern::{ArmBody, Pattern};
+
     use super::*;
 
     #[test]
     fn test_basic() {
         let input = "4 + 8 * 6 * 3";
-        let tokens = Lexer::run(input.as_bytes());
+        let (tokens, _) = Lexer::run(input.as_bytes());
         let stream = ParseStream::from(&tokens);
 
         let expr: Expression = stream.parse().expect("Parse error!");
-
-        // println!("{:#?}", expr);
+        assert!(matches!(
+            expr,
+            Expression::BinOp(BinOp {
+                op: tokens::Tok![enum +],
+                ..
+            })
+        ));
     }
 
     #[test]
     fn test_prec() {
         let input = "4 + 8 * 6 / 2 % 3 - 7 + (3 + 4) * 2";
-        let tokens = Lexer::run(input.as_bytes());
+        let (tokens, _) = Lexer::run(input.as_bytes());
         let stream = ParseStream::from(&tokens);
 
         let expr: Expression = stream.parse().expect("Parse error!");
+        assert!(matches!(
+            expr,
+            Expression::BinOp(BinOp {
+                op: tokens::Tok![enum +],
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_unary() {
+        let input = "-x[0] * !f(y) + ~*p - @a";
+        let (tokens, _) = Lexer::run(input.as_bytes());
+        let stream = ParseStream::from(&tokens);
+
+        let expr: Expression = stream.parse().expect("Parse error!");
+
+        // ((-x[0] * !f(y)) + ~*p) - @a
+        let Expression::BinOp(sub) = expr else {
+            panic!("Expected a subtraction!");
+        };
+        assert!(matches!(*sub.right, Expression::AddressOf(_)));
+
+        let Expression::BinOp(add) = *sub.left else {
+            panic!("Expected an addition!");
+        };
+        let Expression::Unary(not) = *add.right else {
+            panic!("Expected a bitwise not!");
+        };
+        assert!(matches!(not.op, tokens::Tok![enum ~]));
+        assert!(matches!(*not.expr, Expression::Deref(_)));
+
+        let Expression::BinOp(mul) = *add.left else {
+            panic!("Expected a multiplication!");
+        };
+        let (Expression::Unary(neg), Expression::Unary(not)) = (*mul.left, *mul.right) else {
+            panic!("Expected unary operators on both sides!");
+        };
+        assert!(matches!(*neg.expr, Expression::Index(_)));
+        assert!(matches!(*not.expr, Expression::Call(_)));
+    }
+
+    #[test]
+    fn test_assignment() {
+        let input = "a = p.x += b * 2";
+        let (tokens, _) = Lexer::run(input.as_bytes());
+        let stream = ParseStream::from(&tokens);
+
+        // a = (p.x += (b * 2))
+        let Expression::Assign(assign) = stream.parse().expect("Parse error!") else {
+            panic!("Expected an assignment!");
+        };
+        assert!(!assign.is_compound());
+        assert!(matches!(*assign.target, Expression::Ident(_)));
+
+        let Expression::Assign(inner) = *assign.value else {
+            panic!("Expected a nested assignment!");
+        };
+        assert!(inner.is_compound());
+        assert!(matches!(*inner.target, Expression::Field(_)));
+        assert!(matches!(*inner.value, Expression::BinOp(_)));
+
+        for input in ["1 = x", "a + b = c", "f(x) -= 1"] {
+            let (tokens, _) = Lexer::run(input.as_bytes());
+            let stream = ParseStream::from(&tokens);
+
+            assert!(matches!(
+                stream.parse::<Expression>(),
+                Err(ParseError::InvalidAssignment { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_match() {
+        let input = r"match shape {
+    0 => a,
+    1..5 if big => b,
+    Shape::Circle(r, _) => {
+        draw(r);
+    }
+    Point { x, y: 0 } => c,
+    _ => d,
+}";
+        let (tokens, _) = Lexer::run(input.as_bytes());
+        let stream = ParseStream::from(&tokens);
+
+        let Expression::Match(m) = stream.parse().expect("Parse error!") else {
+            panic!("Expected match!");
+        };
+
+        let arms = m.arms.value.as_slice();
+        assert_eq!(arms.len(), 5);
+        assert!(matches!(arms[0].pattern, Pattern::Int(_)));
+        assert!(matches!(arms[1].pattern, Pattern::Range(_)));
+        assert!(arms[1].guard.is_some());
+
+        let Pattern::Variant(variant) = &arms[2].pattern else {
+            panic!("Expected variant pattern!");
+        };
+        assert_eq!(variant.variant.value, "Circle");
+        assert!(matches!(
+            variant
+                .fields
+                .as_ref()
+                .unwrap()
+                .value
+                .iter()
+                .collect::<Vec<_>>()[..],
+            [Pattern::Binding(_), Pattern::Wildcard(_)]
+        ));
+        assert!(matches!(arms[2].body, ArmBody::Block(_)));
+
+        assert!(matches!(arms[3].pattern, Pattern::Struct(_)));
+        assert!(matches!(arms[4].pattern, Pattern::Wildcard(_)));
+    }
+
+    #[test]
+    fn test_negative_patterns() {
+        let input = "match x { -5..-1 => a, -1 => b, 0..3 => c, _ => d }";
+        let (tokens, _) = Lexer::run(input.as_bytes());
+        let stream = ParseStream::from(&tokens);
+
+        let Expression::Match(m) = stream.parse().expect("Parse error!") else {
+            panic!("Expected match!");
+        };
+
+        let arms = m.arms.value.as_slice();
+        let Pattern::Range(range) = &arms[0].pattern else {
+            panic!("Expected range pattern!");
+        };
+        assert_eq!((range.start.value(), range.end.value()), (-5, -1));
+        assert!(matches!(&arms[1].pattern, Pattern::Int(i) if i.value() == -1));
+        assert!(matches!(&arms[2].pattern, Pattern::Range(r) if r.start.minus.is_none()));
+    }
+
+    #[test]
+    fn test_none() {
+        let input = "x != none";
+        let (tokens, _) = Lexer::run(input.as_bytes());
+        let stream = ParseStream::from(&tokens);
 
-        // println!("{:#?}", expr);
+        let Expression::BinOp(b) = stream.parse().expect("Parse error!") else {
+            panic!("Expected a comparison!");
+        };
+        assert!(matches!(*b.right, Expression::Literal(Literal::None(_))));
     }
 }
@@ -1,28 +1,73 @@
-use parsely_lexer::tokens::{self};
+use parsely_lexer::{
+    tokens::{self, Group, GroupBracket, Token},
+    Span,
+};
 
-use crate::{statement::{Statement, ArrayDimension}, types::Type, Braces, Parens, Parse, Punctuation};
+use crate::{
+    pattern::PatternInt,
+    statement::{ArrayDimension, Statement},
+    types::{Type, TypeInt},
+    Braces, Parens, Parse, ParseError, ParseStream, Punctuation,
+};
+
+/// Parses any doc comments preceding an item
+fn parse_docs(stream: &'_ ParseStream<'_>) -> Vec<tokens::DocComment> {
+    let mut docs = Vec::new();
+    while let Ok(Token::DocComment(doc)) = stream.peek() {
+        docs.push(stream.next_ref(doc));
+    }
+
+    docs
+}
+
+/// Number of doc comment tokens starting at the current token
+fn doc_count(stream: &'_ ParseStream<'_>) -> usize {
+    let mut count = 0;
+    while let Ok(Token::DocComment(_)) = stream.peekn(count) {
+        count += 1;
+    }
+
+    count
+}
 
 #[derive(Debug, Clone)]
 pub enum TopLevelItem {
     Function(Function),
     ExternalFunction(ExternalFunction),
     Struct(Struct),
+    Enum(Enum),
+    TypeAlias(TypeAlias),
+    /// An item that failed to parse. The error is reported to the stream.
+    Error(Span),
 }
 
 impl Parse for TopLevelItem {
     fn parse(stream: &'_ crate::ParseStream<'_>) -> crate::Result<Self> {
-        match stream.peek()? {
-            tokens::Tok![enum export] => match stream.peekn(1)? {
+        // Doc comments are parsed by the item itself
+        let docs = doc_count(stream);
+
+        match stream.peekn(docs)? {
+            tokens::Tok![enum export] => match stream.peekn(docs + 1)? {
                 tokens::Tok![enum opaque]
                 | tokens::Tok![enum packed]
                 | tokens::Tok![enum struct] => stream.parse().map(TopLevelItem::Struct),
                 tokens::Tok![enum external] => stream.parse().map(TopLevelItem::ExternalFunction),
+                tokens::Tok![enum enum] => stream.parse().map(TopLevelItem::Enum),
+                tokens::Tok![enum typedef] => stream.parse().map(TopLevelItem::TypeAlias),
+                _ if TypeAlias::starts_with_type(stream, docs + 1) => {
+                    stream.parse().map(TopLevelItem::TypeAlias)
+                }
                 _ => stream.parse().map(TopLevelItem::Function),
             },
             tokens::Tok![enum opaque] | tokens::Tok![enum packed] | tokens::Tok![enum struct] => {
                 stream.parse().map(TopLevelItem::Struct)
             }
             tokens::Tok![enum external] => stream.parse().map(TopLevelItem::ExternalFunction),
+            tokens::Tok![enum enum] => stream.parse().map(TopLevelItem::Enum),
+            tokens::Tok![enum typedef] => stream.parse().map(TopLevelItem::TypeAlias),
+            _ if TypeAlias::starts_with_type(stream, docs) => {
+                stream.parse().map(TopLevelItem::TypeAlias)
+            }
             _ => stream.parse().map(TopLevelItem::Function),
         }
     }
@@ -30,6 +75,7 @@ impl Parse for TopLevelItem {
 
 #[derive(Debug, Clone)]
 pub struct Struct {
+    pub docs: Vec<tokens::DocComment>,
     pub export: Option<tokens::Tok![export]>,
     pub opaque: Option<tokens::Tok![opaque]>,
     pub packed: Option<tokens::Tok![packed]>,
@@ -41,6 +87,7 @@ pub struct Struct {
 impl Parse for Struct {
     fn parse(stream: &'_ crate::ParseStream<'_>) -> crate::Result<Self> {
         Ok(Struct {
+            docs: parse_docs(stream),
             export: stream.parse()?,
             opaque: stream.parse()?,
             packed: stream.parse()?,
@@ -51,8 +98,205 @@ impl Parse for Struct {
     }
 }
 
+/// `enum Name: int8 { A = 1, B(int32, int32), C }`
+///
+/// Variants without a discriminant are numbered from the one before them, like in C. If any
+/// variant has a payload, the enum is a tagged union. The tag type defaults to `int32`.
+#[derive(Debug, Clone)]
+pub struct Enum {
+    pub docs: Vec<tokens::DocComment>,
+    pub export: Option<tokens::Tok![export]>,
+    pub keyword: tokens::Tok![enum],
+    pub ident: tokens::Ident,
+    pub tag: Option<EnumTag>,
+    pub variants: Braces<Punctuation<Variant, tokens::Tok![,]>>,
+}
+
+impl Enum {
+    pub fn is_tagged_union(&self) -> bool {
+        self.variants.value.iter().any(|v| v.fields.is_some())
+    }
+
+    /// The discriminant of each variant, in order
+    ///
+    /// Discriminants are at most 64 bits, so counting on from one can't overflow. Whether they
+    /// fit the tag is up to code generation.
+    pub fn discriminants(&self) -> Vec<i128> {
+        let mut next = 0;
+        self.variants
+            .value
+            .iter()
+            .map(|variant| {
+                let value = variant
+                    .discriminant
+                    .as_ref()
+                    .map_or(next, |d| d.value.value());
+                next = value + 1;
+                value
+            })
+            .collect()
+    }
+}
+
+impl Parse for Enum {
+    fn parse(stream: &'_ crate::ParseStream<'_>) -> crate::Result<Self> {
+        Ok(Enum {
+            docs: parse_docs(stream),
+            export: stream.parse()?,
+            keyword: stream.parse()?,
+            ident: stream.parse()?,
+            tag: match stream.peek()? {
+                tokens::Tok![enum :] => Some(stream.parse()?),
+                _ => None,
+            },
+            variants: stream.parse()?,
+        })
+    }
+}
+
+/// `: int8`, the integer type of an enum's discriminant
+#[derive(Debug, Clone)]
+pub struct EnumTag {
+    pub colon: tokens::Tok![:],
+    pub ty: TypeInt,
+}
+
+impl Parse for EnumTag {
+    fn parse(stream: &'_ crate::ParseStream<'_>) -> crate::Result<Self> {
+        Ok(EnumTag {
+            colon: stream.parse()?,
+            ty: stream.parse()?,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Variant {
+    pub docs: Vec<tokens::DocComment>,
+    pub ident: tokens::Ident,
+    /// Types of the payload
+    pub fields: Option<Parens<Punctuation<Type, tokens::Tok![,]>>>,
+    pub discriminant: Option<Discriminant>,
+}
+
+impl Parse for Variant {
+    fn parse(stream: &'_ crate::ParseStream<'_>) -> crate::Result<Self> {
+        Ok(Variant {
+            docs: parse_docs(stream),
+            ident: stream.parse()?,
+            fields: match stream.peek() {
+                Ok(Token::Group(Group {
+                    bracket: GroupBracket::Paren,
+                    ..
+                })) => Some(stream.parse()?),
+                _ => None,
+            },
+            discriminant: match stream.peek() {
+                Ok(tokens::Tok![enum =]) => Some(stream.parse()?),
+                _ => None,
+            },
+        })
+    }
+}
+
+/// `= 3` or `= -1` after a variant
+#[derive(Debug, Clone)]
+pub struct Discriminant {
+    pub eq: tokens::Tok![=],
+    pub value: PatternInt,
+}
+
+impl Parse for Discriminant {
+    fn parse(stream: &'_ crate::ParseStream<'_>) -> crate::Result<Self> {
+        Ok(Discriminant {
+            eq: stream.parse()?,
+            value: stream.parse()?,
+        })
+    }
+}
+
+/// `typedef Name = Type;` or `type Name = Type;`
+///
+/// A plain alias is just another name for the type. `typedef distinct Name = Type;` declares a
+/// new type with the same representation, which doesn't mix with other named types.
+#[derive(Debug, Clone)]
+pub struct TypeAlias {
+    pub docs: Vec<tokens::DocComment>,
+    pub export: Option<tokens::Tok![export]>,
+    pub keyword: AliasKeyword,
+    /// The contextual keyword `distinct`
+    pub distinct: Option<tokens::Ident>,
+    pub ident: tokens::Ident,
+    pub eq: tokens::Tok![=],
+    pub ty: Box<Type>,
+    pub semi: tokens::Tok![;],
+}
+
+impl TypeAlias {
+    /// Returns true if the token `n` ahead starts an alias with `type`, which is only a keyword
+    /// when a name and `=` or `distinct` follow it
+    fn starts_with_type(stream: &'_ ParseStream<'_>, n: usize) -> bool {
+        match (stream.peekn(n), stream.peekn(n + 1), stream.peekn(n + 2)) {
+            (
+                Ok(Token::Ident(keyword)),
+                Ok(Token::Ident(_)),
+                Ok(tokens::Tok![enum =] | Token::Ident(_)),
+            ) => keyword.value == "type",
+            _ => false,
+        }
+    }
+}
+
+impl Parse for TypeAlias {
+    fn parse(stream: &'_ crate::ParseStream<'_>) -> crate::Result<Self> {
+        let docs = parse_docs(stream);
+        let export = stream.parse()?;
+        let keyword = stream.parse()?;
+
+        // `distinct` is only a keyword when a name follows it
+        let distinct = match (stream.peek()?, stream.peekn(1)) {
+            (Token::Ident(ident), Ok(Token::Ident(_))) if ident.value == "distinct" => {
+                Some(stream.next_ref(ident))
+            }
+            _ => None,
+        };
+
+        Ok(TypeAlias {
+            docs,
+            export,
+            keyword,
+            distinct,
+            ident: stream.parse()?,
+            eq: stream.parse()?,
+            ty: stream.parse()?,
+            semi: stream.parse()?,
+        })
+    }
+}
+
+/// The keyword of a type alias. Both mean the same.
+#[derive(Debug, Clone)]
+pub enum AliasKeyword {
+    Typedef(tokens::Tok![typedef]),
+    /// The contextual keyword `type`
+    Type(tokens::Ident),
+}
+
+impl Parse for AliasKeyword {
+    fn parse(stream: &'_ crate::ParseStream<'_>) -> crate::Result<Self> {
+        match stream.peek()? {
+            tokens::Tok![enum typedef] => stream.parse().map(AliasKeyword::Typedef),
+            Token::Ident(ident) if ident.value == "type" => {
+                Ok(AliasKeyword::Type(stream.next_ref(ident)))
+            }
+            _ => Err(stream.unexpected(tokens::Typedef::NAME)),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Parameter {
+    pub docs: Vec<tokens::DocComment>,
     pub parameter_type: Box<Type>,
     pub ident: tokens::Ident,
     pub arrays: Vec<ArrayDimension>,
@@ -60,16 +304,31 @@ pub struct Parameter {
 
 impl Parse for Parameter {
     fn parse(stream: &'_ crate::ParseStream<'_>) -> crate::Result<Self> {
+        let docs = parse_docs(stream);
+        let parameter_type = stream.parse()?;
+        let ident = stream.parse()?;
+
+        let mut arrays = Vec::new();
+        while let Ok(Token::Group(Group {
+            bracket: GroupBracket::Bracket,
+            ..
+        })) = stream.peek()
+        {
+            arrays.push(stream.parse()?);
+        }
+
         Ok(Parameter {
-            parameter_type: stream.parse()?,
-            ident: stream.parse()?,
-            arrays: stream.parse()?,
+            docs,
+            parameter_type,
+            ident,
+            arrays,
         })
     }
 }
 
 #[derive(Debug, Clone)]
 pub struct Function {
+    pub docs: Vec<tokens::DocComment>,
     pub export: Option<tokens::Tok![export]>,
     pub external: Option<tokens::Tok![external]>,
     pub return_type: Box<Type>,
@@ -81,18 +340,20 @@ pub struct Function {
 impl Parse for Function {
     fn parse(stream: &'_ crate::ParseStream<'_>) -> crate::Result<Self> {
         Ok(Function {
+            docs: parse_docs(stream),
             export: stream.parse()?,
             external: stream.parse()?,
             return_type: stream.parse()?,
             ident: stream.parse()?,
             params: stream.parse()?,
-            body: stream.parse()?,
+            body: Braces::parse_with(stream, Statement::parse_list)?,
         })
     }
 }
 
 #[derive(Debug, Clone)]
 pub struct ExternalFunction {
+    pub docs: Vec<tokens::DocComment>,
     pub export: Option<tokens::Tok![export]>,
     pub external: tokens::Tok![external],
     pub return_type: Box<Type>,
@@ -104,6 +365,7 @@ pub struct ExternalFunction {
 impl Parse for ExternalFunction {
     fn parse(stream: &'_ crate::ParseStream<'_>) -> crate::Result<Self> {
         Ok(ExternalFunction {
+            docs: parse_docs(stream),
             export: stream.parse()?,
             external: stream.parse()?,
             return_type: stream.parse()?,
@@ -119,11 +381,52 @@ pub struct Program {
     pub items: Vec<TopLevelItem>,
 }
 
+impl Program {
+    /// Parses a whole program, recovering from errors
+    ///
+    /// Returns every error in the program, along with the parts that could be parsed. Items and
+    /// statements that failed to parse are `Error` nodes.
+    pub fn parse_all(stream: &'_ ParseStream<'_>) -> (Program, Vec<ParseError>) {
+        let mut items = Vec::new();
+
+        while stream.has_next() && !stream.skip_dangling_docs() {
+            let start = stream.position();
+
+            match stream.parse() {
+                Ok(item) => items.push(item),
+                Err(error) => {
+                    stream.report(error);
+                    let span = stream.recover(start, |token| {
+                        matches!(
+                            token,
+                            tokens::Tok![enum export]
+                                | tokens::Tok![enum external]
+                                | tokens::Tok![enum opaque]
+                                | tokens::Tok![enum packed]
+                                | tokens::Tok![enum struct]
+                                | tokens::Tok![enum enum]
+                                | tokens::Tok![enum typedef]
+                                | Token::DocComment(_)
+                        )
+                    });
+                    items.push(TopLevelItem::Error(span));
+                }
+            }
+        }
+
+        (Program { items }, stream.take_errors())
+    }
+}
+
 impl Parse for Program {
+    /// Parses a whole program, failing with the first error. See [`Program::parse_all`].
     fn parse(stream: &'_ crate::ParseStream<'_>) -> crate::Result<Self> {
-        Ok(Program {
-            items: stream.parse()?,
-        })
+        let (program, errors) = Program::parse_all(stream);
+
+        match errors.into_iter().next() {
+            Some(error) => Err(error),
+            None => Ok(program),
+        }
     }
 }
 
@@ -131,7 +434,12 @@ impl Parse for Program {
 mod test {
     use parsely_lexer::Lexer;
 
-    use crate::ParseStream;
+    use crate::{
+        expression::Expression,
+        statement::{ContinueStatement, ElseBody, ElseBranch, ForIter},
+        types::TypeInt,
+        ParseStream,
+    };
 
     use super::*;
 
@@ -142,10 +450,526 @@ export struct data {
     int32 d;
 }
 ";
-        let tokens = Lexer::run(input.as_bytes());
+        let (tokens, _) = Lexer::run(input.as_bytes());
         let stream = ParseStream::from(&tokens);
 
         let tli: TopLevelItem = stream.parse().unwrap();
         println!("{:#?}", tli);
     }
+
+    #[test]
+    fn test_doc_comments() {
+        let input = r"
+/// A point
+/* not a doc comment */
+struct point {
+    /// Horizontal
+    int32 x;
+    // Not a doc comment either
+    int32 y;
+}
+
+/// Entry point
+//// Four slashes is a regular comment
+int32 main(/// Argument count
+int32 argc) {
+    /// Ignored in statement position
+    return argc;
+}
+";
+        let (tokens, _) = Lexer::run(input.as_bytes());
+        let stream = ParseStream::from(&tokens);
+
+        let program: Program = stream.parse().unwrap();
+
+        let TopLevelItem::Struct(s) = &program.items[0] else {
+            panic!("Expected struct!");
+        };
+        assert_eq!(s.docs.len(), 1);
+        assert_eq!(s.docs[0].value, " A point");
+
+        let fields: Vec<_> = s.body.value.iter().collect();
+        assert_eq!(fields[0].docs[0].value, " Horizontal");
+        assert!(fields[1].docs.is_empty());
+
+        let TopLevelItem::Function(f) = &program.items[1] else {
+            panic!("Expected function!");
+        };
+        assert_eq!(f.docs.len(), 1);
+        assert_eq!(f.docs[0].value, " Entry point");
+        assert_eq!(
+            f.params.value.iter().next().unwrap().docs[0].value,
+            " Argument count"
+        );
+    }
+
+    #[test]
+    fn test_trailing_doc_comment() {
+        let input = "void f() { x; /// trailing
+}";
+        let (tokens, _) = Lexer::run(input.as_bytes());
+        let stream = ParseStream::from(&tokens);
+
+        let program: Program = stream.parse().unwrap();
+        let [TopLevelItem::Function(f)] = program.items.as_slice() else {
+            panic!("Expected a function!");
+        };
+        assert!(matches!(
+            f.body.value.as_slice(),
+            [Statement::Expression(_)]
+        ));
+
+        // Nor after the last field of a struct, the last variant of an enum, or the last item
+        let input = "struct P { int32 x; /// c
+}
+enum E { A, /// c
+}
+/// c";
+        let (tokens, _) = Lexer::run(input.as_bytes());
+        let stream = ParseStream::from(&tokens);
+
+        let (program, errors) = Program::parse_all(&stream);
+        assert!(errors.is_empty(), "{:?}", errors);
+        let [TopLevelItem::Struct(s), TopLevelItem::Enum(e)] = program.items.as_slice() else {
+            panic!("Expected a struct and an enum!");
+        };
+        assert_eq!(s.body.value.len(), 1);
+        assert_eq!(e.variants.value.len(), 1);
+    }
+
+    #[test]
+    fn test_int_types() {
+        let input = "struct S { int8 a; int b; integer c; }";
+        let (tokens, _) = Lexer::run(input.as_bytes());
+        let stream = ParseStream::from(&tokens);
+
+        let program: Program = stream.parse().unwrap();
+        let TopLevelItem::Struct(s) = &program.items[0] else {
+            panic!("Expected struct!");
+        };
+        let types: Vec<_> = s.body.value.iter().map(|f| &*f.parameter_type).collect();
+
+        assert!(matches!(types[0], Type::Int(TypeInt { size: 8, .. })));
+        // Only `int` followed by a size is an int type
+        assert!(matches!(types[1], Type::Named(ident) if ident.value == "int"));
+        assert!(matches!(types[2], Type::Named(ident) if ident.value == "integer"));
+    }
+
+    #[test]
+    fn test_recovery() {
+        let input = r"
+int32 main() {
+    int32 x = ;
+    foo(1, 2);
+    return x +;
+}
+
+struct broken int32 x;
+
+export int32 other() {
+    return 1;
+}
+";
+        let (tokens, _) = Lexer::run(input.as_bytes());
+        let stream = ParseStream::from(&tokens);
+
+        let (program, errors) = Program::parse_all(&stream);
+        assert_eq!(errors.len(), 3);
+        assert_eq!(program.items.len(), 3);
+
+        let TopLevelItem::Function(main) = &program.items[0] else {
+            panic!("Expected function!");
+        };
+        assert!(matches!(
+            main.body.value.as_slice(),
+            [
+                Statement::Error(_),
+                Statement::Expression(_),
+                Statement::Error(_)
+            ]
+        ));
+
+        let TopLevelItem::Error(span) = &program.items[1] else {
+            panic!("Expected error!");
+        };
+        assert_eq!(span.start.line, 7);
+        assert!(matches!(program.items[2], TopLevelItem::Function(_)));
+
+        assert!(ParseStream::from(&tokens).parse::<Program>().is_err());
+    }
+
+    #[test]
+    fn test_else_chain() {
+        let input = r"
+int32 sign(int32 x) {
+    if x < 0 {
+        return 0 - 1;
+    } else if x > 0 {
+        return 1;
+    } else {
+        return 0;
+    }
+}
+";
+        let (tokens, _) = Lexer::run(input.as_bytes());
+        let stream = ParseStream::from(&tokens);
+
+        let program: Program = stream.parse().unwrap();
+        let TopLevelItem::Function(f) = &program.items[0] else {
+            panic!("Expected function!");
+        };
+        let [Statement::IfStatement(stmt)] = f.body.value.as_slice() else {
+            panic!("Expected a single if statement!");
+        };
+
+        let Some(ElseBranch {
+            body: ElseBody::If(else_if),
+            ..
+        }) = &stmt.else_branch
+        else {
+            panic!("Expected else if!");
+        };
+        assert!(matches!(
+            else_if.else_branch,
+            Some(ElseBranch {
+                body: ElseBody::Block(_),
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_for_loops() {
+        let input = r"
+void main(int32 xs[], int32 n) {
+    for i in 0..n {
+        print(i);
+    }
+    for x in xs {
+        print(x);
+    }
+}
+";
+        let (tokens, _) = Lexer::run(input.as_bytes());
+        let stream = ParseStream::from(&tokens);
+
+        let program: Program = stream.parse().unwrap();
+        let TopLevelItem::Function(f) = &program.items[0] else {
+            panic!("Expected function!");
+        };
+        let [Statement::ForLoop(range), Statement::ForLoop(slice)] = f.body.value.as_slice() else {
+            panic!("Expected two for loops!");
+        };
+
+        assert_eq!(range.ident.value, "i");
+        assert!(matches!(
+            &range.iter,
+            ForIter::Range(r) if r.left.is_some() && r.right.is_some()
+        ));
+        assert_eq!(slice.ident.value, "x");
+        assert!(matches!(slice.iter, ForIter::Slice(_)));
+    }
+
+    #[test]
+    fn test_loop_labels() {
+        let input = r"
+void main(int32 n) {
+    'outer: while n > 0 {
+        for i in 0..n {
+            if i == 3 {
+                continue 'outer;
+            }
+            break;
+        }
+    }
+}
+";
+        let (tokens, _) = Lexer::run(input.as_bytes());
+        let stream = ParseStream::from(&tokens);
+
+        let program: Program = stream.parse().unwrap();
+        let TopLevelItem::Function(f) = &program.items[0] else {
+            panic!("Expected function!");
+        };
+        let [Statement::WhileLoop(outer)] = f.body.value.as_slice() else {
+            panic!("Expected a while loop!");
+        };
+        assert_eq!(outer.label.as_ref().unwrap().name.value, "outer");
+
+        let [Statement::ForLoop(inner)] = outer.body.value.as_slice() else {
+            panic!("Expected a for loop!");
+        };
+        assert!(inner.label.is_none());
+
+        let [Statement::IfStatement(stmt), Statement::Break(brk)] = inner.body.value.as_slice()
+        else {
+            panic!("Expected an if statement and a break!");
+        };
+        assert!(brk.label.is_none());
+        let [Statement::Continue(ContinueStatement {
+            label: Some(label), ..
+        })] = stmt.body.value.as_slice()
+        else {
+            panic!("Expected a labelled continue!");
+        };
+        assert_eq!(label.value, "outer");
+    }
+
+    #[test]
+    fn test_enums() {
+        let input = r"
+enum Color { Red = 1, Green, Blue = 10 }
+enum Sign { Negative = -1, Zero, Positive }
+
+/// A shape
+export enum Shape: int8 {
+    Circle(int32),
+    Rect(int32, int32),
+    Empty,
+}
+";
+        let (tokens, _) = Lexer::run(input.as_bytes());
+        let stream = ParseStream::from(&tokens);
+
+        let program: Program = stream.parse().unwrap();
+        let [TopLevelItem::Enum(color), TopLevelItem::Enum(sign), TopLevelItem::Enum(shape)] =
+            program.items.as_slice()
+        else {
+            panic!("Expected three enums!");
+        };
+
+        assert!(!color.is_tagged_union());
+        assert!(color.tag.is_none());
+        assert_eq!(color.discriminants(), [1, 2, 10]);
+        assert_eq!(sign.discriminants(), [-1, 0, 1]);
+
+        assert!(shape.is_tagged_union());
+        assert!(shape.export.is_some());
+        assert_eq!(shape.tag.as_ref().unwrap().ty.size, 8);
+        assert_eq!(shape.discriminants(), [0, 1, 2]);
+
+        let fields: Vec<_> = shape
+            .variants
+            .value
+            .iter()
+            .map(|v| v.fields.as_ref().map_or(0, |f| f.value.len()))
+            .collect();
+        assert_eq!(fields, [1, 2, 0]);
+    }
+
+    #[test]
+    fn test_enum_short_tag() {
+        // Tag types shorter than `int` are an error, not a panic
+        for input in ["enum Shape: u8 { A, B }", "enum Shape: i { A, B }"] {
+            let (tokens, _) = Lexer::run(input.as_bytes());
+            let error = ParseStream::from(&tokens).parse::<Program>().unwrap_err();
+            assert!(
+                matches!(&error, ParseError::UnexpectedToken { expected, .. } if expected.iter().any(|e| e == "int")),
+                "{:?}",
+                error
+            );
+        }
+    }
+
+    #[test]
+    fn test_type_aliases() {
+        let input = r"
+typedef Int = int32;
+export typedef distinct Meters = int64;
+typedef distinct = int8;
+type Index = int64;
+export type distinct Id = int32;
+type f() {}
+";
+        let (tokens, _) = Lexer::run(input.as_bytes());
+        let stream = ParseStream::from(&tokens);
+
+        let program: Program = stream.parse().unwrap();
+        let aliases: Vec<_> = program
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                TopLevelItem::TypeAlias(alias) => Some(alias),
+                _ => None,
+            })
+            .collect();
+        let [int, meters, named, index, id] = aliases.as_slice() else {
+            panic!("Expected five type aliases!");
+        };
+
+        assert_eq!(int.ident.value, "Int");
+        assert!(int.distinct.is_none());
+        assert!(matches!(*int.ty, Type::Int(TypeInt { size: 32, .. })));
+
+        assert_eq!(meters.ident.value, "Meters");
+        assert!(meters.export.is_some() && meters.distinct.is_some());
+
+        // `distinct` can still be used as a name
+        assert_eq!(named.ident.value, "distinct");
+        assert!(named.distinct.is_none());
+
+        assert!(matches!(index.keyword, AliasKeyword::Type(_)));
+        assert!(index.distinct.is_none());
+        assert_eq!(id.ident.value, "Id");
+        assert!(id.export.is_some() && id.distinct.is_some());
+
+        // `type` is still a name when it's a return type
+        assert!(matches!(program.items[5], TopLevelItem::Function(_)));
+    }
+
+    #[test]
+    fn test_pointer_types() {
+        let input = r"
+void main() {
+    int32 x = 1;
+    #int32 p = #x;
+    @int32? q;
+    [int32, @int64] pair;
+    int32 y = *p;
+    *p;
+}
+";
+        let (tokens, _) = Lexer::run(input.as_bytes());
+        let stream = ParseStream::from(&tokens);
+
+        let program: Program = stream.parse().unwrap();
+        let TopLevelItem::Function(f) = &program.items[0] else {
+            panic!("Expected function!");
+        };
+        let decl = |i: usize| match &f.body.value[i] {
+            Statement::VariableDeclaration(decl) => decl,
+            _ => panic!("Expected a declaration!"),
+        };
+        let (p, q, pair, y) = (decl(1), decl(2), decl(3), decl(4));
+        let Statement::Expression(deref) = &f.body.value[5] else {
+            panic!("Expected an expression!");
+        };
+
+        let Type::Pointer(ptr) = &*p.ty else {
+            panic!("Expected a pointer!");
+        };
+        assert!(ptr.is_mutable());
+        assert!(matches!(
+            p.init.as_ref().map(|i| &*i.expression),
+            Some(Expression::AddressOf(a)) if a.is_mutable()
+        ));
+
+        // The pointer is outside of the optional
+        let Type::Pointer(ptr) = &*q.ty else {
+            panic!("Expected a pointer!");
+        };
+        assert!(!ptr.is_mutable());
+        assert!(matches!(*ptr.pointee, Type::Optional(_)));
+
+        let Type::Tuple(fields) = &*pair.ty else {
+            panic!("Expected a tuple!");
+        };
+        assert_eq!(fields.value.len(), 2);
+
+        assert!(matches!(
+            y.init.as_ref().map(|i| &*i.expression),
+            Some(Expression::Deref(_))
+        ));
+        assert!(matches!(*deref.expression, Expression::Deref(_)));
+    }
+
+    #[test]
+    fn test_struct_literals() {
+        let input = r"
+void main(Point q) {
+    Point p = Point { x: 1, y: q.items[0].len(q.n) };
+    if p.x {
+        p.y = 2;
+    }
+}
+";
+        let (tokens, _) = Lexer::run(input.as_bytes());
+        let stream = ParseStream::from(&tokens);
+
+        let program: Program = stream.parse().unwrap();
+        let TopLevelItem::Function(f) = &program.items[0] else {
+            panic!("Expected function!");
+        };
+        let [Statement::VariableDeclaration(p), Statement::IfStatement(stmt)] =
+            f.body.value.as_slice()
+        else {
+            panic!("Expected a declaration and an if statement!");
+        };
+
+        let Some(Expression::StructLiteral(lit)) = p.init.as_ref().map(|i| &*i.expression) else {
+            panic!("Expected a struct literal!");
+        };
+        assert_eq!(lit.ident.value, "Point");
+        let fields: Vec<_> = lit.fields.value.iter().collect();
+        assert_eq!(fields.len(), 2);
+
+        // q.items[0].len(q.n) calls the field `len` of an element of `q.items`
+        let Expression::Call(call) = &fields[1].value else {
+            panic!("Expected a method call!");
+        };
+        let Expression::Field(method) = &*call.expr else {
+            panic!("Expected a field access!");
+        };
+        assert!(matches!(*method.expr, Expression::Index(_)));
+
+        // The braces after the condition are the body, not a struct literal
+        assert!(matches!(*stmt.condition, Expression::Field(_)));
+        assert_eq!(stmt.body.value.len(), 1);
+    }
+
+    #[test]
+    fn test_if_expressions() {
+        let input = r"
+int32 pick(int32 c, int32 d) {
+    int32 x = if c { 1 } else if d { 2 } else { 3 };
+    int32 y = {
+        int32 z = x * 2;
+        z + 1
+    };
+    {
+        x += 1;
+    }
+    if c { x } else { y }
+}
+";
+        let (tokens, _) = Lexer::run(input.as_bytes());
+        let stream = ParseStream::from(&tokens);
+
+        let program: Program = stream.parse().unwrap();
+        assert!(stream.take_errors().is_empty());
+        let TopLevelItem::Function(f) = &program.items[0] else {
+            panic!("Expected function!");
+        };
+        let decl = |i: usize| match &f.body.value[i] {
+            Statement::VariableDeclaration(decl) => decl,
+            _ => panic!("Expected a declaration!"),
+        };
+        let (x, y) = (decl(0), decl(1));
+        let [.., Statement::Expression(block), last] = f.body.value.as_slice() else {
+            panic!("Expected a block and a final statement!");
+        };
+
+        let Some(Expression::If(stmt)) = x.init.as_ref().map(|i| &*i.expression) else {
+            panic!("Expected an if expression!");
+        };
+        assert!(stmt.body.value[0].has_value());
+        assert!(matches!(
+            stmt.else_branch,
+            Some(ElseBranch {
+                body: ElseBody::If(_),
+                ..
+            })
+        ));
+
+        let Some(Expression::Block(body)) = y.init.as_ref().map(|i| &*i.expression) else {
+            panic!("Expected a block expression!");
+        };
+        assert!(!body.value[0].has_value());
+        assert!(body.value[1].has_value());
+
+        // A block statement doesn't need a `;`, and has no value if it isn't last
+        assert!(matches!(*block.expression, Expression::Block(_)));
+        assert!(matches!(last, Statement::IfStatement(_)));
+        assert!(last.has_value());
+    }
 }
@@ -1,9 +1,14 @@
-use std::{cell::Cell, fmt::Display};
+use std::{
+    cell::{Cell, RefCell},
+    fmt::Display,
+    rc::Rc,
+};
 
-use parsely_lexer::tokens::Token;
+use parsely_lexer::{tokens::Token, AsSpan, Label, Report, SourceDatabase, Span};
 
 pub mod expression;
 pub mod item;
+pub mod pattern;
 pub mod statement;
 mod tokens;
 pub mod types;
@@ -13,32 +18,118 @@ pub mod types;
 pub enum ParseError {
     /// Encountered an unexpected token while parsing
     UnexpectedToken {
-        /// Found token 'found'
-        found: Token,
-        /// Expected this token. This is a string to support tokens with data values.
-        expected: String,
+        /// Found token 'found'. Boxed so every `Result` returned by the parser stays small.
+        found: Box<Token>,
+        /// Expected one of these tokens. These are strings to support tokens with data values.
+        expected: Vec<String>,
+        /// Related places in the source
+        labels: Vec<Label>,
     },
-    /// No more input (EOF)
+    /// No more input (EOF, or the end of a group)
     UnexpectedEnd {
-        // /// Expected this token. This is a string to support tokens with data values.
-        // expected: String,
+        /// End of the input, or the closing bracket of the group
+        span: Span,
+        /// Expected one of these tokens
+        expected: Vec<String>,
+        /// Related places in the source, such as the opening bracket of the group
+        labels: Vec<Label>,
     },
     /// Encountered an unexpected size (such as in int32 size)
-    UnexpectedSize { found: String },
+    UnexpectedSize {
+        found: String,
+        span: Span,
+        labels: Vec<Label>,
+    },
+    /// The left side of an assignment isn't something that can be assigned to
+    InvalidAssignment { span: Span, labels: Vec<Label> },
+}
+
+/// Formats a list of expected tokens as "`a`, `b` or `c`"
+fn expected_list(expected: &[String]) -> String {
+    match expected {
+        [] => String::new(),
+        [only] => format!("`{}`", only),
+        [rest @ .., last] => format!("`{}` or `{}`", rest.join("`, `"), last),
+    }
 }
 
 impl Display for ParseError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            ParseError::UnexpectedSize { found } => {
-                write!(f, "Expected size `{}`", found)
+            ParseError::UnexpectedSize { found, .. } => {
+                write!(f, "Invalid size `{}`", found)
+            }
+            ParseError::InvalidAssignment { .. } => {
+                write!(f, "Can't assign to this expression")
             }
-            ParseError::UnexpectedEnd {} => {
-                write!(f, "Unexpected end of input!")
+            ParseError::UnexpectedEnd { expected, .. } if expected.is_empty() => {
+                write!(f, "Unexpected end of input")
             }
-            ParseError::UnexpectedToken { found, expected } => {
-                write!(f, "Expected token `{}`, found `{}`", found, expected)
+            ParseError::UnexpectedEnd { expected, .. } => {
+                write!(
+                    f,
+                    "Expected {}, found end of input",
+                    expected_list(expected)
+                )
             }
+            ParseError::UnexpectedToken {
+                found, expected, ..
+            } if expected.is_empty() => {
+                write!(f, "Unexpected token `{}`", found)
+            }
+            ParseError::UnexpectedToken {
+                found, expected, ..
+            } => {
+                write!(f, "Expected {}, found `{}`", expected_list(expected), found)
+            }
+        }
+    }
+}
+
+impl ParseError {
+    /// Tokens that would have been accepted instead
+    pub fn expected(&self) -> &[String] {
+        match self {
+            ParseError::UnexpectedToken { expected, .. } => expected,
+            ParseError::UnexpectedEnd { expected, .. } => expected,
+            ParseError::UnexpectedSize { .. } | ParseError::InvalidAssignment { .. } => &[],
+        }
+    }
+
+    pub fn labels(&self) -> &[Label] {
+        match self {
+            ParseError::UnexpectedToken { labels, .. } => labels,
+            ParseError::UnexpectedEnd { labels, .. } => labels,
+            ParseError::UnexpectedSize { labels, .. } => labels,
+            ParseError::InvalidAssignment { labels, .. } => labels,
+        }
+    }
+
+    /// Add a secondary label, such as "opening brace here"
+    pub fn with_label(mut self, span: Span, message: impl Into<String>) -> ParseError {
+        let labels = match &mut self {
+            ParseError::UnexpectedToken { labels, .. } => labels,
+            ParseError::UnexpectedEnd { labels, .. } => labels,
+            ParseError::UnexpectedSize { labels, .. } => labels,
+            ParseError::InvalidAssignment { labels, .. } => labels,
+        };
+        labels.push(Label::new(span, message));
+        self
+    }
+
+    /// Report for rendering the error with the source it points to
+    pub fn to_report(&self) -> Report {
+        Report::error(self.to_string(), self.as_span()).with_labels(self.labels().iter().cloned())
+    }
+}
+
+impl AsSpan for ParseError {
+    fn as_span(&self) -> Span {
+        match self {
+            ParseError::UnexpectedToken { found, .. } => found.as_span(),
+            ParseError::UnexpectedEnd { span, .. } => *span,
+            ParseError::UnexpectedSize { span, .. } => *span,
+            ParseError::InvalidAssignment { span, .. } => *span,
         }
     }
 }
@@ -60,6 +151,89 @@ pub struct ParseStream<'a> {
     buffer: &'a [Token],
     /// Current index into token buffer
     index: Cell<usize>,
+    /// Source files the tokens came from, if known
+    source: Option<&'a SourceDatabase>,
+    /// Errors that were recovered from. Shared with child streams.
+    errors: Rc<RefCell<Vec<ParseError>>>,
+    /// Alternatives that were tried and failed at a token index. See [`ParseStream::unexpected`].
+    tried: RefCell<(usize, Vec<String>)>,
+    /// Where the input ends. For a group this is the closing bracket.
+    end: Span,
+    /// Opening bracket, if this stream is over the tokens of a group
+    open: Option<Span>,
+    /// False while parsing an expression that a block follows, like an `if` condition
+    struct_literals: Cell<bool>,
+}
+
+/// Empty span just after the last token
+fn end_of(buffer: &[Token]) -> Span {
+    buffer
+        .last()
+        .map(|token| token.as_span().end.to_span(0))
+        .unwrap_or_default()
+}
+
+/// A saved position in a [`ParseStream`]
+#[derive(Debug, Clone, Copy)]
+pub struct Checkpoint {
+    index: usize,
+    errors: usize,
+}
+
+impl<'a> ParseStream<'a> {
+    /// Create a stream at the start of `buffer`, with no errors yet
+    fn new(buffer: &'a [Token], source: Option<&'a SourceDatabase>) -> ParseStream<'a> {
+        ParseStream {
+            buffer,
+            index: Cell::new(0),
+            source,
+            errors: Default::default(),
+            tried: Default::default(),
+            end: end_of(buffer),
+            open: None,
+            struct_literals: Cell::new(true),
+        }
+    }
+
+    /// Create a stream over `buffer` that knows about the files the tokens came from
+    pub fn with_source(buffer: &'a [Token], source: &'a SourceDatabase) -> ParseStream<'a> {
+        ParseStream::new(buffer, Some(source))
+    }
+
+    /// Create a stream over the tokens of a group, sharing this stream's source database and
+    /// errors. `open` and `close` are the spans of the group's brackets.
+    pub fn child<'b>(&self, buffer: &'b [Token], open: Span, close: Span) -> ParseStream<'b>
+    where
+        'a: 'b,
+    {
+        // Brackets end the expression, so `{` inside can't be the block, as in a new stream
+        ParseStream {
+            errors: self.errors.clone(),
+            end: close,
+            open: Some(open),
+            ..ParseStream::new(buffer, self.source)
+        }
+    }
+
+    /// Create a copy of this stream at the same position, to parse ahead without moving this
+    /// stream. Errors reported to the fork are kept separate until [`ParseStream::advance_to`].
+    pub fn fork(&self) -> ParseStream<'a> {
+        ParseStream {
+            buffer: self.buffer,
+            index: self.index.clone(),
+            source: self.source,
+            errors: Default::default(),
+            tried: self.tried.clone(),
+            end: self.end,
+            open: self.open,
+            struct_literals: self.struct_literals.clone(),
+        }
+    }
+
+    /// Source files the tokens came from
+    pub fn source(&self) -> Option<&'a SourceDatabase> {
+        self.source
+    }
 }
 
 impl ParseStream<'_> {
@@ -80,14 +254,32 @@ impl ParseStream<'_> {
     pub fn peek(&self) -> Result<&Token> {
         self.buffer
             .get(self.index.get())
-            .ok_or(ParseError::UnexpectedEnd {})
+            .ok_or_else(|| self.end_error(Vec::new()))
     }
 
     /// Peek the nth token from the current token
     pub fn peekn(&self, n: usize) -> Result<&Token> {
         self.buffer
             .get(self.index.get() + n)
-            .ok_or(ParseError::UnexpectedEnd {})
+            .ok_or_else(|| self.end_error(Vec::new()))
+    }
+
+    /// Skips doc comments that nothing follows, like at the end of a body, since they don't
+    /// document anything. Returns true if there were any.
+    pub(crate) fn skip_dangling_docs(&self) -> bool {
+        let mut count = 0;
+        while let Ok(Token::DocComment(_)) = self.peekn(count) {
+            count += 1;
+        }
+
+        if count == 0 || self.peekn(count).is_ok() {
+            return false;
+        }
+        for _ in 0..count {
+            self.increment();
+        }
+
+        true
     }
 
     /// Increment the current token without returning it
@@ -109,23 +301,171 @@ impl ParseStream<'_> {
         self.index.set(value + 1);
         token.clone()
     }
+
+    /// Record an error that parsing recovered from
+    pub fn report(&self, error: ParseError) {
+        self.errors.borrow_mut().push(error);
+    }
+
+    /// Take every error recorded by this stream and its children so far
+    pub fn take_errors(&self) -> Vec<ParseError> {
+        self.errors.take()
+    }
+
+    /// Index of the current token
+    pub(crate) fn position(&self) -> usize {
+        self.index.get()
+    }
+
+    /// Save the current position, to go back to it with [`ParseStream::rewind`]
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            index: self.index.get(),
+            errors: self.errors.borrow().len(),
+        }
+    }
+
+    /// Go back to a saved position. Errors reported since the checkpoint are dropped.
+    pub fn rewind(&self, checkpoint: Checkpoint) {
+        self.index.set(checkpoint.index);
+        self.errors.borrow_mut().truncate(checkpoint.errors);
+    }
+
+    /// Move this stream to the position of `fork`, keeping the errors reported to it
+    pub fn advance_to(&self, fork: &ParseStream<'_>) {
+        self.index.set(fork.index.get());
+        self.errors.borrow_mut().extend(fork.take_errors());
+        self.tried.replace(fork.tried.take());
+    }
+
+    /// Parse `T` without struct literals, for an expression followed by a block
+    ///
+    /// Otherwise `if x { ... }` would parse as `x` being a struct literal.
+    pub fn parse_before_block<T: Parse>(&self) -> Result<T> {
+        let old = self.struct_literals.replace(false);
+        let result = self.parse();
+        self.struct_literals.set(old);
+        result
+    }
+
+    /// Whether `Name { ... }` is a struct literal here. See [`ParseStream::parse_before_block`].
+    pub fn allows_struct_literals(&self) -> bool {
+        self.struct_literals.get()
+    }
+
+    /// Try and parse `T`, going back to the current position if it fails
+    ///
+    /// If `T` fails on the current token, what it expected is remembered as an alternative, and
+    /// listed by the next [`ParseStream::unexpected`] error for this token.
+    pub fn try_parse<T: Parse>(&self) -> Option<T> {
+        let checkpoint = self.checkpoint();
+
+        match self.parse() {
+            Ok(value) => Some(value),
+            Err(error) => {
+                if self.index.get() == checkpoint.index {
+                    self.tried(error.expected());
+                }
+
+                self.rewind(checkpoint);
+                None
+            }
+        }
+    }
+
+    /// Remember alternatives that failed on the current token
+    fn tried(&self, expected: &[String]) {
+        let index = self.index.get();
+        let mut tried = self.tried.borrow_mut();
+
+        if tried.0 != index {
+            *tried = (index, Vec::new());
+        }
+
+        for expected in expected {
+            if !tried.1.contains(expected) {
+                tried.1.push(expected.clone());
+            }
+        }
+    }
+
+    /// Error for the current token (or the end of input) when `expected` was expected. Every
+    /// alternative that was already tried on this token is listed as expected too.
+    pub fn unexpected(&self, expected: &str) -> ParseError {
+        let tried = self.tried.borrow();
+
+        let mut candidates = match &*tried {
+            (index, tried) if *index == self.index.get() => tried.clone(),
+            _ => Vec::new(),
+        };
+        if !candidates.iter().any(|c| c == expected) {
+            candidates.push(expected.to_string());
+        }
+
+        match self.buffer.get(self.index.get()) {
+            Some(found) => ParseError::UnexpectedToken {
+                found: Box::new(found.clone()),
+                expected: candidates,
+                labels: Vec::new(),
+            },
+            None => self.end_error(candidates),
+        }
+    }
+
+    /// Error for reaching the end of input. Inside a group, the opening bracket is labeled.
+    fn end_error(&self, expected: Vec<String>) -> ParseError {
+        ParseError::UnexpectedEnd {
+            span: self.end,
+            expected,
+            labels: self
+                .open
+                .map(|open| Label::new(open, "group starts here"))
+                .into_iter()
+                .collect(),
+        }
+    }
+
+    /// Recover from an error in the statement or item that started at token `start`
+    ///
+    /// The stream is moved back to `start`, and tokens are skipped up to and including a `;` or a
+    /// `{...}` group, or up to a token `stop_before` accepts. At least one token is always
+    /// skipped. Returns the span of the skipped tokens.
+    pub(crate) fn recover(&self, start: usize, stop_before: impl Fn(&Token) -> bool) -> Span {
+        self.index.set(start);
+
+        let mut span: Option<Span> = None;
+        while let Ok(token) = self.peek() {
+            if span.is_some() && stop_before(token) {
+                break;
+            }
+
+            let token_span = token.as_span();
+            span = Some(span.map_or(token_span, |span| span.join(token_span)));
+            self.increment();
+
+            if let Token::Semi(_)
+            | Token::Group(parsely_lexer::tokens::Group {
+                bracket: parsely_lexer::tokens::GroupBracket::Brace,
+                ..
+            }) = token
+            {
+                break;
+            }
+        }
+
+        span.unwrap_or_default()
+    }
 }
 
 impl<'a> From<&'a [Token]> for ParseStream<'a> {
     fn from(value: &'a [Token]) -> Self {
-        Self {
-            buffer: value,
-            index: Cell::new(0),
-        }
+        ParseStream::new(value, None)
     }
 }
 
 impl<'a> From<&'a Vec<Token>> for ParseStream<'a> {
     fn from(value: &'a Vec<Token>) -> Self {
-        Self {
-            buffer: value,
-            index: Cell::new(0),
-        }
+        ParseStream::new(value, None)
     }
 }
 
@@ -134,7 +474,7 @@ where
     T: Parse,
 {
     fn parse(stream: &'_ ParseStream<'_>) -> Result<Self> {
-        Ok(stream.parse().ok())
+        Ok(stream.try_parse())
     }
 }
 
@@ -160,11 +500,8 @@ where
     }
 }
 
-pub fn parse_from_vec(vec: &Vec<Token>) -> ParseStream<'_> {
-    ParseStream {
-        buffer: &vec,
-        index: Cell::new(0),
-    }
+pub fn parse_from_vec(vec: &[Token]) -> ParseStream<'_> {
+    ParseStream::new(vec, None)
 }
 
 #[derive(Debug, Clone)]
@@ -175,26 +512,24 @@ pub struct Parens<T: Parse> {
 
 impl<T: Parse> Parse for Parens<T> {
     fn parse(stream: &'_ ParseStream<'_>) -> Result<Self> {
-        match stream.next() {
-            Token::Group(parsely_lexer::tokens::Group {
+        match stream.peek() {
+            Ok(Token::Group(parsely_lexer::tokens::Group {
                 bracket: parsely_lexer::tokens::GroupBracket::Paren,
                 tokens,
                 open,
                 close,
                 ..
-            }) => {
-                let stream = crate::parse_from_vec(&tokens);
+            })) => {
+                stream.increment();
+                let stream = stream.child(tokens, *open, *close);
                 stream.parse().map(|value| Parens {
                     parens: parsely_lexer::tokens::Paren {
-                        span: open.join(close),
+                        span: open.join(*close),
                     },
                     value: Box::new(value),
                 })
             }
-            found => Err(ParseError::UnexpectedToken {
-                found,
-                expected: "Parenthesis".to_string(),
-            }),
+            _ => Err(stream.unexpected("Parenthesis")),
         }
     }
 }
@@ -210,52 +545,48 @@ impl<T: Parse> Braces<T> {
         stream: &'_ ParseStream<'_>,
         f: impl Fn(&'_ ParseStream<'_>) -> Result<T>,
     ) -> Result<Self> {
-        match stream.next() {
-            Token::Group(parsely_lexer::tokens::Group {
+        match stream.peek() {
+            Ok(Token::Group(parsely_lexer::tokens::Group {
                 bracket: parsely_lexer::tokens::GroupBracket::Brace,
                 tokens,
                 open,
                 close,
                 ..
-            }) => {
-                let stream = crate::parse_from_vec(&tokens);
+            })) => {
+                stream.increment();
+                let stream = stream.child(tokens, *open, *close);
                 f(&stream).map(|value| Braces {
                     parens: parsely_lexer::tokens::Brace {
-                        span: open.join(close),
+                        span: open.join(*close),
                     },
                     value: Box::new(value),
                 })
             }
-            found => Err(ParseError::UnexpectedToken {
-                found,
-                expected: "Braces".to_string(),
-            }),
+            _ => Err(stream.unexpected("Braces")),
         }
     }
 }
 
 impl<T: Parse> Parse for Braces<T> {
     fn parse(stream: &'_ ParseStream<'_>) -> Result<Self> {
-        match stream.next() {
-            Token::Group(parsely_lexer::tokens::Group {
+        match stream.peek() {
+            Ok(Token::Group(parsely_lexer::tokens::Group {
                 bracket: parsely_lexer::tokens::GroupBracket::Brace,
                 tokens,
                 open,
                 close,
                 ..
-            }) => {
-                let stream = crate::parse_from_vec(&tokens);
+            })) => {
+                stream.increment();
+                let stream = stream.child(tokens, *open, *close);
                 stream.parse().map(|value| Braces {
                     parens: parsely_lexer::tokens::Brace {
-                        span: open.join(close),
+                        span: open.join(*close),
                     },
                     value: Box::new(value),
                 })
             }
-            found => Err(ParseError::UnexpectedToken {
-                found,
-                expected: "Braces".to_string(),
-            }),
+            _ => Err(stream.unexpected("Braces")),
         }
     }
 }
@@ -268,26 +599,24 @@ pub struct Brackets<T> {
 
 impl<T: Parse> Parse for Brackets<T> {
     fn parse(stream: &'_ ParseStream<'_>) -> Result<Self> {
-        match stream.next() {
-            Token::Group(parsely_lexer::tokens::Group {
+        match stream.peek() {
+            Ok(Token::Group(parsely_lexer::tokens::Group {
                 bracket: parsely_lexer::tokens::GroupBracket::Bracket,
                 tokens,
                 open,
                 close,
                 ..
-            }) => {
-                let stream = crate::parse_from_vec(&tokens);
+            })) => {
+                stream.increment();
+                let stream = stream.child(tokens, *open, *close);
                 stream.parse().map(|value| Brackets {
                     parens: parsely_lexer::tokens::Bracket {
-                        span: open.join(close),
+                        span: open.join(*close),
                     },
                     value: Box::new(value),
                 })
             }
-            found => Err(ParseError::UnexpectedToken {
-                found,
-                expected: "Brackets".to_string(),
-            }),
+            _ => Err(stream.unexpected("Brackets")),
         }
     }
 }
@@ -297,30 +626,46 @@ impl<T> Brackets<T> {
         stream: &'_ ParseStream<'_>,
         f: impl Fn(&'_ ParseStream<'_>) -> Result<T>,
     ) -> Result<Self> {
-        match stream.next() {
-            Token::Group(parsely_lexer::tokens::Group {
+        match stream.peek() {
+            Ok(Token::Group(parsely_lexer::tokens::Group {
                 bracket: parsely_lexer::tokens::GroupBracket::Bracket,
                 tokens,
                 open,
                 close,
                 ..
-            }) => {
-                let stream = crate::parse_from_vec(&tokens);
+            })) => {
+                stream.increment();
+                let stream = stream.child(tokens, *open, *close);
                 f(&stream).map(|value| Brackets {
                     parens: parsely_lexer::tokens::Bracket {
-                        span: open.join(close),
+                        span: open.join(*close),
                     },
                     value: Box::new(value),
                 })
             }
-            found => Err(ParseError::UnexpectedToken {
-                found,
-                expected: "Brackets".to_string(),
-            }),
+            _ => Err(stream.unexpected("Brackets")),
         }
     }
 }
 
+impl<T: Parse> AsSpan for Parens<T> {
+    fn as_span(&self) -> Span {
+        self.parens.span
+    }
+}
+
+impl<T: Parse> AsSpan for Braces<T> {
+    fn as_span(&self) -> Span {
+        self.parens.span
+    }
+}
+
+impl<T> AsSpan for Brackets<T> {
+    fn as_span(&self) -> Span {
+        self.parens.span
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Punctuation<T: Parse, P: Parse> {
     items: Vec<(T, P)>,
@@ -336,6 +681,10 @@ where
         self.items.len() + if self.last.is_some() { 1 } else { 0 }
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
     pub fn iter(&self) -> impl Iterator<Item = &T> {
         self.items
             .iter()
@@ -343,13 +692,6 @@ where
             .chain(self.last.as_ref().map(|last| last.as_ref()))
     }
 
-    pub fn into_iter(self) -> impl Iterator<Item = T> {
-        self.items
-            .into_iter()
-            .map(|item| item.0)
-            .chain(self.last.map(|last| *last))
-    }
-
     pub fn iter_punct(&self) -> impl Iterator<Item = &P> {
         self.items.iter().map(|item| &item.1)
     }
@@ -375,7 +717,7 @@ where
     pub fn parse_terminated(stream: &'_ ParseStream<'_>) -> Result<Self> {
         let mut items = Vec::new();
 
-        while stream.has_next() {
+        while stream.has_next() && !stream.skip_dangling_docs() {
             let item = stream.parse()?;
             let punct = stream.parse()?;
             items.push((item, punct));
@@ -385,6 +727,22 @@ where
     }
 }
 
+impl<T: Parse, P: Parse> IntoIterator for Punctuation<T, P> {
+    type Item = T;
+    type IntoIter = std::iter::Chain<
+        std::iter::Map<std::vec::IntoIter<(T, P)>, fn((T, P)) -> T>,
+        std::option::IntoIter<T>,
+    >;
+
+    fn into_iter(self) -> Self::IntoIter {
+        let items: fn((T, P)) -> T = |item| item.0;
+        self.items
+            .into_iter()
+            .map(items)
+            .chain(self.last.map(|last| *last))
+    }
+}
+
 impl<T, P> Parse for Punctuation<T, P>
 where
     T: Parse,
@@ -393,14 +751,13 @@ where
     fn parse(stream: &'_ ParseStream<'_>) -> Result<Self> {
         let mut items = Vec::new();
         let mut last = None;
-        
-        while stream.has_next() {
+
+        while stream.has_next() && !stream.skip_dangling_docs() {
             let item = stream.parse()?;
 
-            let punct = stream.parse();
-            match punct {
-                Ok(punct) => items.push((item, punct)),
-                _ => {
+            match stream.try_parse() {
+                Some(punct) => items.push((item, punct)),
+                None => {
                     last = Some(Box::new(item));
                     break;
                 }
@@ -428,4 +785,65 @@ where
 
 #[cfg(test)]
 mod test {
+    use parsely_lexer::{tokens, Lexer};
+
+    use crate::{item::Struct, statement::VariableInit};
+
+    use super::*;
+
+    #[test]
+    fn test_backtracking() {
+        let (tokens, _) = Lexer::run("= ; x".as_bytes());
+        let stream = ParseStream::from(&tokens);
+
+        // `=` is consumed before the expression fails, but the stream is put back
+        let init: Option<VariableInit> = stream.parse().unwrap();
+        assert!(init.is_none());
+        assert!(matches!(stream.peek(), Ok(tokens::Tok![enum =])));
+
+        let fork = stream.fork();
+        fork.increment();
+        fork.report(fork.unexpected("x"));
+        assert!(matches!(stream.peek(), Ok(tokens::Tok![enum =])));
+        assert!(stream.take_errors().is_empty());
+
+        stream.advance_to(&fork);
+        assert!(matches!(stream.peek(), Ok(tokens::Tok![enum ;])));
+        assert_eq!(stream.take_errors().len(), 1);
+
+        let checkpoint = stream.checkpoint();
+        stream.increment();
+        stream.report(stream.unexpected("x"));
+        stream.rewind(checkpoint);
+        assert!(matches!(stream.peek(), Ok(tokens::Tok![enum ;])));
+        assert!(stream.take_errors().is_empty());
+    }
+
+    #[test]
+    fn test_expected_alternatives() {
+        let (tokens, _) = Lexer::run("export int32 data {}".as_bytes());
+        let stream = ParseStream::from(&tokens);
+
+        let Err(ParseError::UnexpectedToken { expected, .. }) = stream.parse::<Struct>() else {
+            panic!("Expected an unexpected token error!");
+        };
+        assert_eq!(expected, ["opaque", "packed", "struct"]);
+    }
+
+    #[test]
+    fn test_error_spans() {
+        let (tokens, _) = Lexer::run("int32 main() {\n    return 1\n}".as_bytes());
+        let stream = ParseStream::from(&tokens);
+
+        let error = stream.parse::<item::Program>().unwrap_err();
+        assert_eq!(error.to_string(), "Expected `;`, found end of input");
+        assert_eq!(error.as_span(), parsely_lexer::span!(2:0-1 @ 28));
+        assert_eq!(
+            error.labels(),
+            [Label::new(
+                parsely_lexer::span!(0:13-14),
+                "group starts here"
+            )]
+        );
+    }
 }
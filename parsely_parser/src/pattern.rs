@@ -0,0 +1,287 @@
+use parsely_lexer::{
+    tokens::{self, Group, GroupBracket, Token},
+    AsSpan, Span,
+};
+
+use crate::{
+    expression::{Expression, LiteralInt},
+    statement::Statement,
+    Braces, Parens, Parse, ParseStream, Punctuation, Result,
+};
+
+/// `match value { pattern => body, ... }`
+#[derive(Debug, Clone)]
+pub struct Match {
+    pub token: tokens::Tok![match],
+    pub expr: Box<Expression>,
+    pub arms: Braces<Vec<MatchArm>>,
+}
+
+impl Parse for Match {
+    fn parse(stream: &'_ ParseStream<'_>) -> Result<Self> {
+        Ok(Match {
+            token: stream.parse()?,
+            expr: stream.parse_before_block()?,
+            arms: Braces::parse_with(stream, |stream| {
+                let mut arms = Vec::new();
+                while stream.has_next() {
+                    arms.push(stream.parse()?);
+                }
+                Ok(arms)
+            })?,
+        })
+    }
+}
+
+impl AsSpan for Match {
+    fn as_span(&self) -> Span {
+        self.token.as_span().join(self.arms.as_span())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub guard: Option<MatchGuard>,
+    pub arrow: tokens::Tok![=>],
+    pub body: ArmBody,
+    /// Only optional after a block, or after the last arm
+    pub comma: Option<tokens::Tok![,]>,
+}
+
+impl Parse for MatchArm {
+    fn parse(stream: &'_ ParseStream<'_>) -> Result<Self> {
+        let pattern = stream.parse()?;
+
+        let guard = if let Ok(tokens::Tok![enum if]) = stream.peek() {
+            Some(stream.parse()?)
+        } else {
+            None
+        };
+
+        let arrow = stream.parse()?;
+        let body = stream.parse()?;
+
+        let comma = match (&body, stream.peek()) {
+            (_, Ok(tokens::Tok![enum,])) => Some(stream.parse()?),
+            (ArmBody::Expression(_), Ok(_)) => return Err(stream.unexpected(tokens::Comma::NAME)),
+            _ => None,
+        };
+
+        Ok(MatchArm {
+            pattern,
+            guard,
+            arrow,
+            body,
+            comma,
+        })
+    }
+}
+
+/// `if condition` after a pattern. The arm only matches if the condition is true.
+#[derive(Debug, Clone)]
+pub struct MatchGuard {
+    pub token: tokens::Tok![if],
+    pub condition: Box<Expression>,
+}
+
+impl Parse for MatchGuard {
+    fn parse(stream: &'_ ParseStream<'_>) -> Result<Self> {
+        Ok(MatchGuard {
+            token: stream.parse()?,
+            condition: stream.parse()?,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum ArmBody {
+    Block(Braces<Vec<Statement>>),
+    Expression(Box<Expression>),
+}
+
+impl Parse for ArmBody {
+    fn parse(stream: &'_ ParseStream<'_>) -> Result<Self> {
+        match stream.peek()? {
+            Token::Group(Group {
+                bracket: GroupBracket::Brace,
+                ..
+            }) => Ok(ArmBody::Block(Braces::parse_with(
+                stream,
+                Statement::parse_list,
+            )?)),
+            _ => stream.parse().map(ArmBody::Expression),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    /// `_`, matches anything
+    Wildcard(tokens::Ident),
+    /// Matches anything, and binds it to a name
+    Binding(tokens::Ident),
+    Int(PatternInt),
+    Bool(tokens::Bool),
+    Char(tokens::Char),
+    /// `start..end`, excluding `end`
+    Range(PatternRange),
+    /// `Enum::Variant` or `Enum::Variant(patterns...)`
+    Variant(PatternVariant),
+    /// `Struct { field, field: pattern }`. Fields that aren't listed match anything.
+    Struct(PatternStruct),
+}
+
+impl Parse for Pattern {
+    fn parse(stream: &'_ ParseStream<'_>) -> Result<Self> {
+        match (stream.peek()?, stream.peekn(1)) {
+            (Token::Ident(_), Ok(tokens::Tok![enum ::])) => stream.parse().map(Pattern::Variant),
+            (
+                Token::Ident(_),
+                Ok(Token::Group(Group {
+                    bracket: GroupBracket::Brace,
+                    ..
+                })),
+            ) => stream.parse().map(Pattern::Struct),
+            (Token::Ident(ident), _) if ident.value == "_" => {
+                Ok(Pattern::Wildcard(stream.next_ref(ident)))
+            }
+            (Token::Ident(ident), _) => Ok(Pattern::Binding(stream.next_ref(ident))),
+            (Token::Int(_), _) | (tokens::Tok![enum -], Ok(Token::Int(_))) => {
+                let start = stream.parse()?;
+                match stream.peek() {
+                    Ok(tokens::Tok![enum ..]) => Ok(Pattern::Range(PatternRange {
+                        start,
+                        token: stream.parse()?,
+                        end: stream.parse()?,
+                    })),
+                    _ => Ok(Pattern::Int(start)),
+                }
+            }
+            (Token::Bool(b), _) => Ok(Pattern::Bool(stream.next_ref(b))),
+            (Token::Char(c), _) => Ok(Pattern::Char(stream.next_ref(c))),
+            _ => Err(stream.unexpected("Pattern")),
+        }
+    }
+}
+
+impl AsSpan for Pattern {
+    fn as_span(&self) -> Span {
+        match self {
+            Pattern::Wildcard(i) | Pattern::Binding(i) => i.as_span(),
+            Pattern::Int(i) => i.as_span(),
+            Pattern::Bool(b) => b.as_span(),
+            Pattern::Char(c) => c.as_span(),
+            Pattern::Range(r) => r.start.as_span().join(r.end.as_span()),
+            Pattern::Variant(v) => match &v.fields {
+                Some(fields) => v.ty.as_span().join(fields.as_span()),
+                None => v.ty.as_span().join(v.variant.as_span()),
+            },
+            Pattern::Struct(s) => s.ty.as_span().join(s.fields.as_span()),
+        }
+    }
+}
+
+/// An integer with an optional `-` in front of it
+#[derive(Debug, Clone)]
+pub struct PatternInt {
+    pub minus: Option<tokens::Tok![-]>,
+    pub int: LiteralInt,
+}
+
+impl PatternInt {
+    pub fn value(&self) -> i128 {
+        let value = self.int.value.value as i128;
+        match self.minus {
+            Some(_) => -value,
+            None => value,
+        }
+    }
+}
+
+impl Parse for PatternInt {
+    fn parse(stream: &'_ ParseStream<'_>) -> Result<Self> {
+        Ok(PatternInt {
+            minus: stream.parse()?,
+            int: stream.parse()?,
+        })
+    }
+}
+
+impl AsSpan for PatternInt {
+    fn as_span(&self) -> Span {
+        match &self.minus {
+            Some(minus) => minus.as_span().join(self.int.value.as_span()),
+            None => self.int.value.as_span(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PatternRange {
+    pub start: PatternInt,
+    pub token: tokens::Tok![..],
+    pub end: PatternInt,
+}
+
+#[derive(Debug, Clone)]
+pub struct PatternVariant {
+    pub ty: tokens::Ident,
+    pub sep: tokens::Tok![::],
+    pub variant: tokens::Ident,
+    /// Patterns for the payload of the variant
+    pub fields: Option<Parens<Punctuation<Pattern, tokens::Tok![,]>>>,
+}
+
+impl Parse for PatternVariant {
+    fn parse(stream: &'_ ParseStream<'_>) -> Result<Self> {
+        Ok(PatternVariant {
+            ty: stream.parse()?,
+            sep: stream.parse()?,
+            variant: stream.parse()?,
+            fields: match stream.peek() {
+                Ok(Token::Group(Group {
+                    bracket: GroupBracket::Paren,
+                    ..
+                })) => Some(stream.parse()?),
+                _ => None,
+            },
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PatternStruct {
+    pub ty: tokens::Ident,
+    pub fields: Braces<Punctuation<FieldPattern, tokens::Tok![,]>>,
+}
+
+impl Parse for PatternStruct {
+    fn parse(stream: &'_ ParseStream<'_>) -> Result<Self> {
+        Ok(PatternStruct {
+            ty: stream.parse()?,
+            fields: stream.parse()?,
+        })
+    }
+}
+
+/// `field` binds the field to a variable of the same name, `field: pattern` matches it
+#[derive(Debug, Clone)]
+pub struct FieldPattern {
+    pub ident: tokens::Ident,
+    pub pattern: Option<(tokens::Tok![:], Pattern)>,
+}
+
+impl Parse for FieldPattern {
+    fn parse(stream: &'_ ParseStream<'_>) -> Result<Self> {
+        let ident = stream.parse()?;
+
+        let pattern = if let Ok(tokens::Tok![enum :]) = stream.peek() {
+            Some((stream.parse()?, stream.parse()?))
+        } else {
+            None
+        };
+
+        Ok(FieldPattern { ident, pattern })
+    }
+}
@@ -1,6 +1,14 @@
-use parsely_lexer::tokens::{self, Group, GroupBracket, Token};
+use parsely_lexer::{
+    tokens::{self, Group, GroupBracket, Token},
+    AsSpan, Span,
+};
 
-use crate::{expression::Expression, types::Type, Braces, Brackets, Parse};
+use crate::{
+    expression::{Expression, Range},
+    pattern::Match,
+    types::Type,
+    Braces, Brackets, Parse, ParseStream,
+};
 
 #[derive(Debug, Clone)]
 pub enum Statement {
@@ -8,18 +16,85 @@ pub enum Statement {
     VariableDeclaration(VariableDeclaration),
     IfStatement(IfStatement),
     WhileLoop(WhileLoop),
+    ForLoop(Box<ForLoop>),
     ReturnStatement(ReturnStatement),
+    Break(BreakStatement),
+    Continue(ContinueStatement),
+    /// A match in statement position, which doesn't need a `;`
+    Match(Match),
+    /// A statement that failed to parse. The error is reported to the stream.
+    Error(Span),
+}
+
+impl Statement {
+    /// Parses statements up to the end of the stream, recovering from errors
+    ///
+    /// A statement that fails to parse is reported to the stream and replaced with
+    /// [`Statement::Error`], and parsing continues after the next `;` or `{...}`.
+    pub fn parse_list(stream: &'_ ParseStream<'_>) -> crate::Result<Vec<Statement>> {
+        let mut statements = Vec::new();
+
+        while stream.has_next() {
+            // Doc comments don't document anything inside of a function body, even at its end
+            if let Ok(Token::DocComment(_)) = stream.peek() {
+                stream.increment();
+                continue;
+            }
+
+            let start = stream.position();
+
+            match stream.parse() {
+                Ok(statement) => statements.push(statement),
+                Err(error) => {
+                    stream.report(error);
+                    statements.push(Statement::Error(stream.recover(start, |_| false)));
+                }
+            }
+        }
+
+        Ok(statements)
+    }
+
+    /// Returns true if the statement gives the block it ends a value: an expression without a
+    /// `;`, or an `if` or `match` that may have one
+    pub fn has_value(&self) -> bool {
+        match self {
+            Statement::Expression(expr) => expr.semi.is_none(),
+            Statement::IfStatement(_) | Statement::Match(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Returns true if the next tokens are a type followed by a name
+    fn is_declaration(stream: &'_ ParseStream<'_>) -> bool {
+        let fork = stream.fork();
+        fork.parse::<Type>().is_ok() && matches!(fork.peek(), Ok(Token::Ident(_)))
+    }
 }
 
 impl Parse for Statement {
     fn parse(stream: &'_ crate::ParseStream<'_>) -> crate::Result<Self> {
+        // Doc comments don't document anything inside of a function body
+        while let Ok(Token::DocComment(_)) = stream.peek() {
+            stream.increment();
+        }
+
         match (stream.peek()?, stream.peekn(1)) {
-            (Token::Ident(_), Ok(Token::Ident(_))) => {
+            _ if Statement::is_declaration(stream) => {
                 stream.parse().map(Statement::VariableDeclaration)
             }
             (tokens::Tok![enum if], _) => stream.parse().map(Statement::IfStatement),
             (tokens::Tok![enum while], _) => stream.parse().map(Statement::WhileLoop),
+            (tokens::Tok![enum for], _) => stream.parse().map(Statement::ForLoop),
+            // A label is always followed by `:` and then the loop it names
+            (Token::LoopLabel(_), _) => match stream.peekn(2) {
+                Ok(tokens::Tok![enum for]) => stream.parse().map(Statement::ForLoop),
+                _ => stream.parse().map(Statement::WhileLoop),
+            },
             (tokens::Tok![enum return], _) => stream.parse().map(Statement::ReturnStatement),
+            (tokens::Tok![enum break], _) => stream.parse().map(Statement::Break),
+            (tokens::Tok![enum continue], _) => stream.parse().map(Statement::Continue),
+            (tokens::Tok![enum match], _) => stream.parse().map(Statement::Match),
             _ => stream.parse().map(Statement::Expression),
         }
     }
@@ -28,15 +103,22 @@ impl Parse for Statement {
 #[derive(Debug, Clone)]
 pub struct ExpressionStatement {
     pub expression: Box<Expression>,
-    pub semi: tokens::Tok!(;),
+    /// Missing after the last expression of a block, which is the block's value, and may be
+    /// missing after an expression that ends with a block
+    pub semi: Option<tokens::Tok!(;)>,
 }
 
 impl Parse for ExpressionStatement {
     fn parse(stream: &'_ crate::ParseStream<'_>) -> crate::Result<Self> {
-        Ok(ExpressionStatement {
-            expression: stream.parse()?,
-            semi: stream.parse()?,
-        })
+        let expression: Box<Expression> = stream.parse()?;
+
+        let semi = if stream.has_next() && !expression.ends_with_block() {
+            Some(stream.parse()?)
+        } else {
+            stream.parse()?
+        };
+
+        Ok(ExpressionStatement { expression, semi })
     }
 }
 
@@ -112,21 +194,105 @@ pub struct IfStatement {
     pub token: tokens::Tok![if],
     pub condition: Box<Expression>,
     pub body: Braces<Vec<Statement>>,
+    pub else_branch: Option<ElseBranch>,
+}
+
+impl AsSpan for IfStatement {
+    fn as_span(&self) -> Span {
+        let end = match &self.else_branch {
+            Some(ElseBranch {
+                body: ElseBody::If(stmt),
+                ..
+            }) => stmt.as_span(),
+            Some(ElseBranch {
+                body: ElseBody::Block(body),
+                ..
+            }) => body.as_span(),
+            None => self.body.as_span(),
+        };
+
+        self.token.as_span().join(end)
+    }
 }
 
 impl Parse for IfStatement {
     fn parse(stream: &'_ crate::ParseStream<'_>) -> crate::Result<Self> {
+        let token = stream.parse()?;
+        let condition = stream.parse_before_block()?;
+        let body = Braces::parse_with(stream, Statement::parse_list)?;
+
+        let else_branch = if let Ok(tokens::Tok![enum else]) = stream.peek() {
+            Some(stream.parse()?)
+        } else {
+            None
+        };
+
         Ok(IfStatement {
-            token: stream.parse()?,
-            condition: stream.parse()?,
-            body: stream.parse()?,
+            token,
+            condition,
+            body,
+            else_branch,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ElseBranch {
+    pub token: tokens::Tok![else],
+    pub body: ElseBody,
+}
+
+/// What follows an `else`: either another if statement, or a block
+#[derive(Debug, Clone)]
+pub enum ElseBody {
+    If(Box<IfStatement>),
+    Block(Braces<Vec<Statement>>),
+}
+
+impl Parse for ElseBranch {
+    fn parse(stream: &'_ crate::ParseStream<'_>) -> crate::Result<Self> {
+        let token = stream.parse()?;
+
+        let body = match stream.peek() {
+            Ok(tokens::Tok![enum if]) => ElseBody::If(Box::new(stream.parse()?)),
+            _ => ElseBody::Block(Braces::parse_with(stream, Statement::parse_list)?),
+        };
+
+        Ok(ElseBranch { token, body })
+    }
+}
+
+/// `'name:` before a loop, so `break` and `continue` in nested loops can refer to it
+#[derive(Debug, Clone)]
+pub struct Label {
+    pub name: tokens::LoopLabel,
+    pub colon: tokens::Tok![:],
+}
+
+impl Label {
+    /// Parses a label if the next token is one
+    fn parse_optional(stream: &'_ ParseStream<'_>) -> crate::Result<Option<Label>> {
+        if let Ok(Token::LoopLabel(_)) = stream.peek() {
+            Ok(Some(stream.parse()?))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl Parse for Label {
+    fn parse(stream: &'_ crate::ParseStream<'_>) -> crate::Result<Self> {
+        Ok(Label {
+            name: stream.parse()?,
+            colon: stream.parse()?,
         })
     }
 }
 
 #[derive(Debug, Clone)]
 pub struct WhileLoop {
-    pub token: tokens::Tok![if],
+    pub label: Option<Label>,
+    pub token: tokens::Tok![while],
     pub condition: Box<Expression>,
     pub body: Braces<Vec<Statement>>,
 }
@@ -134,9 +300,59 @@ pub struct WhileLoop {
 impl Parse for WhileLoop {
     fn parse(stream: &'_ crate::ParseStream<'_>) -> crate::Result<Self> {
         Ok(WhileLoop {
+            label: Label::parse_optional(stream)?,
             token: stream.parse()?,
-            condition: stream.parse()?,
-            body: stream.parse()?,
+            condition: stream.parse_before_block()?,
+            body: Braces::parse_with(stream, Statement::parse_list)?,
+        })
+    }
+}
+
+/// `for i in 0..n { ... }` or `for x in slice { ... }`
+#[derive(Debug, Clone)]
+pub struct ForLoop {
+    pub label: Option<Label>,
+    pub token: tokens::Tok![for],
+    pub ident: tokens::Ident,
+    pub in_token: tokens::Tok![in],
+    pub iter: ForIter,
+    pub body: Braces<Vec<Statement>>,
+}
+
+/// What a for loop goes over
+#[derive(Debug, Clone)]
+pub enum ForIter {
+    /// Integers from `left` up to, but not including, `right`. Both ends are always present.
+    Range(Range),
+    /// Each element of a slice or array
+    Slice(Box<Expression>),
+}
+
+impl Parse for ForLoop {
+    fn parse(stream: &'_ crate::ParseStream<'_>) -> crate::Result<Self> {
+        let label = Label::parse_optional(stream)?;
+        let token = stream.parse()?;
+        let ident = stream.parse()?;
+        let in_token = stream.parse()?;
+
+        let expr = stream.parse_before_block()?;
+        let iter = if let Ok(tokens::Tok![enum ..]) = stream.peek() {
+            ForIter::Range(Range {
+                left: Some(expr),
+                token: stream.parse()?,
+                right: Some(stream.parse_before_block()?),
+            })
+        } else {
+            ForIter::Slice(expr)
+        };
+
+        Ok(ForLoop {
+            label,
+            token,
+            ident,
+            in_token,
+            iter,
+            body: Braces::parse_with(stream, Statement::parse_list)?,
         })
     }
 }
@@ -157,3 +373,37 @@ impl Parse for ReturnStatement {
         })
     }
 }
+
+#[derive(Debug, Clone)]
+pub struct BreakStatement {
+    pub token: tokens::Tok![break],
+    pub label: Option<tokens::LoopLabel>,
+    pub semi: tokens::Tok![;],
+}
+
+impl Parse for BreakStatement {
+    fn parse(stream: &'_ crate::ParseStream<'_>) -> crate::Result<Self> {
+        Ok(BreakStatement {
+            token: stream.parse()?,
+            label: stream.parse()?,
+            semi: stream.parse()?,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ContinueStatement {
+    pub token: tokens::Tok![continue],
+    pub label: Option<tokens::LoopLabel>,
+    pub semi: tokens::Tok![;],
+}
+
+impl Parse for ContinueStatement {
+    fn parse(stream: &'_ crate::ParseStream<'_>) -> crate::Result<Self> {
+        Ok(ContinueStatement {
+            token: stream.parse()?,
+            label: stream.parse()?,
+            semi: stream.parse()?,
+        })
+    }
+}
@@ -1,18 +1,15 @@
-use parsely_lexer::tokens::{self, Token, Group};
+use parsely_lexer::tokens::{self, Token};
 
-use crate::{Parse, ParseError, Result};
+use crate::{Parse, Result};
 
 macro_rules! impl_token_parse {
     ($($struct_name:ident),*, $(,)*) => {
         $(
             impl Parse for tokens::$struct_name {
                 fn parse(stream: &'_ crate::ParseStream<'_>) -> Result<Self> {
-                    match stream.peek()? {
-                        Token::$struct_name(a) => Ok(stream.next_ref(a)),
-                        tok => Err(ParseError::UnexpectedToken {
-                            found: tok.clone(),
-                            expected: tokens::$struct_name::NAME.to_string(),
-                        }),
+                    match stream.peek() {
+                        Ok(Token::$struct_name(a)) => Ok(stream.next_ref(a)),
+                        _ => Err(stream.unexpected(tokens::$struct_name::NAME)),
                     }
                 }
             }
@@ -31,6 +28,7 @@ impl_token_parse! {
     External,
     For,
     If,
+    In,
     Match,
     Nones,
     Opaque,
@@ -46,14 +44,18 @@ impl_token_parse! {
     // Punctuation
     Semi,
     Colon,
+    PathSep,
     Comma,
     Pound,
+    At,
+    Question,
 
     // Operators
     And,
     AndEq,
     Assign,
     Eq,
+    FatArrow,
     Dot,
     Gt,
     GtEq,
@@ -79,6 +81,7 @@ impl_token_parse! {
     SlashEq,
     Star,
     StarEq,
+    Tilde,
     Xor,
     XorEq,
 
@@ -87,14 +90,8 @@ impl_token_parse! {
     Float,
     Bool,
     String,
+    ByteString,
     Char,
+    LoopLabel,
+    DocComment,
 }
-
-pub trait ParseGrouped {
-
-}
-
-impl ParseGrouped for Group {
-
-}
-
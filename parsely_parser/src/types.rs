@@ -1,6 +1,9 @@
-use parsely_lexer::tokens::{self, Group, GroupBracket, Token};
+use parsely_lexer::{
+    tokens::{self, Group, GroupBracket, Token},
+    AsSpan,
+};
 
-use crate::{statement::ArrayDimension, Parse, ParseError};
+use crate::{statement::ArrayDimension, Brackets, Parse, ParseError, Punctuation};
 
 #[derive(Debug, Clone)]
 pub enum Type {
@@ -11,34 +14,81 @@ pub enum Type {
     Str(tokens::Ident),
     Void(tokens::Void),
     Named(tokens::Ident),
+    Pointer(TypePointer),
+    Optional(TypeOptional),
+    /// `[T, U, ...]`
+    Tuple(Brackets<Punctuation<Type, tokens::Tok![,]>>),
 }
 
 impl Parse for Type {
     fn parse(stream: &'_ crate::ParseStream<'_>) -> crate::Result<Self> {
-        let base = match stream.peek()? {
-            tokens::Tok!(enum void as v) => Ok(Type::Void(stream.next_ref(v))),
-            Token::Ident(ident) if &ident.value[..3] == "int" => stream.parse().map(Type::Int),
-            Token::Ident(ident) if ident.value == "str" => stream.parse().map(Type::Str),
-            Token::Ident(_) => stream.parse().map(Type::Named),
-            found => Err(ParseError::UnexpectedToken {
-                found: found.clone(),
-                expected: "Type".to_string(),
-            }),
+        let base = match stream.peek() {
+            // The pointee includes any suffixes, so `@int32?` points to an optional
+            Ok(tokens::Tok![enum @] | tokens::Tok![enum #]) => {
+                return Ok(Type::Pointer(TypePointer {
+                    token: stream.next(),
+                    pointee: stream.parse()?,
+                }))
+            }
+            Ok(Token::Group(Group {
+                bracket: GroupBracket::Bracket,
+                ..
+            })) => stream.parse().map(Type::Tuple),
+            Ok(tokens::Tok!(enum void as v)) => Ok(Type::Void(stream.next_ref(v))),
+            Ok(Token::Ident(ident))
+                if ident.value.strip_prefix("int").is_some_and(|size| {
+                    !size.is_empty() && size.chars().all(|c| c.is_ascii_digit())
+                }) =>
+            {
+                stream.parse().map(Type::Int)
+            }
+            Ok(Token::Ident(ident)) if ident.value == "str" => stream.parse().map(Type::Str),
+            Ok(Token::Ident(_)) => stream.parse().map(Type::Named),
+            _ => Err(stream.unexpected("Type")),
         };
 
-        base.and_then(|base| match stream.peek()? {
-            Token::Group(Group {
+        let mut ty = base.and_then(|base| match stream.peek() {
+            Ok(Token::Group(Group {
                 bracket: GroupBracket::Bracket,
                 ..
-            }) => Ok(Type::Array(TypeArray {
+            })) => Ok(Type::Array(TypeArray {
                 element: Box::new(base),
                 arrays: stream.parse()?,
             })),
             _ => Ok(base),
-        })
+        })?;
+
+        while let Ok(tokens::Tok![enum ?]) = stream.peek() {
+            ty = Type::Optional(TypeOptional {
+                inner: Box::new(ty),
+                token: stream.parse()?,
+            });
+        }
+
+        Ok(ty)
     }
 }
 
+/// `@T`, or `#T` for a pointer that can be written through
+#[derive(Debug, Clone)]
+pub struct TypePointer {
+    pub token: tokens::Token,
+    pub pointee: Box<Type>,
+}
+
+impl TypePointer {
+    pub fn is_mutable(&self) -> bool {
+        matches!(self.token, tokens::Tok![enum #])
+    }
+}
+
+/// `T?`, either a `T` or nothing
+#[derive(Debug, Clone)]
+pub struct TypeOptional {
+    pub inner: Box<Type>,
+    pub token: tokens::Tok![?],
+}
+
 #[derive(Debug, Clone)]
 pub struct TypeInt {
     pub size: usize,
@@ -47,27 +97,22 @@ pub struct TypeInt {
 
 impl Parse for TypeInt {
     fn parse(stream: &'_ crate::ParseStream<'_>) -> crate::Result<Self> {
-        match stream.peek()? {
-            Token::Ident(tok @ tokens::Ident { value, .. }) => {
-                let (ipart, size_part) = value.split_at(3);
-                if ipart != "int" {
-                    return Err(ParseError::UnexpectedToken {
-                        found: stream.peek()?.clone(),
-                        expected: "int".to_string(),
-                    });
-                }
+        match stream.peek() {
+            Ok(Token::Ident(tok @ tokens::Ident { value, .. })) => {
+                let Some(size_part) = value.strip_prefix("int") else {
+                    return Err(stream.unexpected("int"));
+                };
 
                 Ok(TypeInt {
                     size: size_part.parse().map_err(|_| ParseError::UnexpectedSize {
                         found: size_part.to_string(),
+                        span: tok.as_span(),
+                        labels: Vec::new(),
                     })?,
                     token: stream.next_ref(tok),
                 })
             }
-            found => Err(ParseError::UnexpectedToken {
-                found: found.clone(),
-                expected: "Int Type".to_string(),
-            }),
+            _ => Err(stream.unexpected("Int Type")),
         }
     }
 }